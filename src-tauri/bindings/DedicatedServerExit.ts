@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Emitted whenever a server process exits
+ */
+export type DedicatedServerExit = { name: string, exit_code: number | null, 
+/**
+ * Whether the server exited because it was stopped through FlightCore
+ */
+requested: boolean, will_restart: boolean, };
@@ -0,0 +1,22 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Configuration of a dedicated server
+ */
+export type DedicatedServerInstance = { 
+/**
+ * Unique name identifying the instance
+ */
+name: string, game_path: string, profile: string, 
+/**
+ * Wine prefix to run the server in (Linux only)
+ */
+wine_prefix: string | null, port: number, 
+/**
+ * Extra command line arguments in addition to `-dedicated` and `-port`
+ */
+arguments: Array<string>, 
+/**
+ * Convars passed on the command line, these take precedence over `autoexec_ns_server.cfg`
+ */
+convars: { [key in string]: string }, environment: { [key in string]: string }, restart_on_crash: boolean, };
@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Current state of an instance
+ */
+export type DedicatedServerStatus = { name: string, running: boolean, pid: number | null, 
+/**
+ * RFC 3339 timestamp of the last (re)start
+ */
+started_at: string | null, restart_count: number, 
+/**
+ * Path of the current log file
+ */
+log_path: string | null, };
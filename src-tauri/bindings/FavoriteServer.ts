@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type FavoriteServer = { id: string, name: string, 
+/**
+ * RFC 3339 timestamp
+ */
+added_at: string, };
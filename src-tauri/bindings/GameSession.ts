@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { GameSessionExitStatus } from "./GameSessionExitStatus";
+
+/**
+ * A single run of Northstar
+ */
+export type GameSession = { profile: string, game_path: string | null, 
+/**
+ * RFC 3339 timestamps
+ */
+started_at: string, ended_at: string | null, duration_seconds: bigint, exit_code: number | null, exit_status: GameSessionExitStatus, };
@@ -0,0 +1,19 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { InstallQueueItemState } from "./InstallQueueItemState";
+
+/**
+ * A package install in the queue
+ */
+export type InstallQueueItem = { id: number, thunderstore_mod_string: string, game_path: string, profile: string, state: InstallQueueItemState, downloaded: bigint, 
+/**
+ * `0` while the size is unknown
+ */
+total_size: bigint, 
+/**
+ * Set if the install failed
+ */
+error: string | null, 
+/**
+ * Ids of the queued installs that have to finish first
+ */
+dependencies: Array<number>, };
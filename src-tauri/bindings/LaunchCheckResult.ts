@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { LaunchCheckStatus } from "./LaunchCheckStatus";
+
+/**
+ * Outcome of a single pre-launch check
+ */
+export type LaunchCheckResult = { name: string, status: LaunchCheckStatus, message: string, fix_suggestion: string | null, };
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { LinuxRunnerKind } from "./LinuxRunnerKind";
+
+/**
+ * A Wine or Proton build that can run Titanfall2 on Linux
+ * `path` points to the `proton` script or `wine` binary
+ */
+export type LinuxRunner = { kind: LinuxRunnerKind, name: string, path: string, };
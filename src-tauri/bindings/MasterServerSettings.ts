@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { MasterServer } from "./MasterServer";
+
+/**
+ * Known master servers and which one a profile uses
+ */
+export type MasterServerSettings = { selected_url: string, master_servers: Array<MasterServer>, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A Northstar process spawned by FlightCore
+ */
+export type NorthstarProcess = { pid: number, profile: string, game_path: string, };
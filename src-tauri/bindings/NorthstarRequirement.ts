@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Northstar version a package requires compared to the installed one
+ */
+export type NorthstarRequirement = { thunderstore_mod_string: string, 
+/**
+ * `None` if the package does not depend on Northstar
+ */
+required_version: string | null, 
+/**
+ * `None` if the installed version could not be determined
+ */
+installed_version: string | null, 
+/**
+ * Whether the installed Northstar is recent enough, if not Northstar should be updated first
+ */
+is_satisfied: boolean, };
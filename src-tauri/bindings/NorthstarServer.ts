@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { NorthstarServerModInfo } from "./NorthstarServerModInfo";
+
+/**
+ * A server as listed by the master server
+ */
+export type NorthstarServer = { id: string, name: string, description: string, playerCount: number, maxPlayers: number, map: string, playlist: string, region: string, hasPassword: boolean, modInfo: NorthstarServerModInfo, };
@@ -0,0 +1,51 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Well-known settings of `autoexec_ns_server.cfg`
+ * `None` means the setting is not present in the file and Northstar's default applies
+ */
+export type NorthstarServerConfig = { 
+/**
+ * `ns_server_name`
+ */
+server_name: string | null, 
+/**
+ * `ns_server_desc`
+ */
+server_description: string | null, 
+/**
+ * `ns_server_password`
+ */
+server_password: string | null, 
+/**
+ * Set via the `setplaylist` command
+ */
+playlist: string | null, 
+/**
+ * `ns_report_server_to_masterserver`
+ */
+report_to_master_server: boolean | null, 
+/**
+ * `ns_masterserver_hostname`
+ */
+master_server_hostname: string | null, 
+/**
+ * `ns_auth_allow_insecure`
+ */
+allow_insecure_auth: boolean | null, 
+/**
+ * `ns_should_return_to_lobby`
+ */
+return_to_lobby: boolean | null, 
+/**
+ * `everything_unlocked`
+ */
+everything_unlocked: boolean | null, 
+/**
+ * `hostport`, UDP port the game is hosted on
+ */
+host_port: number | null, 
+/**
+ * `ns_player_auth_port`, TCP port used for authenticating players
+ */
+player_auth_port: number | null, };
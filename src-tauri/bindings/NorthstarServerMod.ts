@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Mod a server runs as reported to the master server
+ */
+export type NorthstarServerMod = { Name: string, Version: string, 
+/**
+ * Whether clients need the mod installed to join
+ */
+RequiredOnClient: boolean, };
@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { PackageAuditIssue } from "./PackageAuditIssue";
+
+/**
+ * An installed package that should be looked at
+ */
+export type PackageAuditFinding = { thunderstore_mod_string: string, 
+/**
+ * Mods the package installed
+ */
+mod_names: Array<string>, issue: PackageAuditIssue, reason: string, replacement: string | null, };
@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A package that should not be installed
+ */
+export type PackageBlocklistEntry = { 
+/**
+ * `author-name` of the package
+ */
+package: string, 
+/**
+ * Affected versions, all versions if empty
+ */
+versions: Array<string>, reason: string, 
+/**
+ * `author-name` of a package to use instead
+ */
+replacement: string | null, };
@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Changelog entries between the installed and the latest version
+ */
+export type PackageChangesSinceInstalled = { installed_version: string, latest_version: string, 
+/**
+ * Versions the changelog has entries for, newest first
+ */
+versions: Array<string>, 
+/**
+ * The matching changelog sections concatenated and rendered from markdown to sanitized HTML,
+ * `None` if the package has no changelog
+ */
+changelog_html: string | null, };
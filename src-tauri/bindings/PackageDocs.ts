@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { PackageDocsSource } from "./PackageDocsSource";
+
+/**
+ * README and changelog of a package version
+ */
+export type PackageDocs = { thunderstore_mod_string: string, 
+/**
+ * README rendered from markdown to sanitized HTML, `None` if the package has no README
+ */
+readme_html: string | null, 
+/**
+ * Changelog rendered from markdown to sanitized HTML, `None` if the package has no changelog
+ */
+changelog_html: string | null, source: PackageDocsSource, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A mod change to apply on the next relaunch
+ */
+export type PendingModOperation = { "SetEnabled": { mod_name: string, mod_version: string, is_enabled: boolean, } } | { "Install": { thunderstore_mod_string: string, } };
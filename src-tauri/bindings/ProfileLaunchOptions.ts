@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Custom options a profile is launched with
+ * - extra command line arguments such as `-novid` or `-multiple`
+ * - convars passed as `+name value`
+ * - environment variables (only applied when FlightCore spawns the game itself, e.g. via Proton)
+ */
+export type ProfileLaunchOptions = { arguments: Array<string>, convars: { [key in string]: string }, environment: { [key in string]: string }, };
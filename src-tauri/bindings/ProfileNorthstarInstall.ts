@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Northstar package and version a profile owns
+ */
+export type ProfileNorthstarInstall = { package_name: string, version: string, };
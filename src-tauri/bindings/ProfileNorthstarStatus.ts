@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Northstar install state of a single profile
+ */
+export type ProfileNorthstarStatus = { profile: string, package_name: string, installed_version: string | null, latest_version: string | null, is_outdated: boolean, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Accumulated playtime of a profile
+ */
+export type ProfilePlaytime = { profile: string, game_path: string | null, total_playtime_seconds: bigint, session_count: number, last_played: string, };
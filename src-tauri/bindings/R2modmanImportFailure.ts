@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A package from the r2modman profile that could not be installed
+ */
+export type R2modmanImportFailure = { thunderstore_mod_string: string, reason: string, };
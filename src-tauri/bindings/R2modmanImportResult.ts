@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { R2modmanImportFailure } from "./R2modmanImportFailure";
+
+/**
+ * Outcome of importing an r2modman profile
+ */
+export type R2modmanImportResult = { profile: string, installed_packages: Array<string>, failed_packages: Array<R2modmanImportFailure>, };
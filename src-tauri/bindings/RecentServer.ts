@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type RecentServer = { id: string, 
+/**
+ * Empty until the server was seen in the server list
+ */
+name: string, profile: string, 
+/**
+ * RFC 3339 timestamp of the end of the session the server was joined in
+ */
+last_played: string, };
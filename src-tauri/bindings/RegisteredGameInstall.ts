@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { InstallType } from "./InstallType";
+
+/**
+ * A Titanfall2 install known to FlightCore
+ */
+export type RegisteredGameInstall = { label: string, game_path: string, profile: string, install_type: InstallType, wine_prefix: string | null, user_added: boolean, is_valid: boolean, };
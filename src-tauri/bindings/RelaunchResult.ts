@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Outcome of relaunching Northstar
+ */
+export type RelaunchResult = { 
+/**
+ * Result of launching the game again
+ */
+message: string, 
+/**
+ * Errors of queued mod changes that failed, these stay queued
+ */
+failed_mod_operations: Array<string>, };
@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { NorthstarServer } from "./NorthstarServer";
+
+/**
+ * A favorite or recent server together with its current entry in the server list
+ */
+export type SavedServerStatus = { id: string, name: string, is_favorite: boolean, last_played: string | null, 
+/**
+ * `None` if the server is currently not listed
+ */
+live: NorthstarServer | null, };
@@ -0,0 +1,20 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ServerSortField } from "./ServerSortField";
+
+/**
+ * Filters and sorting applied to the server list
+ * Text filters are case-insensitive, unset fields do not filter
+ */
+export type ServerListFilter = { 
+/**
+ * Matched against name and description
+ */
+search: string | null, region: string | null, map: string | null, playlist: string | null, hide_full: boolean, hide_empty: boolean, hide_password_protected: boolean, 
+/**
+ * Only show servers that can be joined without installing additional mods
+ */
+hide_with_required_mods: boolean, 
+/**
+ * Only show servers requiring the given mod
+ */
+required_mod: string | null, sort_by: ServerSortField, sort_descending: boolean, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A required mod that could not be provided
+ */
+export type ServerModInstallFailure = { mod_name: string, version: string, reason: string, };
@@ -0,0 +1,19 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ServerModInstallFailure } from "./ServerModInstallFailure";
+
+/**
+ * Outcome of installing the required mods of a server
+ */
+export type ServerModsInstallResult = { 
+/**
+ * Profile the mods were installed into
+ */
+profile: string, 
+/**
+ * Thunderstore mod strings of newly installed packages
+ */
+installed_packages: Array<string>, 
+/**
+ * Names of already installed mods that were enabled
+ */
+enabled_mods: Array<string>, failed_mods: Array<ServerModInstallFailure>, };
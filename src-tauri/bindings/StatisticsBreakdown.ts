@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Players and servers of a single playlist or region
+ */
+export type StatisticsBreakdown = { player_count: number, server_count: number, };
@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Aggregated samples of one time slot
+ */
+export type StatisticsBucket = { 
+/**
+ * RFC 3339 timestamp of the start of the time slot
+ */
+start: string, sample_count: number, average_player_count: number, peak_player_count: number, average_server_count: number, 
+/**
+ * Average player count per playlist
+ */
+playlists: { [key in string]: number }, 
+/**
+ * Average player count per region
+ */
+regions: { [key in string]: number }, };
@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { StatisticsBucket } from "./StatisticsBucket";
+import type { StatisticsHistoryRange } from "./StatisticsHistoryRange";
+
+export type StatisticsHistory = { range: StatisticsHistoryRange, 
+/**
+ * Oldest first, time slots without samples are left out
+ */
+buckets: Array<StatisticsBucket>, };
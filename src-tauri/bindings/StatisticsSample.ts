@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { StatisticsBreakdown } from "./StatisticsBreakdown";
+
+/**
+ * Player and server counts at a single point in time
+ */
+export type StatisticsSample = { 
+/**
+ * RFC 3339 timestamp
+ */
+timestamp: string, 
+/**
+ * Master server the server list was fetched from
+ */
+master_server_url: string, player_count: number, server_count: number, playlists: { [key in string]: StatisticsBreakdown }, regions: { [key in string]: StatisticsBreakdown }, };
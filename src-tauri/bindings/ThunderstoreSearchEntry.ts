@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ThunderstoreMod } from "./ThunderstoreMod";
+
+/**
+ * A package as returned by a search, only containing its latest version
+ */
+export type ThunderstoreSearchEntry = { package: ThunderstoreMod, 
+/**
+ * Downloads summed up over all versions
+ */
+total_downloads: bigint, 
+/**
+ * Installed version if the package is installed in the given install
+ */
+installed_version: string | null, has_update: boolean, };
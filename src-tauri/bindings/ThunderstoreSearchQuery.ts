@@ -0,0 +1,28 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ThunderstoreSortField } from "./ThunderstoreSortField";
+
+/**
+ * Filters, sorting and page of a package search
+ * Unset fields do not filter
+ */
+export type ThunderstoreSearchQuery = { 
+/**
+ * Fuzzy matched against name and owner, substring matched against the description
+ */
+search: string | null, 
+/**
+ * Packages need to have all of these categories
+ */
+categories: Array<string>, author: string | null, include_deprecated: boolean, include_nsfw: boolean, 
+/**
+ * Only packages installed in the given install
+ */
+only_installed: boolean, 
+/**
+ * Only installed packages with a newer version available
+ */
+only_with_update: boolean, sort_by: ThunderstoreSortField, sort_descending: boolean, 
+/**
+ * Zero-based page index
+ */
+page: number, page_size: number | null, };
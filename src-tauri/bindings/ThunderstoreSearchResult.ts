@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ThunderstoreSearchEntry } from "./ThunderstoreSearchEntry";
+
+export type ThunderstoreSearchResult = { entries: Array<ThunderstoreSearchEntry>, 
+/**
+ * Number of packages matching the query over all pages
+ */
+total: number, page: number, page_size: number, };
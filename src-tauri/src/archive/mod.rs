@@ -0,0 +1,257 @@
+//! Extracting zip archives from untrusted sources
+//!
+//! Mods, pull request builds and Northstar releases are all downloaded zip files. Before anything
+//! is written every entry is checked to stay inside the target directory, symlinks are rejected
+//! and the number of entries and the uncompressed size are limited to catch zip bombs.
+use anyhow::{anyhow, Context, Result};
+use std::io::{Read, Seek};
+use std::path::{Component, Path, PathBuf};
+use zip::ZipArchive;
+
+/// Upper bounds for what an archive may unpack to
+#[derive(Debug, Clone)]
+pub struct ExtractLimits {
+    /// Uncompressed size of all extracted files together
+    pub max_total_size: u64,
+    pub max_entries: usize,
+}
+
+impl Default for ExtractLimits {
+    /// Generous enough for large map and skin packs
+    fn default() -> Self {
+        ExtractLimits {
+            max_total_size: 8 * 1024 * 1024 * 1024,
+            max_entries: 50_000,
+        }
+    }
+}
+
+/// Whether the path only consists of plain names, i.e. cannot leave the directory it is joined to
+fn is_contained(path: &Path) -> bool {
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// Root directory shared by all entries, such as `NorthstarMods-main/` in GitHub branch archives
+fn common_root_dir<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Option<PathBuf> {
+    let mut root: Option<PathBuf> = None;
+    for name in archive.file_names() {
+        // A file at the top level means there is no root directory to leave out
+        if Path::new(name).components().count() < 2 && !name.ends_with('/') {
+            return None;
+        }
+        let first = Path::new(name).components().next()?;
+        let Component::Normal(first) = first else {
+            return None;
+        };
+        match &root {
+            Some(root) if root.as_os_str() != first => return None,
+            Some(_) => {}
+            None => root = Some(PathBuf::from(first)),
+        }
+    }
+    root
+}
+
+/// Extracts the entries `map_path` returns a destination for, relative to `target`
+/// Returns the mapped paths of the extracted files
+fn extract_entries<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    target: &Path,
+    limits: &ExtractLimits,
+    map_path: impl Fn(&Path) -> Option<PathBuf>,
+) -> Result<Vec<PathBuf>> {
+    if archive.len() > limits.max_entries {
+        return Err(anyhow!(
+            "Archive has {} entries, more than the allowed {}",
+            archive.len(),
+            limits.max_entries
+        ));
+    }
+
+    // Check all entries before writing anything so that a hostile archive leaves no files behind
+    let mut entries: Vec<(usize, PathBuf, bool)> = vec![];
+    let mut total_size: u64 = 0;
+    for index in 0..archive.len() {
+        let entry = archive
+            .by_index_raw(index)
+            .with_context(|| format!("Unable to read archive entry {index}"))?;
+        let Some(enclosed_name) = entry.enclosed_name() else {
+            return Err(anyhow!(
+                "Archive entry \"{}\" points outside of the archive",
+                entry.name()
+            ));
+        };
+        if entry.is_symlink() {
+            return Err(anyhow!(
+                "Archive entry \"{}\" is a symlink, which is not allowed",
+                entry.name()
+            ));
+        }
+
+        let Some(destination) = map_path(&enclosed_name) else {
+            continue;
+        };
+        if destination.as_os_str().is_empty() {
+            continue;
+        }
+        if !is_contained(&destination) {
+            return Err(anyhow!(
+                "Archive entry \"{}\" points outside of the target directory",
+                entry.name()
+            ));
+        }
+
+        if !entry.is_dir() {
+            total_size = total_size.saturating_add(entry.size());
+        }
+        entries.push((index, destination, entry.is_dir()));
+    }
+    if total_size > limits.max_total_size {
+        return Err(anyhow!(
+            "Archive unpacks to {total_size} bytes, more than the allowed {}",
+            limits.max_total_size
+        ));
+    }
+
+    std::fs::create_dir_all(target)
+        .with_context(|| format!("Unable to create directory {}", target.display()))?;
+    let canonical_target = target
+        .canonicalize()
+        .with_context(|| format!("Unable to resolve {}", target.display()))?;
+
+    let mut extracted = vec![];
+    let mut written: u64 = 0;
+    for (index, destination, is_dir) in entries {
+        let out = target.join(&destination);
+        let directory = if is_dir {
+            out.as_path()
+        } else {
+            out.parent().unwrap_or(target)
+        };
+        std::fs::create_dir_all(directory)
+            .with_context(|| format!("Unable to create directory {}", directory.display()))?;
+        // Symlinks already present in the target could still lead elsewhere
+        if !directory.canonicalize()?.starts_with(&canonical_target) {
+            return Err(anyhow!(
+                "{} leads outside of {}",
+                directory.display(),
+                target.display()
+            ));
+        }
+        if is_dir {
+            continue;
+        }
+
+        let mut entry = archive.by_index(index)?;
+        let mut outfile = std::fs::File::create(&out)
+            .with_context(|| format!("Unable to create file {}", out.display()))?;
+        // Sizes in the archive are not to be trusted, stop writing once the limit is reached
+        let remaining = limits.max_total_size - written;
+        let copied = std::io::copy(&mut (&mut entry).take(remaining + 1), &mut outfile)
+            .with_context(|| format!("Unable to write to file {}", out.display()))?;
+        if copied > remaining {
+            return Err(anyhow!(
+                "Archive unpacks to more than the allowed {} bytes",
+                limits.max_total_size
+            ));
+        }
+        written += copied;
+
+        log::info!("Write file {}", out.display());
+        extracted.push(destination);
+    }
+
+    Ok(extracted)
+}
+
+/// Extracts a zip archive into `target`
+/// `map_path` maps the path of each entry to where it goes relative to `target`, entries it
+/// returns `None` for are skipped
+/// Returns the mapped paths of the extracted files
+pub fn extract<R: Read + Seek>(
+    reader: R,
+    target: &Path,
+    limits: &ExtractLimits,
+    map_path: impl Fn(&Path) -> Option<PathBuf>,
+) -> Result<Vec<PathBuf>> {
+    let mut archive = ZipArchive::new(reader).context("Unable to open zip archive")?;
+    extract_entries(&mut archive, target, limits, map_path)
+}
+
+/// Extracts a zip archive into `target`, leaving out a root directory shared by all entries
+pub fn extract_unwrapped_root_dir<R: Read + Seek>(
+    reader: R,
+    target: &Path,
+    limits: &ExtractLimits,
+) -> Result<Vec<PathBuf>> {
+    let mut archive = ZipArchive::new(reader).context("Unable to open zip archive")?;
+    let root = common_root_dir(&mut archive);
+    extract_entries(&mut archive, target, limits, |path| match &root {
+        Some(root) => path.strip_prefix(root).ok().map(Path::to_path_buf),
+        None => Some(path.to_path_buf()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use zip::write::SimpleFileOptions;
+
+    fn build_archive(entries: &[(&str, &str)]) -> Cursor<Vec<u8>> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in entries {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+        Cursor::new(writer.finish().unwrap().into_inner())
+    }
+
+    #[test]
+    fn test_extract_unwrapped_root_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = build_archive(&[
+            ("NorthstarMods-main/Northstar.Client/mod.json", "{}"),
+            ("NorthstarMods-main/README.md", "readme"),
+        ]);
+
+        let extracted =
+            extract_unwrapped_root_dir(archive, dir.path(), &ExtractLimits::default()).unwrap();
+        assert_eq!(extracted.len(), 2);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("Northstar.Client/mod.json")).unwrap(),
+            "{}"
+        );
+    }
+
+    #[test]
+    fn test_extract_rejects_hostile_archives() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("target");
+        let keep_all = |path: &Path| Some(path.to_path_buf());
+
+        let archive = build_archive(&[("fine.txt", "a"), ("../escape.txt", "b")]);
+        let err = extract(archive, &target, &ExtractLimits::default(), keep_all).unwrap_err();
+        assert!(err.to_string().contains("points outside"));
+        // Nothing is written if any entry is rejected
+        assert!(!target.join("fine.txt").exists());
+        assert!(!dir.path().join("escape.txt").exists());
+
+        let archive = build_archive(&[("a.txt", "0123456789"), ("b.txt", "0123456789")]);
+        let limits = ExtractLimits {
+            max_total_size: 15,
+            max_entries: 10,
+        };
+        assert!(extract(archive, &target, &limits, keep_all).is_err());
+
+        let archive = build_archive(&[("a.txt", ""), ("b.txt", ""), ("c.txt", "")]);
+        let limits = ExtractLimits {
+            max_total_size: 100,
+            max_entries: 2,
+        };
+        assert!(extract(archive, &target, &limits, keep_all).is_err());
+    }
+}
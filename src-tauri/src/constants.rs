@@ -2,6 +2,9 @@
 use const_format::concatcp;
 use std::time::Duration;
 
+/// File FlightCore persists its settings in, shared with the frontend
+pub const FLIGHTCORE_SETTINGS_STORE: &str = "flight-core-settings.json";
+
 /// FlightCore user agent for web requests
 pub const APP_USER_AGENT: &str = concatcp!("FlightCore/", env!("CARGO_PKG_VERSION"));
 
@@ -41,6 +44,10 @@ pub const REFRESH_DELAY: Duration = Duration::from_secs(5 * 60);
 /// Flightcore repo name and org name on GitHub
 pub const FLIGHTCORE_REPO_NAME: &str = "R2NorthstarTools/FlightCore";
 
+/// Blocklist of Thunderstore packages maintained in the FlightCore repo
+pub const PACKAGE_BLOCKLIST_URL: &str =
+    "https://raw.githubusercontent.com/R2NorthstarTools/FlightCore/main/docs/package-blocklist.json";
+
 /// Northstar release repo name and org name on GitHub
 pub const NORTHSTAR_RELEASE_REPO_NAME: &str = "R2Northstar/Northstar";
 
@@ -60,5 +67,11 @@ pub const NORTHSTAR_DLL: &str = "Northstar.dll";
 /// Profile that Northstar defaults to and ships with
 pub const NORTHSTAR_DEFAULT_PROFILE: &str = "R2Northstar";
 
+/// File inside a profile recording which Northstar package and version it owns
+pub const PROFILE_NORTHSTAR_INSTALL_FILE: &str = "flightcore-northstar.json";
+
+/// File inside a profile storing the custom arguments and environment it is launched with
+pub const PROFILE_LAUNCH_OPTIONS_FILE: &str = "flightcore-launch-options.json";
+
 /// enabledmods.json format
 pub const NORTHSTAR_MODS_MANIFEST_VERSION: i64 = 0;
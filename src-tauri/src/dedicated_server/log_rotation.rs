@@ -0,0 +1,107 @@
+//! Size-limited log files for dedicated server output
+//!
+//! Output is written to `server.log`, once that exceeds `MAX_LOG_SIZE` it is moved to
+//! `server.log.1`, older files are shifted up and the oldest is dropped.
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Size at which the current log file is rotated
+const MAX_LOG_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Number of rotated files kept next to the current one
+const MAX_ROTATED_LOGS: u32 = 5;
+
+const LOG_FILE_NAME: &str = "server.log";
+
+pub struct RotatingLog {
+    dir: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+}
+
+impl RotatingLog {
+    /// Opens the log in the given directory, appending to an existing log
+    pub fn open(dir: &Path) -> std::io::Result<Self> {
+        Self::open_with_max_size(dir, MAX_LOG_SIZE)
+    }
+
+    fn open_with_max_size(dir: &Path, max_size: u64) -> std::io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(LOG_FILE_NAME))?;
+        let size = file.metadata()?.len();
+
+        Ok(RotatingLog {
+            dir: dir.to_path_buf(),
+            file,
+            size,
+            max_size,
+        })
+    }
+
+    /// Path of the file currently written to
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(LOG_FILE_NAME)
+    }
+
+    fn rotated_path(&self, index: u32) -> PathBuf {
+        self.dir.join(format!("{LOG_FILE_NAME}.{index}"))
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        let _ = std::fs::remove_file(self.rotated_path(MAX_ROTATED_LOGS));
+        for index in (1..MAX_ROTATED_LOGS).rev() {
+            let rotated_path = self.rotated_path(index);
+            if rotated_path.exists() {
+                std::fs::rename(rotated_path, self.rotated_path(index + 1))?;
+            }
+        }
+        std::fs::rename(self.path(), self.rotated_path(1))?;
+
+        self.file = File::create(self.path())?;
+        self.size = 0;
+        Ok(())
+    }
+
+    /// Appends a timestamped line, rotating the log first if it would grow too large
+    pub fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        let line = format!("[{}] {line}\n", chrono::Local::now().format("%F %T"));
+        if self.size > 0 && self.size + line.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotate_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut log = RotatingLog::open_with_max_size(dir.path(), 100).unwrap();
+
+        for index in 0..20 {
+            log.write_line(&format!("line {index}")).unwrap();
+        }
+
+        let current = std::fs::read_to_string(log.path()).unwrap();
+        assert!(current.ends_with("line 19\n"));
+        assert!(current.len() <= 100);
+        assert!(log.rotated_path(MAX_ROTATED_LOGS).exists());
+        assert!(!log.rotated_path(MAX_ROTATED_LOGS + 1).exists());
+
+        // Reopening continues the existing file
+        let size = current.len() as u64;
+        let log = RotatingLog::open_with_max_size(dir.path(), 100).unwrap();
+        assert_eq!(log.size, size);
+    }
+}
@@ -0,0 +1,639 @@
+//! Hosting Northstar dedicated servers
+//!
+//! Instances are named server configurations that are started as child processes, directly on
+//! Windows and through the selected Wine/Proton build on Linux. A supervisor thread per running
+//! instance writes the server output into rotating log files and restarts the server if it
+//! crashes.
+pub mod log_rotation;
+pub mod server_config;
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::BufRead;
+use std::process::{Child, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+use ts_rs::TS;
+
+use crate::northstar::profile::{is_valid_profile_name, ProfileLaunchOptions};
+use crate::util::{get_stored_value, set_stored_value};
+use log_rotation::RotatingLog;
+
+/// Key under which the instances are persisted
+const DEDICATED_SERVERS_KEY: &str = "dedicated-servers";
+
+/// How often the supervisor checks whether the server exited or should be stopped
+const SUPERVISOR_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Delay before restarting a crashed server
+const RESTART_DELAY: Duration = Duration::from_secs(5);
+
+/// A server running at least this long before crashing resets the crash counter
+const MIN_HEALTHY_UPTIME: Duration = Duration::from_secs(60);
+
+/// Restarting stops after this many crashes in a row that happened shortly after starting
+const MAX_CONSECUTIVE_CRASHES: u32 = 5;
+
+/// How long to wait for all servers to stop when FlightCore exits
+const STOP_ALL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Configuration of a dedicated server
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+#[ts(export)]
+pub struct DedicatedServerInstance {
+    /// Unique name identifying the instance
+    pub name: String,
+    pub game_path: String,
+    pub profile: String,
+    /// Wine prefix to run the server in (Linux only)
+    #[serde(default)]
+    pub wine_prefix: Option<String>,
+    pub port: u16,
+    /// Extra command line arguments in addition to `-dedicated` and `-port`
+    #[serde(default)]
+    pub arguments: Vec<String>,
+    /// Convars passed on the command line, these take precedence over `autoexec_ns_server.cfg`
+    #[serde(default)]
+    pub convars: BTreeMap<String, String>,
+    #[serde(default)]
+    pub environment: BTreeMap<String, String>,
+    #[serde(default = "default_restart_on_crash")]
+    pub restart_on_crash: bool,
+}
+
+fn default_restart_on_crash() -> bool {
+    true
+}
+
+impl DedicatedServerInstance {
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Instance name may not be empty".to_string());
+        }
+        // The name is used as directory for the logs
+        if self.name.contains(['/', '\\']) || self.name.contains("..") {
+            return Err("Instance name may not contain path separators or \"..\"".to_string());
+        }
+        if !is_valid_profile_name(&self.profile) {
+            return Err(format!("\"{}\" is not a valid profile name", self.profile));
+        }
+        if self.port == 0 {
+            return Err("Port may not be 0".to_string());
+        }
+        if self
+            .arguments
+            .iter()
+            .any(|argument| argument == "-dedicated" || argument == "-port")
+        {
+            return Err("-dedicated and -port are set by FlightCore".to_string());
+        }
+        self.launch_options().validate()
+    }
+
+    /// Arguments, convars and environment the server is launched with
+    fn launch_options(&self) -> ProfileLaunchOptions {
+        let mut arguments = vec![
+            "-dedicated".to_string(),
+            "-port".to_string(),
+            self.port.to_string(),
+        ];
+        arguments.extend(self.arguments.iter().cloned());
+
+        ProfileLaunchOptions {
+            arguments,
+            convars: self.convars.clone(),
+            environment: self.environment.clone(),
+        }
+    }
+}
+
+/// Current state of an instance
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct DedicatedServerStatus {
+    pub name: String,
+    pub running: bool,
+    pub pid: Option<u32>,
+    /// RFC 3339 timestamp of the last (re)start
+    pub started_at: Option<String>,
+    pub restart_count: u32,
+    /// Path of the current log file
+    pub log_path: Option<String>,
+}
+
+/// Emitted whenever a server process exits
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct DedicatedServerExit {
+    pub name: String,
+    pub exit_code: Option<i32>,
+    /// Whether the server exited because it was stopped through FlightCore
+    pub requested: bool,
+    pub will_restart: bool,
+}
+
+struct RunningInstance {
+    stop_requested: Arc<AtomicBool>,
+    status: DedicatedServerStatus,
+}
+
+/// Instances currently supervised, managed as Tauri state
+#[derive(Default)]
+pub struct DedicatedServerManager(Mutex<HashMap<String, RunningInstance>>);
+
+impl DedicatedServerManager {
+    fn update_status(&self, name: &str, update: impl FnOnce(&mut DedicatedServerStatus)) {
+        if let Some(running_instance) = self.0.lock().unwrap().get_mut(name) {
+            update(&mut running_instance.status);
+        }
+    }
+}
+
+fn load_instances(app: &tauri::AppHandle) -> Result<Vec<DedicatedServerInstance>, String> {
+    get_stored_value(app, DEDICATED_SERVERS_KEY)
+        .map(Option::unwrap_or_default)
+        .map_err(|err| format!("Failed reading dedicated servers: {err}"))
+}
+
+fn save_instances(
+    app: &tauri::AppHandle,
+    instances: &[DedicatedServerInstance],
+) -> Result<(), String> {
+    set_stored_value(app, DEDICATED_SERVERS_KEY, &instances)
+        .map_err(|err| format!("Failed saving dedicated servers: {err}"))
+}
+
+/// Builds the command starting the server with its output piped
+fn build_server_command(
+    app: &tauri::AppHandle,
+    instance: &DedicatedServerInstance,
+) -> Result<std::process::Command, String> {
+    let launch_options = instance.launch_options();
+
+    #[cfg(target_os = "linux")]
+    let mut command = {
+        use crate::platform_specific::{get_selected_runner, linux};
+
+        let game_install = crate::GameInstall {
+            game_path: instance.game_path.clone(),
+            profile: instance.profile.clone(),
+            install_type: crate::InstallType::UNKNOWN,
+            wine_prefix: instance.wine_prefix.clone(),
+        };
+        let runner = linux::resolve_runner(get_selected_runner(app)?)?;
+        let steam_path = steamlocate::SteamDir::locate()
+            .ok()
+            .map(|steamdir| steamdir.path().to_path_buf());
+        linux::build_launch_command(
+            &runner,
+            &game_install,
+            &launch_options,
+            steam_path.as_deref(),
+        )?
+    };
+    // Wine starts the server as a separate process, a process group allows stopping both
+    #[cfg(target_os = "linux")]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+    #[cfg(not(target_os = "linux"))]
+    let mut command = {
+        let _ = app;
+        let ns_exe_path = std::path::Path::new(&instance.game_path).join("NorthstarLauncher.exe");
+        let mut command = std::process::Command::new(ns_exe_path);
+        command
+            .current_dir(&instance.game_path)
+            .arg(format!("-profile={}", instance.profile))
+            .args(launch_options.to_args())
+            .envs(&launch_options.environment);
+        command
+    };
+
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    Ok(command)
+}
+
+/// Copies every line of the given output into the log
+fn forward_output(
+    output: impl std::io::Read + Send + 'static,
+    log: Arc<Mutex<RotatingLog>>,
+) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        for line in std::io::BufReader::new(output).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if let Err(err) = log.lock().unwrap().write_line(&line) {
+                log::warn!("Failed writing dedicated server log: {err}");
+            }
+        }
+    })
+}
+
+/// Spawns the server and starts forwarding its output into the log
+fn spawn_server(
+    app: &tauri::AppHandle,
+    instance: &DedicatedServerInstance,
+    log: &Arc<Mutex<RotatingLog>>,
+) -> Result<Child, String> {
+    let mut child = build_server_command(app, instance)?
+        .spawn()
+        .map_err(|err| format!("Failed to start {}: {err}", instance.name))?;
+
+    if let Some(stdout) = child.stdout.take() {
+        forward_output(stdout, log.clone());
+    }
+    if let Some(stderr) = child.stderr.take() {
+        forward_output(stderr, log.clone());
+    }
+    Ok(child)
+}
+
+/// Kills the server, on Linux together with the Wine processes it started
+fn kill_server(child: &mut Child) {
+    #[cfg(target_os = "linux")]
+    {
+        // The server leads its own process group, see `build_server_command`
+        match std::process::Command::new("kill")
+            .args(["-KILL", "--", &format!("-{}", child.id())])
+            .status()
+        {
+            Ok(exit_status) if exit_status.success() => {}
+            Ok(exit_status) => {
+                log::warn!("Killing dedicated server processes failed: {exit_status}")
+            }
+            Err(err) => log::warn!("Failed killing dedicated server processes: {err}"),
+        }
+    }
+    let _ = child.kill();
+}
+
+/// Waits for the server to exit or to be asked to stop
+/// Returns `None` if the state of the server couldn't be determined
+fn wait_for_exit(child: &mut Child, stop_requested: &AtomicBool) -> Option<ExitStatus> {
+    loop {
+        match child.try_wait() {
+            Ok(Some(exit_status)) => return Some(exit_status),
+            Ok(None) => {}
+            Err(err) => {
+                log::warn!("Failed checking dedicated server status: {err}");
+                return None;
+            }
+        }
+        if stop_requested.load(Ordering::SeqCst) {
+            kill_server(child);
+            return child.wait().ok();
+        }
+        std::thread::sleep(SUPERVISOR_POLL_INTERVAL);
+    }
+}
+
+/// Whether the server exited because of an error rather than shutting down normally
+/// Being killed by a signal counts as a crash, as does an exit that couldn't be observed
+fn is_crash(exit_status: Option<ExitStatus>) -> bool {
+    !exit_status.is_some_and(|exit_status| exit_status.success())
+}
+
+/// Runs the server until it is stopped, restarting it after crashes
+fn supervise(
+    app: tauri::AppHandle,
+    instance: DedicatedServerInstance,
+    mut child: Child,
+    log: Arc<Mutex<RotatingLog>>,
+    stop_requested: Arc<AtomicBool>,
+) {
+    let dedicated_server_manager = app.state::<DedicatedServerManager>();
+    let mut consecutive_crashes = 0;
+
+    loop {
+        let started_at = Instant::now();
+        let exit_status = wait_for_exit(&mut child, &stop_requested);
+        let exit_code = exit_status.and_then(|exit_status| exit_status.code());
+        let requested = stop_requested.load(Ordering::SeqCst);
+        let crashed = !requested && is_crash(exit_status);
+
+        if started_at.elapsed() >= MIN_HEALTHY_UPTIME {
+            consecutive_crashes = 0;
+        }
+        consecutive_crashes += 1;
+        let will_restart =
+            crashed && instance.restart_on_crash && consecutive_crashes <= MAX_CONSECUTIVE_CRASHES;
+
+        let exit_description = match exit_status {
+            Some(exit_status) => exit_status.to_string(),
+            None => "unknown status".to_string(),
+        };
+        let message = format!(
+            "Dedicated server {} exited with {exit_description}{}",
+            instance.name,
+            if will_restart { ", restarting" } else { "" }
+        );
+        if crashed {
+            log::warn!("{message}");
+        } else {
+            log::info!("{message}");
+        }
+        let _ = log
+            .lock()
+            .unwrap()
+            .write_line(&format!("[FlightCore] {message}"));
+        if let Err(err) = app.emit(
+            "dedicated-server-exited",
+            DedicatedServerExit {
+                name: instance.name.clone(),
+                exit_code,
+                requested,
+                will_restart,
+            },
+        ) {
+            log::warn!("Failed emitting dedicated server event: {err}");
+        }
+
+        if !will_restart {
+            break;
+        }
+        std::thread::sleep(RESTART_DELAY);
+        if stop_requested.load(Ordering::SeqCst) {
+            break;
+        }
+
+        child = match spawn_server(&app, &instance, &log) {
+            Ok(child) => child,
+            Err(err) => {
+                log::error!("{err}");
+                break;
+            }
+        };
+        dedicated_server_manager.update_status(&instance.name, |status| {
+            status.pid = Some(child.id());
+            status.started_at = Some(chrono::Utc::now().to_rfc3339());
+            status.restart_count += 1;
+        });
+    }
+
+    dedicated_server_manager
+        .0
+        .lock()
+        .unwrap()
+        .remove(&instance.name);
+}
+
+/// Returns all configured instances
+#[tauri::command]
+pub fn get_dedicated_servers(
+    app: tauri::AppHandle,
+) -> Result<Vec<DedicatedServerInstance>, String> {
+    load_instances(&app)
+}
+
+/// Adds an instance or replaces the one with the same name
+/// Changes to a running instance take effect on its next start
+#[tauri::command]
+pub fn save_dedicated_server(
+    app: tauri::AppHandle,
+    instance: DedicatedServerInstance,
+) -> Result<(), String> {
+    instance.validate()?;
+
+    let mut instances = load_instances(&app)?;
+    if instances.iter().any(|other| {
+        other.name != instance.name
+            && other.game_path == instance.game_path
+            && other.port == instance.port
+    }) {
+        return Err(format!("Port {} is already used", instance.port));
+    }
+
+    match instances
+        .iter_mut()
+        .find(|other| other.name == instance.name)
+    {
+        Some(existing) => *existing = instance,
+        None => instances.push(instance),
+    }
+    save_instances(&app, &instances)
+}
+
+/// Removes a stopped instance
+#[tauri::command]
+pub fn remove_dedicated_server(app: tauri::AppHandle, name: String) -> Result<(), String> {
+    if app
+        .state::<DedicatedServerManager>()
+        .0
+        .lock()
+        .unwrap()
+        .contains_key(&name)
+    {
+        return Err(format!("{name} is still running"));
+    }
+
+    let mut instances = load_instances(&app)?;
+    instances.retain(|instance| instance.name != name);
+    save_instances(&app, &instances)
+}
+
+/// Starts the instance and keeps it running until it is stopped
+#[tauri::command]
+pub fn start_dedicated_server(
+    app: tauri::AppHandle,
+    name: String,
+) -> Result<DedicatedServerStatus, String> {
+    let Some(instance) = load_instances(&app)?
+        .into_iter()
+        .find(|instance| instance.name == name)
+    else {
+        return Err(format!("No dedicated server named {name}"));
+    };
+    instance.validate()?;
+
+    let dedicated_server_manager = app.state::<DedicatedServerManager>();
+    let mut running_instances = dedicated_server_manager.0.lock().unwrap();
+    if running_instances.contains_key(&name) {
+        return Err(format!("{name} is already running"));
+    }
+
+    let log_dir = app
+        .path()
+        .app_log_dir()
+        .map_err(|err| format!("Failed getting log directory: {err}"))?
+        .join("dedicated-servers")
+        .join(&name);
+    let log = RotatingLog::open(&log_dir).map_err(|err| format!("Failed opening log: {err}"))?;
+    let log_path = log.path().display().to_string();
+    let log = Arc::new(Mutex::new(log));
+
+    let child = spawn_server(&app, &instance, &log)?;
+    log::info!("Started dedicated server {name} with PID {}", child.id());
+
+    let status = DedicatedServerStatus {
+        name: name.clone(),
+        running: true,
+        pid: Some(child.id()),
+        started_at: Some(chrono::Utc::now().to_rfc3339()),
+        restart_count: 0,
+        log_path: Some(log_path),
+    };
+    let stop_requested = Arc::new(AtomicBool::new(false));
+    running_instances.insert(
+        name,
+        RunningInstance {
+            stop_requested: stop_requested.clone(),
+            status: status.clone(),
+        },
+    );
+    drop(running_instances);
+
+    let app_handle = app.clone();
+    std::thread::spawn(move || supervise(app_handle, instance, child, log, stop_requested));
+
+    if let Err(err) = app.emit("dedicated-server-started", &status) {
+        log::warn!("Failed emitting dedicated server event: {err}");
+    }
+    Ok(status)
+}
+
+/// Stops the instance, it is not restarted
+#[tauri::command]
+pub fn stop_dedicated_server(app: tauri::AppHandle, name: String) -> Result<(), String> {
+    let dedicated_server_manager = app.state::<DedicatedServerManager>();
+    let running_instances = dedicated_server_manager.0.lock().unwrap();
+    match running_instances.get(&name) {
+        Some(running_instance) => {
+            running_instance
+                .stop_requested
+                .store(true, Ordering::SeqCst);
+            Ok(())
+        }
+        None => Err(format!("{name} is not running")),
+    }
+}
+
+/// Stops all running instances and waits for their supervisors to kill them
+/// Called when FlightCore exits so that no servers are left running without supervision
+pub fn stop_all_dedicated_servers(app: &tauri::AppHandle) {
+    let dedicated_server_manager = app.state::<DedicatedServerManager>();
+    for running_instance in dedicated_server_manager.0.lock().unwrap().values() {
+        running_instance
+            .stop_requested
+            .store(true, Ordering::SeqCst);
+    }
+
+    let stop_started_at = Instant::now();
+    while !dedicated_server_manager.0.lock().unwrap().is_empty() {
+        if stop_started_at.elapsed() >= STOP_ALL_TIMEOUT {
+            log::warn!("Timed out waiting for dedicated servers to stop");
+            return;
+        }
+        std::thread::sleep(SUPERVISOR_POLL_INTERVAL);
+    }
+}
+
+/// Returns the state of every configured instance
+#[tauri::command]
+pub fn get_dedicated_server_status(
+    app: tauri::AppHandle,
+) -> Result<Vec<DedicatedServerStatus>, String> {
+    let instances = load_instances(&app)?;
+    let dedicated_server_manager = app.state::<DedicatedServerManager>();
+    let running_instances = dedicated_server_manager.0.lock().unwrap();
+
+    Ok(instances
+        .into_iter()
+        .map(|instance| match running_instances.get(&instance.name) {
+            Some(running_instance) => running_instance.status.clone(),
+            None => DedicatedServerStatus {
+                name: instance.name,
+                running: false,
+                pid: None,
+                started_at: None,
+                restart_count: 0,
+                log_path: None,
+            },
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_is_crash() {
+        use std::os::unix::process::ExitStatusExt;
+
+        assert!(!is_crash(Some(ExitStatus::from_raw(0))));
+        // Exit code 1
+        assert!(is_crash(Some(ExitStatus::from_raw(1 << 8))));
+        // Killed by SIGSEGV
+        assert!(is_crash(Some(ExitStatus::from_raw(11))));
+        assert!(is_crash(None));
+    }
+
+    fn instance() -> DedicatedServerInstance {
+        serde_json::from_value(serde_json::json!({
+            "name": "attrition",
+            "game_path": "/games/Titanfall2",
+            "profile": "R2Server",
+            "port": 37015,
+            "arguments": ["-softwared3d11"],
+            "convars": { "ns_server_name": "FlightCore" },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_instance_launch_options() {
+        let instance = instance();
+        assert!(instance.restart_on_crash);
+        assert_eq!(
+            instance.launch_options().to_args(),
+            vec![
+                "-dedicated",
+                "-port",
+                "37015",
+                "-softwared3d11",
+                "+ns_server_name",
+                "FlightCore"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_instance() {
+        assert!(instance().validate().is_ok());
+
+        let mut reserved = instance();
+        reserved.arguments.push("-port".to_string());
+        assert!(reserved.validate().is_err());
+
+        let mut profile = instance();
+        profile.arguments.push("-profile=other".to_string());
+        assert!(profile.validate().is_err());
+
+        for profile in ["../x", "vpk", "R2Northstar/mods"] {
+            let mut invalid_profile = instance();
+            invalid_profile.profile = profile.to_string();
+            assert!(invalid_profile.validate().is_err());
+        }
+
+        let mut no_port = instance();
+        no_port.port = 0;
+        assert!(no_port.validate().is_err());
+
+        for name in [
+            "../attrition",
+            "servers/attrition",
+            "servers\\attrition",
+            "..",
+        ] {
+            let mut path_name = instance();
+            path_name.name = name.to_string();
+            assert!(path_name.validate().is_err());
+        }
+    }
+}
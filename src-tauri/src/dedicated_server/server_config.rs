@@ -0,0 +1,453 @@
+//! Reading and editing Northstar cfg files such as `autoexec_ns_server.cfg`
+//!
+//! Only the lines of changed settings are rewritten, comments and all other lines are kept as is.
+//! Note that Northstar updates replace the cfg files shipped with `Northstar.CustomServers`.
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use ts_rs::TS;
+
+use crate::GameInstall;
+
+/// Location of the server autoexec relative to the profile folder
+const SERVER_CFG_PATH: &str = "mods/Northstar.CustomServers/mod/cfg/autoexec_ns_server.cfg";
+
+/// Setting a convar or running a command, e.g. `ns_server_name "My Server"`
+#[derive(Debug, Clone, PartialEq)]
+struct CfgStatement {
+    name: String,
+    value: String,
+}
+
+/// A line of a cfg file, which may hold several statements separated by `;`
+#[derive(Debug, Clone, PartialEq)]
+struct CfgLine {
+    /// Line as read, written back unchanged unless one of its statements is changed
+    raw: Option<String>,
+    /// Empty for comments and blank lines
+    statements: Vec<CfgStatement>,
+    /// Trailing comment including the whitespace before it
+    comment: String,
+}
+
+/// Splits off a trailing `//` comment that is not inside quotes
+fn split_comment(line: &str) -> (&str, &str) {
+    let mut in_quotes = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '/' if !in_quotes && line[index..].starts_with("//") => {
+                let content = line[..index].trim_end();
+                return (content, &line[content.len()..]);
+            }
+            _ => {}
+        }
+    }
+    (line.trim_end(), &line[line.trim_end().len()..])
+}
+
+/// Splits the content of a line at every `;` that is not inside quotes
+fn split_statements(content: &str) -> Vec<&str> {
+    let mut statements = vec![];
+    let mut in_quotes = false;
+    let mut start = 0;
+    for (index, c) in content.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ';' if !in_quotes => {
+                statements.push(&content[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    statements.push(&content[start..]);
+    statements
+}
+
+fn parse_statement(statement: &str) -> Option<CfgStatement> {
+    let statement = statement.trim();
+    if statement.is_empty() {
+        return None;
+    }
+
+    let (name, value) = match statement.split_once(char::is_whitespace) {
+        Some((name, value)) => (name, value.trim()),
+        None => (statement, ""),
+    };
+    let value = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value);
+
+    Some(CfgStatement {
+        name: name.to_string(),
+        value: value.to_string(),
+    })
+}
+
+fn parse_line(line: &str) -> CfgLine {
+    let (content, comment) = split_comment(line);
+    CfgLine {
+        raw: Some(line.to_string()),
+        statements: split_statements(content)
+            .into_iter()
+            .filter_map(parse_statement)
+            .collect(),
+        comment: comment.to_string(),
+    }
+}
+
+/// Contents of a cfg file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CfgFile {
+    lines: Vec<CfgLine>,
+}
+
+impl CfgFile {
+    pub fn parse(contents: &str) -> Self {
+        CfgFile {
+            lines: contents.lines().map(parse_line).collect(),
+        }
+    }
+
+    /// Value of the last statement with the given name, as that is the one in effect
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.lines
+            .iter()
+            .rev()
+            .flat_map(|line| line.statements.iter().rev())
+            .find(|statement| statement.name.eq_ignore_ascii_case(name))
+            .map(|statement| statement.value.as_str())
+    }
+
+    /// Sets the value of the statement in effect or appends a new one
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        if value.contains(['"', '\n', '\r']) {
+            return Err(format!("Invalid value \"{value}\" for {name}"));
+        }
+
+        let existing = self.lines.iter_mut().rev().find_map(|line| {
+            let statement = line
+                .statements
+                .iter_mut()
+                .rev()
+                .find(|statement| statement.name.eq_ignore_ascii_case(name))?;
+            Some((&mut line.raw, statement))
+        });
+        match existing {
+            Some((_, statement)) if statement.value == value => {}
+            Some((raw, statement)) => {
+                statement.value = value.to_string();
+                *raw = None;
+            }
+            None => self.lines.push(CfgLine {
+                raw: None,
+                statements: vec![CfgStatement {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                }],
+                comment: String::new(),
+            }),
+        }
+        Ok(())
+    }
+
+    /// Removes all statements with the given name, lines left without content are dropped
+    pub fn remove(&mut self, name: &str) {
+        self.lines.retain_mut(|line| {
+            let count = line.statements.len();
+            line.statements
+                .retain(|statement| !statement.name.eq_ignore_ascii_case(name));
+            if line.statements.len() == count {
+                return true;
+            }
+            line.raw = None;
+            !line.statements.is_empty() || !line.comment.is_empty()
+        });
+    }
+}
+
+/// Quotes values that would otherwise be split or dropped
+fn format_value(value: &str) -> String {
+    if value.is_empty() || value.contains(char::is_whitespace) || value.contains("//") {
+        format!("\"{value}\"")
+    } else {
+        value.to_string()
+    }
+}
+
+impl std::fmt::Display for CfgFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for line in &self.lines {
+            if let Some(raw) = &line.raw {
+                writeln!(f, "{raw}")?;
+                continue;
+            }
+            let statements = line
+                .statements
+                .iter()
+                .map(|statement| format!("{} {}", statement.name, format_value(&statement.value)))
+                .collect::<Vec<String>>()
+                .join("; ");
+            if statements.is_empty() {
+                writeln!(f, "{}", line.comment.trim_start())?;
+            } else {
+                writeln!(f, "{statements}{}", line.comment)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Well-known settings of `autoexec_ns_server.cfg`
+/// `None` means the setting is not present in the file and Northstar's default applies
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, TS)]
+#[ts(export)]
+#[serde(default)]
+pub struct NorthstarServerConfig {
+    /// `ns_server_name`
+    pub server_name: Option<String>,
+    /// `ns_server_desc`
+    pub server_description: Option<String>,
+    /// `ns_server_password`
+    pub server_password: Option<String>,
+    /// Set via the `setplaylist` command
+    pub playlist: Option<String>,
+    /// `ns_report_server_to_masterserver`
+    pub report_to_master_server: Option<bool>,
+    /// `ns_masterserver_hostname`
+    pub master_server_hostname: Option<String>,
+    /// `ns_auth_allow_insecure`
+    pub allow_insecure_auth: Option<bool>,
+    /// `ns_should_return_to_lobby`
+    pub return_to_lobby: Option<bool>,
+    /// `everything_unlocked`
+    pub everything_unlocked: Option<bool>,
+    /// `hostport`, UDP port the game is hosted on
+    pub host_port: Option<u16>,
+    /// `ns_player_auth_port`, TCP port used for authenticating players
+    pub player_auth_port: Option<u16>,
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "0" => Some(false),
+        "1" => Some(true),
+        _ => None,
+    }
+}
+
+fn format_bool(value: bool) -> String {
+    if value { "1" } else { "0" }.to_string()
+}
+
+impl NorthstarServerConfig {
+    pub fn from_cfg(cfg: &CfgFile) -> Self {
+        let text = |name: &str| cfg.get(name).map(str::to_string);
+        let flag = |name: &str| cfg.get(name).and_then(parse_bool);
+        let port = |name: &str| cfg.get(name).and_then(|value| value.parse().ok());
+
+        NorthstarServerConfig {
+            server_name: text("ns_server_name"),
+            server_description: text("ns_server_desc"),
+            server_password: text("ns_server_password"),
+            playlist: text("setplaylist"),
+            report_to_master_server: flag("ns_report_server_to_masterserver"),
+            master_server_hostname: text("ns_masterserver_hostname"),
+            allow_insecure_auth: flag("ns_auth_allow_insecure"),
+            return_to_lobby: flag("ns_should_return_to_lobby"),
+            everything_unlocked: flag("everything_unlocked"),
+            host_port: port("hostport"),
+            player_auth_port: port("ns_player_auth_port"),
+        }
+    }
+
+    /// Cfg statement and formatted value of every setting
+    fn settings(&self) -> [(&'static str, Option<String>); 11] {
+        [
+            ("ns_server_name", self.server_name.clone()),
+            ("ns_server_desc", self.server_description.clone()),
+            ("ns_server_password", self.server_password.clone()),
+            ("setplaylist", self.playlist.clone()),
+            (
+                "ns_report_server_to_masterserver",
+                self.report_to_master_server.map(format_bool),
+            ),
+            (
+                "ns_masterserver_hostname",
+                self.master_server_hostname.clone(),
+            ),
+            (
+                "ns_auth_allow_insecure",
+                self.allow_insecure_auth.map(format_bool),
+            ),
+            (
+                "ns_should_return_to_lobby",
+                self.return_to_lobby.map(format_bool),
+            ),
+            (
+                "everything_unlocked",
+                self.everything_unlocked.map(format_bool),
+            ),
+            ("hostport", self.host_port.map(|port| port.to_string())),
+            (
+                "ns_player_auth_port",
+                self.player_auth_port.map(|port| port.to_string()),
+            ),
+        ]
+    }
+
+    /// Writes the settings that differ from `previous` into the cfg, settings changed to `None`
+    /// are removed
+    /// `previous` is what `from_cfg` read from the cfg, so settings it couldn't parse are only
+    /// touched if the caller actually changed them
+    pub fn apply_to(
+        &self,
+        previous: &NorthstarServerConfig,
+        cfg: &mut CfgFile,
+    ) -> Result<(), String> {
+        for ((name, value), (_, previous_value)) in
+            self.settings().into_iter().zip(previous.settings())
+        {
+            if value == previous_value {
+                continue;
+            }
+            match value {
+                Some(value) => cfg.set(name, &value)?,
+                None => cfg.remove(name),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn server_cfg_path(game_install: &GameInstall) -> PathBuf {
+    PathBuf::from(&game_install.game_path)
+        .join(&game_install.profile)
+        .join(SERVER_CFG_PATH)
+}
+
+fn read_server_cfg(game_install: &GameInstall) -> Result<CfgFile, String> {
+    let path = server_cfg_path(game_install);
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => Ok(CfgFile::parse(&contents)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(CfgFile::default()),
+        Err(err) => Err(format!("Failed reading {}: {err}", path.display())),
+    }
+}
+
+/// Reads the server settings of the given profile
+#[tauri::command]
+pub fn get_server_config(game_install: GameInstall) -> Result<NorthstarServerConfig, String> {
+    let cfg = read_server_cfg(&game_install)?;
+    Ok(NorthstarServerConfig::from_cfg(&cfg))
+}
+
+/// Updates the server settings of the given profile
+/// Settings changed to `None` are removed so that Northstar's defaults apply
+#[tauri::command]
+pub fn set_server_config(
+    game_install: GameInstall,
+    config: NorthstarServerConfig,
+) -> Result<(), String> {
+    let mut cfg = read_server_cfg(&game_install)?;
+    let previous = NorthstarServerConfig::from_cfg(&cfg);
+    config.apply_to(&previous, &mut cfg)?;
+
+    let path = server_cfg_path(&game_install);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|err| format!("Failed creating {}: {err}", parent.display()))?;
+    }
+    std::fs::write(&path, cfg.to_string())
+        .map_err(|err| format!("Failed writing {}: {err}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERVER_CFG: &str = r#"// server name
+ns_server_name "Unnamed Northstar Server" // shown in the server browser
+ns_server_desc "Default server description"
+ns_server_password "" // leave empty for no password
+ns_report_server_to_masterserver 1
+
+// gameplay
+setplaylist aitdm
+sv_cheats 0; everything_unlocked 1
+ns_should_return_to_lobby 1
+"#;
+
+    #[test]
+    fn test_parse_server_cfg() {
+        let cfg = CfgFile::parse(SERVER_CFG);
+        assert_eq!(cfg.to_string(), SERVER_CFG);
+
+        let config = NorthstarServerConfig::from_cfg(&cfg);
+        assert_eq!(
+            config.server_name.as_deref(),
+            Some("Unnamed Northstar Server")
+        );
+        assert_eq!(config.server_password.as_deref(), Some(""));
+        assert_eq!(config.playlist.as_deref(), Some("aitdm"));
+        assert_eq!(config.report_to_master_server, Some(true));
+        assert_eq!(config.everything_unlocked, Some(true));
+        assert_eq!(config.host_port, None);
+    }
+
+    #[test]
+    fn test_update_server_cfg() {
+        let mut cfg = CfgFile::parse(SERVER_CFG);
+        let previous = NorthstarServerConfig::from_cfg(&cfg);
+        let mut config = previous.clone();
+        config.server_name = Some("My Server".to_string());
+        config.server_description = None;
+        config.host_port = Some(37016);
+        config.apply_to(&previous, &mut cfg).unwrap();
+
+        assert_eq!(
+            cfg.to_string(),
+            r#"// server name
+ns_server_name "My Server" // shown in the server browser
+ns_server_password "" // leave empty for no password
+ns_report_server_to_masterserver 1
+
+// gameplay
+setplaylist aitdm
+sv_cheats 0; everything_unlocked 1
+ns_should_return_to_lobby 1
+hostport 37016
+"#
+        );
+
+        assert!(cfg.set("ns_server_name", "quote\"d").is_err());
+    }
+
+    #[test]
+    fn test_update_server_cfg_keeps_unparsed_settings() {
+        let mut cfg = CfgFile::parse(
+            "hostport ${HOST_PORT}\nsv_cheats 0; everything_unlocked 1 // unlocks\nns_auth_allow_insecure yes\n",
+        );
+        let previous = NorthstarServerConfig::from_cfg(&cfg);
+        assert_eq!(previous.host_port, None);
+        assert_eq!(previous.allow_insecure_auth, None);
+
+        let mut config = previous.clone();
+        config.everything_unlocked = Some(false);
+        config.apply_to(&previous, &mut cfg).unwrap();
+        assert_eq!(
+            cfg.to_string(),
+            "hostport ${HOST_PORT}\nsv_cheats 0; everything_unlocked 0 // unlocks\nns_auth_allow_insecure yes\n"
+        );
+
+        let previous = NorthstarServerConfig::from_cfg(&cfg);
+        let mut config = previous.clone();
+        config.everything_unlocked = None;
+        config.apply_to(&previous, &mut cfg).unwrap();
+        assert_eq!(
+            cfg.to_string(),
+            "hostport ${HOST_PORT}\nsv_cheats 0 // unlocks\nns_auth_allow_insecure yes\n"
+        );
+    }
+}
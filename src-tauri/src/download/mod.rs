@@ -0,0 +1,380 @@
+//! Downloading files to disk
+//!
+//! Downloads are streamed into a `.part` file next to the target and only moved into place once
+//! complete and verified. Failed attempts are retried with exponential backoff and continue where
+//! they left off through HTTP range requests if the server supports them. Resuming is bound to
+//! the `ETag`/`Last-Modified` of the first response through `If-Range`, so a file that changed
+//! in between is downloaded again instead of being spliced together.
+use anyhow::{anyhow, Context, Result};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::constants::APP_USER_AGENT;
+
+/// Attempts made before a download is given up on
+const MAX_ATTEMPTS: u32 = 5;
+
+/// Wait before the first retry, doubled with every further attempt
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// A connection receiving nothing for this long is considered stalled and retried
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What is known about a file before downloading it, used to verify the download
+#[derive(Debug, Clone, Default)]
+pub struct ExpectedFile {
+    pub size: Option<u64>,
+    /// Hex encoded SHA-256 checksum
+    pub sha256: Option<String>,
+}
+
+enum AttemptError {
+    /// Connection problems and server errors that may go away
+    Retry(anyhow::Error),
+    /// Errors another attempt would not fix
+    Fatal(anyhow::Error),
+}
+
+fn part_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".part");
+    path.with_file_name(file_name)
+}
+
+fn retry_delay(attempt: u32) -> Duration {
+    INITIAL_RETRY_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(MAX_RETRY_DELAY)
+}
+
+fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    status.is_server_error()
+        || status == reqwest::StatusCode::REQUEST_TIMEOUT
+        || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+}
+
+/// Checks size and checksum of a finished download against what was expected
+fn verify(path: &Path, expected: &ExpectedFile) -> Result<()> {
+    let size = std::fs::metadata(path)?.len();
+    if let Some(expected_size) = expected.size {
+        if size != expected_size {
+            return Err(anyhow!(
+                "Downloaded file has {size} bytes but {expected_size} were expected"
+            ));
+        }
+    }
+
+    if let Some(expected_sha256) = &expected.sha256 {
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+        let sha256 = format!("{:x}", hasher.finalize());
+        if !sha256.eq_ignore_ascii_case(expected_sha256) {
+            return Err(anyhow!(
+                "Checksum of downloaded file is {sha256} but {expected_sha256} was expected"
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs `verify` off the async runtime, hashing large files takes a while
+async fn verify_blocking(path: &Path, expected: &ExpectedFile) -> Result<()> {
+    let path = path.to_path_buf();
+    let expected = expected.clone();
+    tokio::task::spawn_blocking(move || verify(&path, &expected)).await?
+}
+
+/// Validator of the response to send as `If-Range` when resuming
+/// Weak ETags may not be used for range requests, `Last-Modified` is used instead then
+fn response_validator(headers: &reqwest::header::HeaderMap) -> Option<String> {
+    let etag = headers
+        .get(reqwest::header::ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .filter(|etag| !etag.starts_with("W/"));
+    let last_modified = headers
+        .get(reqwest::header::LAST_MODIFIED)
+        .and_then(|last_modified| last_modified.to_str().ok());
+    etag.or(last_modified).map(str::to_string)
+}
+
+/// First byte of a `Content-Range` header (`bytes <start>-<end>/<size>`)
+fn content_range_start(content_range: &str) -> Option<u64> {
+    content_range
+        .strip_prefix("bytes ")?
+        .split_once('-')?
+        .0
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Downloads into `part_path`, continuing a previous attempt if the server allows it
+/// `validator` identifies the file version the part file holds, it is only resumed if the
+/// server still has that version
+async fn download_attempt(
+    client: &reqwest::Client,
+    url: &str,
+    part_path: &Path,
+    expected: &ExpectedFile,
+    validator: &mut Option<String>,
+    on_progress: &mut impl FnMut(u64, u64),
+    cancelled: Option<&AtomicBool>,
+) -> Result<(), AttemptError> {
+    // Without a validator there is no telling which file version the part file belongs to
+    if validator.is_none() {
+        let _ = std::fs::remove_file(part_path);
+    }
+    let existing = std::fs::metadata(part_path)
+        .map(|metadata| metadata.len())
+        .unwrap_or(0);
+    // A previous attempt may have finished but failed before it was moved into place
+    if existing > 0 && expected.size == Some(existing) {
+        return Ok(());
+    }
+
+    let mut request = client.get(url);
+    if let Some(validator) = validator.as_deref().filter(|_| existing > 0) {
+        log::info!("Resuming download of {url} at {existing} bytes");
+        request = request
+            .header(reqwest::header::RANGE, format!("bytes={existing}-"))
+            .header(reqwest::header::IF_RANGE, validator);
+    }
+    let mut response = request
+        .send()
+        .await
+        .map_err(|err| AttemptError::Retry(err.into()))?;
+
+    let status = response.status();
+    let mut downloaded = match status {
+        reqwest::StatusCode::PARTIAL_CONTENT => {
+            let range_start = response
+                .headers()
+                .get(reqwest::header::CONTENT_RANGE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(content_range_start);
+            if range_start != Some(existing) {
+                // Appending anything else would corrupt the file, start over
+                let _ = std::fs::remove_file(part_path);
+                *validator = None;
+                return Err(AttemptError::Retry(anyhow!(
+                    "Server resumed the download at the wrong position"
+                )));
+            }
+            existing
+        }
+        reqwest::StatusCode::RANGE_NOT_SATISFIABLE => {
+            // The partial file doesn't belong to what the server has, start over
+            let _ = std::fs::remove_file(part_path);
+            *validator = None;
+            return Err(AttemptError::Retry(anyhow!(
+                "Server rejected resuming the download"
+            )));
+        }
+        status if status.is_success() => {
+            // Either a fresh download or the file changed since the last attempt
+            *validator = response_validator(response.headers());
+            0
+        }
+        status if is_retryable_status(status) => {
+            return Err(AttemptError::Retry(anyhow!(
+                "Request unsuccessful: {status}"
+            )))
+        }
+        status => {
+            return Err(AttemptError::Fatal(anyhow!(
+                "Request unsuccessful: {status}"
+            )))
+        }
+    };
+
+    let total = response
+        .content_length()
+        .map(|length| length + downloaded)
+        .or(expected.size)
+        .unwrap_or_default();
+    if let Some(expected_size) = expected.size {
+        if total != 0 && total != expected_size {
+            return Err(AttemptError::Fatal(anyhow!(
+                "Server reports {total} bytes but {expected_size} were expected"
+            )));
+        }
+    }
+
+    // Servers ignoring the range send the whole file again
+    let mut file = std::fs::File::options()
+        .create(true)
+        .write(true)
+        .append(downloaded > 0)
+        .truncate(downloaded == 0)
+        .open(part_path)
+        .map_err(|err| AttemptError::Fatal(err.into()))?;
+
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|err| AttemptError::Retry(err.into()))?
+    {
+        if cancelled.is_some_and(|cancelled| cancelled.load(Ordering::Relaxed)) {
+            return Err(AttemptError::Fatal(anyhow!("Download was cancelled")));
+        }
+        file.write_all(&chunk)
+            .map_err(|err| AttemptError::Fatal(err.into()))?;
+        downloaded += chunk.len() as u64;
+        on_progress(downloaded, total);
+    }
+    Ok(())
+}
+
+/// Downloads `url` to `path`, retrying and resuming on failures
+/// `on_progress` is called with the downloaded and total bytes, total being `0` if unknown
+/// Setting `cancelled` aborts the download and removes what was downloaded so far
+pub async fn download_file(
+    url: &str,
+    path: &Path,
+    expected: &ExpectedFile,
+    mut on_progress: impl FnMut(u64, u64),
+    cancelled: Option<&AtomicBool>,
+) -> Result<()> {
+    let part_path = part_path(path);
+    let client = reqwest::Client::builder()
+        .user_agent(APP_USER_AGENT)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .read_timeout(READ_TIMEOUT)
+        .build()?;
+
+    let mut validator: Option<String> = None;
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let mut result = download_attempt(
+            &client,
+            url,
+            &part_path,
+            expected,
+            &mut validator,
+            &mut on_progress,
+            cancelled,
+        )
+        .await;
+        if result.is_ok() {
+            result = verify_blocking(&part_path, expected).await.map_err(|err| {
+                // Resuming a corrupted file would only corrupt it again
+                let _ = std::fs::remove_file(&part_path);
+                validator = None;
+                AttemptError::Retry(err)
+            });
+        }
+
+        match result {
+            Ok(()) => {
+                return std::fs::rename(&part_path, path)
+                    .with_context(|| format!("Failed moving download to {}", path.display()))
+            }
+            Err(AttemptError::Retry(err)) if attempt < MAX_ATTEMPTS => {
+                let delay = retry_delay(attempt);
+                log::warn!(
+                    "Downloading {url} failed: {err}, retrying in {}s",
+                    delay.as_secs()
+                );
+                tokio::time::sleep(delay).await;
+            }
+            Err(AttemptError::Retry(err)) => {
+                return Err(err.context(format!(
+                    "Failed downloading {url} after {MAX_ATTEMPTS} attempts"
+                )))
+            }
+            Err(AttemptError::Fatal(err)) => {
+                let _ = std::fs::remove_file(&part_path);
+                return Err(err.context(format!("Failed downloading {url}")));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_part_path() {
+        assert_eq!(
+            part_path(Path::new("/tmp/download-dir/northstar-1.19.0.zip")),
+            PathBuf::from("/tmp/download-dir/northstar-1.19.0.zip.part")
+        );
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(1), Duration::from_secs(1));
+        assert_eq!(retry_delay(3), Duration::from_secs(4));
+        assert_eq!(retry_delay(10), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn test_response_validator() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        assert_eq!(response_validator(&headers), None);
+
+        headers.insert(
+            reqwest::header::LAST_MODIFIED,
+            "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+        );
+        headers.insert(reqwest::header::ETAG, "W/\"weak\"".parse().unwrap());
+        assert_eq!(
+            response_validator(&headers).as_deref(),
+            Some("Wed, 21 Oct 2015 07:28:00 GMT")
+        );
+
+        headers.insert(reqwest::header::ETAG, "\"strong\"".parse().unwrap());
+        assert_eq!(response_validator(&headers).as_deref(), Some("\"strong\""));
+    }
+
+    #[test]
+    fn test_content_range_start() {
+        assert_eq!(content_range_start("bytes 1024-2047/2048"), Some(1024));
+        assert_eq!(content_range_start("bytes 0-99/*"), Some(0));
+        assert_eq!(content_range_start("bytes */2048"), None);
+        assert_eq!(content_range_start("items 0-1/2"), None);
+    }
+
+    #[test]
+    fn test_verify() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        std::fs::write(&path, "hello").unwrap();
+
+        let sha256 = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        assert!(verify(
+            &path,
+            &ExpectedFile {
+                size: Some(5),
+                sha256: Some(sha256.to_uppercase()),
+            }
+        )
+        .is_ok());
+        assert!(verify(
+            &path,
+            &ExpectedFile {
+                size: Some(6),
+                sha256: None,
+            }
+        )
+        .is_err());
+        assert!(verify(
+            &path,
+            &ExpectedFile {
+                size: None,
+                sha256: Some("00".repeat(32)),
+            }
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,456 @@
+//! Keeps track of all Titanfall2 installs FlightCore knows about
+//!
+//! Installs are either auto-detected (Steam libraries, EA App / Origin) or added by the user
+//! and persisted so that the user can label and switch between them.
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::constants::NORTHSTAR_DEFAULT_PROFILE;
+use crate::northstar::profile::{is_valid_profile_name, validate_profile};
+use crate::repair_and_verify::check_is_valid_game_path;
+use crate::util::{get_stored_value, set_stored_value};
+use crate::{GameInstall, InstallType};
+
+#[cfg(target_os = "windows")]
+use crate::platform_specific::windows;
+
+/// Key under which the install registry is persisted
+const GAME_INSTALL_REGISTRY_KEY: &str = "game-install-registry";
+
+/// A Titanfall2 install known to FlightCore
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct RegisteredGameInstall {
+    pub label: String,
+    pub game_path: String,
+    pub profile: String,
+    pub install_type: InstallType,
+    #[serde(default)]
+    pub wine_prefix: Option<String>,
+    pub user_added: bool,
+    #[serde(default)]
+    pub is_valid: bool,
+}
+
+impl RegisteredGameInstall {
+    pub fn to_game_install(&self) -> GameInstall {
+        GameInstall {
+            game_path: self.game_path.clone(),
+            profile: self.profile.clone(),
+            install_type: self.install_type.clone(),
+            wine_prefix: self.wine_prefix.clone(),
+        }
+    }
+}
+
+/// Persisted state of all known installs
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct GameInstallRegistry {
+    installs: Vec<RegisteredGameInstall>,
+    active_game_path: Option<String>,
+}
+
+impl GameInstallRegistry {
+    fn load(app: &tauri::AppHandle) -> Result<Self, String> {
+        match get_stored_value(app, GAME_INSTALL_REGISTRY_KEY) {
+            Ok(registry) => Ok(registry.unwrap_or_default()),
+            Err(err) => Err(format!("Failed reading install registry: {err}")),
+        }
+    }
+
+    fn save(&self, app: &tauri::AppHandle) -> Result<(), String> {
+        set_stored_value(app, GAME_INSTALL_REGISTRY_KEY, self)
+            .map_err(|err| format!("Failed saving install registry: {err}"))
+    }
+
+    fn find_mut(&mut self, game_path: &str) -> Option<&mut RegisteredGameInstall> {
+        self.installs
+            .iter_mut()
+            .find(|install| is_same_path(&install.game_path, game_path))
+    }
+
+    /// Re-checks whether each install still points to a valid Titanfall2 install
+    fn revalidate(&mut self) {
+        for install in self.installs.iter_mut() {
+            install.is_valid = check_is_valid_game_path(&install.game_path).is_ok();
+        }
+    }
+}
+
+/// Compares two game paths ignoring trailing separators
+fn is_same_path(a: &str, b: &str) -> bool {
+    let a = a.trim_end_matches(['/', '\\']);
+    let b = b.trim_end_matches(['/', '\\']);
+
+    if cfg!(target_os = "windows") {
+        a.eq_ignore_ascii_case(b)
+    } else {
+        a == b
+    }
+}
+
+/// Default label shown for an auto-detected install
+fn default_label(install_type: &InstallType, game_path: &str) -> String {
+    format!("{install_type:?} ({game_path})")
+}
+
+/// Finds Titanfall2 in every library of every detected Steam install
+fn discover_steam_installs() -> Vec<GameInstall> {
+    let mut game_installs: Vec<GameInstall> = vec![];
+
+    let steamdirs = match steamlocate::locate_all() {
+        Ok(steamdirs) => steamdirs,
+        Err(err) => {
+            log::info!("Couldn't locate Steam on this computer! {}", err);
+            return game_installs;
+        }
+    };
+
+    for steamdir in steamdirs {
+        #[cfg(target_os = "linux")]
+        {
+            let snap_dir = match std::env::var("SNAP_USER_DATA") {
+                Ok(snap_dir) => std::path::PathBuf::from(snap_dir),
+                Err(_) => match dirs::home_dir() {
+                    Some(path) => path,
+                    None => std::path::PathBuf::new(),
+                }
+                .join("snap"),
+            };
+
+            if steamdir.path().starts_with(snap_dir) {
+                log::warn!("Found Steam installed via Snap, you may encounter issues");
+            }
+        }
+
+        let libraries = match steamdir.libraries() {
+            Ok(libraries) => libraries,
+            Err(err) => {
+                log::info!(
+                    "Couldn't read Steam libraries of {}: {}",
+                    steamdir.path().display(),
+                    err
+                );
+                continue;
+            }
+        };
+
+        for library in libraries.filter_map(Result::ok) {
+            let app = match library.app(thermite::TITANFALL2_STEAM_ID) {
+                Some(Ok(app)) => app,
+                Some(Err(err)) => {
+                    log::info!(
+                        "Something went wrong while trying to find Titanfall 2 {}",
+                        err
+                    );
+                    continue;
+                }
+                None => continue,
+            };
+
+            let app_path = library.resolve_app_dir(&app);
+            game_installs.push(GameInstall {
+                game_path: app_path.display().to_string(),
+                profile: NORTHSTAR_DEFAULT_PROFILE.to_string(),
+                install_type: InstallType::STEAM,
+                wine_prefix: None,
+            });
+        }
+    }
+
+    game_installs
+}
+
+/// Finds all Titanfall2 installs on this computer
+/// Only installs that pass `check_is_valid_game_path` are returned
+pub fn discover_game_installs() -> Vec<GameInstall> {
+    let mut game_installs: Vec<GameInstall> = vec![];
+
+    // Linux specific layouts come first as they also know about the wine prefix
+    #[cfg(target_os = "linux")]
+    game_installs.extend(crate::platform_specific::linux::discover_game_installs());
+
+    game_installs.extend(discover_steam_installs());
+
+    // (On Windows only) try parsing Windows registry for Origin install path
+    #[cfg(target_os = "windows")]
+    match windows::origin_install_location_detection() {
+        Ok(game_path) => game_installs.push(GameInstall {
+            game_path,
+            profile: NORTHSTAR_DEFAULT_PROFILE.to_string(),
+            install_type: InstallType::ORIGIN,
+            wine_prefix: None,
+        }),
+        Err(err) => {
+            log::info!("{}", err);
+        }
+    };
+
+    filter_valid_installs(game_installs)
+}
+
+/// Drops invalid installs and duplicates found by more than one detection method
+/// The first install found for a path is kept
+fn filter_valid_installs(game_installs: Vec<GameInstall>) -> Vec<GameInstall> {
+    let mut valid_installs: Vec<GameInstall> = vec![];
+    for game_install in game_installs {
+        if let Err(err) = check_is_valid_game_path(&game_install.game_path) {
+            log::warn!("{err}");
+            continue;
+        }
+        if valid_installs
+            .iter()
+            .any(|known| is_same_path(&known.game_path, &game_install.game_path))
+        {
+            continue;
+        }
+        valid_installs.push(game_install);
+    }
+
+    valid_installs
+}
+
+/// Returns all known Titanfall2 installs
+#[tauri::command]
+pub async fn get_game_installs(
+    app: tauri::AppHandle,
+) -> Result<Vec<RegisteredGameInstall>, String> {
+    let mut registry = GameInstallRegistry::load(&app)?;
+    registry.revalidate();
+    Ok(registry.installs)
+}
+
+/// Searches for Titanfall2 installs and adds newly found ones to the list of known installs
+#[tauri::command]
+pub async fn discover_game_installs_wrapper(
+    app: tauri::AppHandle,
+) -> Result<Vec<RegisteredGameInstall>, String> {
+    let mut registry = GameInstallRegistry::load(&app)?;
+
+    for game_install in discover_game_installs() {
+        if registry.find_mut(&game_install.game_path).is_some() {
+            continue;
+        }
+
+        log::info!("Found new Titanfall2 install at {}", game_install.game_path);
+        registry.installs.push(RegisteredGameInstall {
+            label: default_label(&game_install.install_type, &game_install.game_path),
+            game_path: game_install.game_path,
+            profile: game_install.profile,
+            install_type: game_install.install_type,
+            wine_prefix: game_install.wine_prefix,
+            user_added: false,
+            is_valid: true,
+        });
+    }
+
+    // Make the first install the active one if none was selected yet
+    if registry.active_game_path.is_none() {
+        registry.active_game_path = registry
+            .installs
+            .first()
+            .map(|install| install.game_path.clone());
+    }
+
+    registry.revalidate();
+    registry.save(&app)?;
+    Ok(registry.installs)
+}
+
+/// Adds a manually selected Titanfall2 install
+#[tauri::command]
+pub async fn add_game_install(
+    app: tauri::AppHandle,
+    game_path: String,
+    label: Option<String>,
+    install_type: Option<InstallType>,
+    wine_prefix: Option<String>,
+) -> Result<RegisteredGameInstall, String> {
+    check_is_valid_game_path(&game_path)?;
+
+    let mut registry = GameInstallRegistry::load(&app)?;
+    if registry.find_mut(&game_path).is_some() {
+        return Err(format!("{game_path} is already known"));
+    }
+
+    let install_type = install_type.unwrap_or(InstallType::UNKNOWN);
+    let game_install = RegisteredGameInstall {
+        label: label.unwrap_or(default_label(&install_type, &game_path)),
+        game_path,
+        profile: NORTHSTAR_DEFAULT_PROFILE.to_string(),
+        install_type,
+        wine_prefix,
+        user_added: true,
+        is_valid: true,
+    };
+
+    registry.installs.push(game_install.clone());
+    registry.save(&app)?;
+    Ok(game_install)
+}
+
+/// Removes a Titanfall2 install from the list of known installs
+/// Does NOT touch any files on disk
+#[tauri::command]
+pub async fn remove_game_install(app: tauri::AppHandle, game_path: String) -> Result<(), String> {
+    let mut registry = GameInstallRegistry::load(&app)?;
+
+    let previous_len = registry.installs.len();
+    registry
+        .installs
+        .retain(|install| !is_same_path(&install.game_path, &game_path));
+    if registry.installs.len() == previous_len {
+        return Err(format!("{game_path} is not a known install"));
+    }
+
+    if registry
+        .active_game_path
+        .as_ref()
+        .is_some_and(|active| is_same_path(active, &game_path))
+    {
+        registry.active_game_path = None;
+    }
+
+    registry.save(&app)
+}
+
+/// Sets the label shown for a known Titanfall2 install
+#[tauri::command]
+pub async fn set_game_install_label(
+    app: tauri::AppHandle,
+    game_path: String,
+    label: String,
+) -> Result<(), String> {
+    let mut registry = GameInstallRegistry::load(&app)?;
+
+    match registry.find_mut(&game_path) {
+        Some(install) => install.label = label,
+        None => return Err(format!("{game_path} is not a known install")),
+    }
+
+    registry.save(&app)
+}
+
+/// Switches to a known Titanfall2 install and returns it
+#[tauri::command]
+pub async fn set_active_game_install(
+    app: tauri::AppHandle,
+    game_path: String,
+    profile: Option<String>,
+) -> Result<GameInstall, String> {
+    let mut registry = GameInstallRegistry::load(&app)?;
+
+    let install = match registry.find_mut(&game_path) {
+        Some(install) => install,
+        None => return Err(format!("{game_path} is not a known install")),
+    };
+    check_is_valid_game_path(&install.game_path)?;
+
+    // Remember last used profile per install
+    if let Some(profile) = profile {
+        if !is_valid_profile_name(&profile)
+            || !validate_profile(install.to_game_install(), profile.clone())
+        {
+            return Err(format!("{profile} is not a valid Profile"));
+        }
+        install.profile = profile;
+    }
+
+    let game_install = install.to_game_install();
+    registry.active_game_path = Some(game_install.game_path.clone());
+    registry.save(&app)?;
+
+    Ok(game_install)
+}
+
+/// Returns the currently selected Titanfall2 install if any
+#[tauri::command]
+pub async fn get_active_game_install(app: tauri::AppHandle) -> Result<Option<GameInstall>, String> {
+    let mut registry = GameInstallRegistry::load(&app)?;
+
+    let Some(active_game_path) = registry.active_game_path.clone() else {
+        return Ok(None);
+    };
+
+    Ok(registry
+        .find_mut(&active_game_path)
+        .map(|install| install.to_game_install()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game_install(game_path: &str, install_type: InstallType) -> GameInstall {
+        GameInstall {
+            game_path: game_path.to_string(),
+            profile: NORTHSTAR_DEFAULT_PROFILE.to_string(),
+            install_type,
+            wine_prefix: None,
+        }
+    }
+
+    #[test]
+    fn test_is_same_path() {
+        assert!(is_same_path("/games/Titanfall2", "/games/Titanfall2/"));
+        assert!(is_same_path(
+            "C:\\Games\\Titanfall2\\",
+            "C:\\Games\\Titanfall2"
+        ));
+        assert!(!is_same_path("/games/Titanfall2", "/games/Titanfall2-old"));
+        assert_eq!(
+            is_same_path("/games/Titanfall2", "/games/titanfall2"),
+            cfg!(target_os = "windows")
+        );
+    }
+
+    #[test]
+    fn test_filter_valid_installs() {
+        let games_dir = tempfile::tempdir().unwrap();
+        let game_path = games_dir.path().join("Titanfall2");
+        std::fs::create_dir(&game_path).unwrap();
+        std::fs::write(game_path.join("Titanfall2.exe"), "").unwrap();
+        let game_path = game_path.display().to_string();
+
+        let mut wine_install = game_install(&game_path, InstallType::ORIGIN);
+        wine_install.wine_prefix = Some("/prefixes/ea-app".to_string());
+        let game_installs = vec![
+            wine_install,
+            game_install(&format!("{game_path}/"), InstallType::STEAM),
+            game_install(
+                &games_dir.path().join("missing").display().to_string(),
+                InstallType::STEAM,
+            ),
+        ];
+
+        let valid_installs = filter_valid_installs(game_installs);
+        assert_eq!(valid_installs.len(), 1);
+        assert!(matches!(
+            valid_installs[0].install_type,
+            InstallType::ORIGIN
+        ));
+        assert_eq!(
+            valid_installs[0].wine_prefix.as_deref(),
+            Some("/prefixes/ea-app")
+        );
+    }
+
+    #[test]
+    fn test_registry_find() {
+        let mut registry = GameInstallRegistry {
+            installs: vec![RegisteredGameInstall {
+                label: "Steam".to_string(),
+                game_path: "/games/Titanfall2".to_string(),
+                profile: NORTHSTAR_DEFAULT_PROFILE.to_string(),
+                install_type: InstallType::STEAM,
+                wine_prefix: None,
+                user_added: false,
+                is_valid: true,
+            }],
+            active_game_path: None,
+        };
+
+        assert!(registry.find_mut("/games/Titanfall2/").is_some());
+        assert!(registry.find_mut("/games/Titanfall").is_none());
+    }
+}
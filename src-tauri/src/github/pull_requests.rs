@@ -1,14 +1,14 @@
+use crate::archive::{self, ExtractLimits};
 use crate::constants::{APP_USER_AGENT, NORTHSTAR_LAUNCHER_REPO_NAME, NORTHSTAR_MODS_REPO_NAME};
+use crate::download::{download_file, ExpectedFile};
 use crate::repair_and_verify::check_is_valid_game_path;
 use crate::GameInstall;
 use anyhow::anyhow;
 use serde::{Deserialize, Serialize};
-use std::fs::{self, File};
-use std::io;
+use std::fs::File;
 use std::io::prelude::*;
 use std::path::Path;
 use ts_rs::TS;
-use zip::read::root_dir_common_filter;
 
 #[derive(Serialize, Deserialize, Debug, Clone, TS)]
 #[ts(export)]
@@ -159,20 +159,44 @@ pub async fn check_github_api(url: &str) -> Result<serde_json::Value, Box<dyn st
     Ok(json)
 }
 
-/// Downloads a file from given URL into an array in memory
-pub async fn download_zip_into_memory(download_url: String) -> Result<Vec<u8>, anyhow::Error> {
-    let client = reqwest::Client::builder()
-        .user_agent(APP_USER_AGENT)
-        .build()?;
-
-    let response = client.get(download_url).send().await?;
+/// Downloads a zip archive from given URL into the given directory and opens it
+pub async fn download_zip(download_url: String, directory: &Path) -> Result<File, anyhow::Error> {
+    let path = directory.join("archive.zip");
+    download_file(
+        &download_url,
+        &path,
+        &ExpectedFile::default(),
+        |_, _| {},
+        None,
+    )
+    .await?;
+    Ok(File::open(path)?)
+}
 
-    if !response.status().is_success() {
-        return Err(anyhow!("Request unsuccessful: {}", response.status()));
+/// Files of a launcher build that are copied into the game folder
+const LAUNCHER_FILES: [&str; 2] = ["NorthstarLauncher.exe", "Northstar.dll"];
+
+/// Extracts only the launcher files of a launcher build archive into the game folder
+pub fn install_launcher_files(archive: File, game_path: &str) -> Result<(), String> {
+    let extracted = archive::extract(
+        archive,
+        Path::new(game_path),
+        &ExtractLimits::default(),
+        |path| {
+            LAUNCHER_FILES
+                .iter()
+                .any(|file| path == Path::new(file))
+                .then(|| path.to_path_buf())
+        },
+    )
+    .map_err(|err| format!("{err:#}"))?;
+
+    for file in LAUNCHER_FILES {
+        if !extracted.iter().any(|path| path == Path::new(file)) {
+            return Err(format!("Archive is missing {file}"));
+        }
     }
-
-    let bytes = response.bytes().await?;
-    Ok(bytes.to_vec())
+    Ok(())
 }
 
 /// Gets GitHub download link of a mods PR
@@ -282,39 +306,13 @@ pub async fn apply_launcher_pr(
         }
     };
 
-    let archive = match download_zip_into_memory(download_url).await {
+    // Use a temp dir to store archive
+    let temp_dir = tempfile::tempdir().map_err(|err| err.to_string())?;
+    let archive = match download_zip(download_url, temp_dir.path()).await {
         Ok(archive) => archive,
-        Err(err) => return Err(err.to_string()),
+        Err(err) => return Err(format!("{err:#}")),
     };
-
-    // Use a temp file to store archive
-    let mut tmpfile = tempfile::tempfile().unwrap();
-    match tmpfile.write_all(&archive) {
-        Ok(_) => (),
-        Err(err) => return Err(err.to_string()),
-    }
-    let mut zip = zip::ZipArchive::new(tmpfile).unwrap();
-
-    // Copy only necessary files from temp dir
-    // Copy:
-    // - NorthstarLauncher.exe
-    // - Northstar.dll
-    let files_to_copy = vec!["NorthstarLauncher.exe", "Northstar.dll"];
-    for file_name in files_to_copy {
-        let mut zip_file = match zip.by_name(file_name) {
-            Ok(file) => file,
-            Err(err) => return Err(err.to_string()),
-        };
-        let destination_file_path = format!("{}/{}", game_install.game_path, file_name);
-        let mut file = match fs::File::create(Path::new(&destination_file_path)) {
-            Ok(f) => f,
-            Err(err) => return Err(err.to_string()),
-        };
-        match io::copy(&mut zip_file, &mut file) {
-            Ok(_) => (),
-            Err(err) => return Err(err.to_string()),
-        };
-    }
+    install_launcher_files(archive, &game_install.game_path)?;
 
     log::info!("All done with installing launcher PR");
     Ok(())
@@ -334,9 +332,11 @@ pub async fn apply_mods_pr(
         Err(err) => return Err(err.to_string()),
     };
 
-    let archive = match download_zip_into_memory(download_url).await {
+    // Use a temp dir to store archive
+    let temp_dir = tempfile::tempdir().map_err(|err| err.to_string())?;
+    let archive = match download_zip(download_url, temp_dir.path()).await {
         Ok(archive) => archive,
-        Err(err) => return Err(err.to_string()),
+        Err(err) => return Err(format!("{err:#}")),
     };
 
     let profile_folder = format!(
@@ -361,18 +361,11 @@ pub async fn apply_mods_pr(
 
     let target_dir = std::path::PathBuf::from(format!("{profile_folder}/mods")); // Doesn't need to exist
 
-    // Use a temp file to store archive
-    let mut tmpfile = tempfile::tempfile().unwrap();
-    match tmpfile.write_all(&archive) {
-        Ok(_) => (),
-        Err(err) => return Err(err.to_string()),
-    }
-
     // Extract the archive to game profile
-    let mut zip = zip::ZipArchive::new(tmpfile).unwrap();
-    match zip.extract_unwrapped_root_dir(&target_dir, root_dir_common_filter) {
-        Ok(()) => (),
-        Err(err) => return Err(err.to_string()),
+    if let Err(err) =
+        archive::extract_unwrapped_root_dir(archive, &target_dir, &ExtractLimits::default())
+    {
+        return Err(format!("{err:#}"));
     }
 
     // Add batch file to launch right profile
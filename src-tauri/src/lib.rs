@@ -1,17 +1,23 @@
 use std::{env, time::Duration};
 
+mod archive;
 mod constants;
+mod dedicated_server;
 mod development;
+mod download;
+mod game_installs;
 mod github;
 mod mod_management;
 mod northstar;
 mod platform_specific;
+mod process_monitor;
 mod repair_and_verify;
+mod server_browser;
 mod thunderstore;
 mod util;
 
 use serde::{Deserialize, Serialize};
-use tauri::Emitter;
+use tauri::{Emitter, Manager};
 use tokio::time::sleep;
 use ts_rs::TS;
 
@@ -65,47 +71,97 @@ pub fn run() {
                     app_handle.emit("backend-ping", "ping").unwrap();
                 }
             });
+            // Single process monitor serving EA App and Northstar status as well as game sessions
             let app_handle = app.handle().clone();
+            process_monitor::load_interval(&app_handle);
             tauri::async_runtime::spawn(async move {
                 loop {
-                    sleep(Duration::from_millis(2000)).await;
+                    let process_monitor = app_handle.state::<process_monitor::ProcessMonitor>();
+                    sleep(process_monitor.interval()).await;
+
+                    // Sessions are tracked even while FlightCore is minimized during play,
+                    // otherwise there is no need to scan processes while hidden
+                    let is_window_hidden = process_monitor::is_window_hidden(&app_handle);
+                    if is_window_hidden && !northstar::session::is_session_pending(&app_handle) {
+                        continue;
+                    }
+                    let process_snapshot = process_monitor.refresh();
+                    northstar::session::update_game_session(&app_handle, &process_snapshot);
+
+                    // Nobody to show the status to
+                    if is_window_hidden {
+                        continue;
+                    }
+                    app_handle
+                        .emit("ea-app-running-ping", process_snapshot.is_ea_app_running())
+                        .unwrap();
                     app_handle
                         .emit(
-                            "ea-app-running-ping",
-                            util::check_ea_app_or_origin_running(),
+                            "northstar-running-ping",
+                            process_snapshot.is_northstar_running(),
                         )
                         .unwrap();
                 }
             });
-            let app_handle = app.handle().clone();
+
+            // Keep the Thunderstore index used for searching up to date
             tauri::async_runtime::spawn(async move {
                 loop {
-                    sleep(Duration::from_millis(2000)).await;
-                    app_handle
-                        .emit("northstar-running-ping", util::check_northstar_running())
-                        .unwrap();
+                    if let Err(err) = thunderstore::index::get_packages().await {
+                        log::warn!("{err}");
+                    }
+                    sleep(thunderstore::index::INDEX_TTL).await;
                 }
             });
 
-            // Emit updated player and server count to GUI
+            // Record player and server count of the master servers in use
+            // and emit the one of the active profile to GUI
             let app_handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
                 loop {
                     sleep(constants::REFRESH_DELAY).await;
-                    app_handle
-                        .emit(
-                            "northstar-statistics",
-                            util::get_server_player_count().await,
+                    let master_server_urls =
+                        server_browser::master_server::get_selected_master_server_urls(&app_handle)
+                            .await;
+                    for (i, master_server_url) in master_server_urls.iter().enumerate() {
+                        let statistics = server_browser::statistics::record_statistics(
+                            &app_handle,
+                            master_server_url,
                         )
-                        .unwrap();
+                        .await;
+                        if i == 0 {
+                            app_handle.emit("northstar-statistics", statistics).unwrap();
+                        }
+                    }
                 }
             });
 
             Ok(())
         })
         .manage(())
+        .manage(dedicated_server::DedicatedServerManager::default())
+        .manage(mod_management::install_queue::InstallQueue::default())
+        .manage(northstar::LaunchedNorthstar::default())
+        .manage(northstar::relaunch::PendingModOperations::default())
+        .manage(northstar::session::GameSessionTracker::default())
+        .manage(process_monitor::ProcessMonitor::default())
         .invoke_handler(tauri::generate_handler![
+            dedicated_server::get_dedicated_server_status,
+            dedicated_server::get_dedicated_servers,
+            dedicated_server::remove_dedicated_server,
+            dedicated_server::save_dedicated_server,
+            dedicated_server::server_config::get_server_config,
+            dedicated_server::server_config::set_server_config,
+            dedicated_server::start_dedicated_server,
+            dedicated_server::stop_dedicated_server,
             development::install_git_main,
+            game_installs::add_game_install,
+            game_installs::discover_game_installs_wrapper,
+            game_installs::get_active_game_install,
+            game_installs::get_game_installs,
+            game_installs::remove_game_install,
+            game_installs::set_active_game_install,
+            game_installs::set_game_install_label,
             github::compare_tags,
             github::get_list_of_tags,
             github::pull_requests::apply_launcher_pr,
@@ -120,38 +176,86 @@ pub fn run() {
             mod_management::delete_thunderstore_mod,
             mod_management::get_installed_mods_and_properties,
             mod_management::install_mod_wrapper,
+            mod_management::install_queue::cancel_mod_install,
+            mod_management::install_queue::get_install_queue,
+            mod_management::install_queue::get_install_queue_parallel_limit,
+            mod_management::install_queue::queue_mod_install,
+            mod_management::install_queue::set_install_queue_parallel_limit,
+            mod_management::northstar_requirement::check_northstar_requirement,
+            mod_management::r2modman::import_r2modman_profile_code,
+            mod_management::r2modman::import_r2modman_profile_file,
             mod_management::set_mod_enabled_status,
             northstar::check_is_northstar_outdated,
+            northstar::check_profiles_northstar_outdated,
             northstar::get_available_northstar_versions,
             northstar::get_northstar_version_number,
             northstar::install::find_game_install_location,
             northstar::install::install_northstar_wrapper,
             northstar::install::update_northstar,
+            northstar::launch_checks::run_launch_checks_wrapper,
             northstar::launch_northstar,
             northstar::profile::clone_profile,
             northstar::profile::delete_profile,
             northstar::profile::fetch_profiles,
+            northstar::profile::get_profile_launch_options,
+            northstar::profile::set_profile_launch_options,
             northstar::profile::validate_profile,
+            northstar::relaunch::clear_pending_mod_operations,
+            northstar::relaunch::get_pending_mod_operations,
+            northstar::relaunch::queue_mod_operation,
+            northstar::relaunch::relaunch_northstar,
+            northstar::relaunch::stop_northstar,
+            northstar::session::get_active_game_session,
+            northstar::session::get_game_session_history,
+            northstar::session::get_profile_playtime,
             platform_specific::check_cgnat,
             platform_specific::get_host_os,
+            platform_specific::get_linux_runners,
             platform_specific::get_local_northstar_proton_wrapper_version,
+            platform_specific::get_selected_linux_runner,
             platform_specific::install_northstar_proton_wrapper,
+            platform_specific::set_selected_linux_runner,
             platform_specific::uninstall_northstar_proton_wrapper,
+            process_monitor::get_process_monitor_interval,
+            process_monitor::set_process_monitor_interval,
             repair_and_verify::clean_up_download_folder_wrapper,
             repair_and_verify::disable_all_but_core,
             repair_and_verify::get_log_list,
             repair_and_verify::verify_game_files,
             repair_and_verify::verify_install_location,
+            server_browser::get_server_list,
+            server_browser::favorites::add_favorite_server,
+            server_browser::favorites::get_favorite_servers,
+            server_browser::favorites::get_recent_servers,
+            server_browser::favorites::get_saved_server_status,
+            server_browser::favorites::remove_favorite_server,
+            server_browser::master_server::add_master_server,
+            server_browser::master_server::get_master_servers,
+            server_browser::master_server::remove_master_server,
+            server_browser::master_server::set_master_server,
+            server_browser::statistics::get_statistics_history,
+            server_browser::get_server_player_count,
+            server_browser::required_mods::install_server_required_mods,
+            thunderstore::audit::audit_installed_packages,
+            thunderstore::package_docs::get_package_changes_since_installed,
+            thunderstore::package_docs::get_package_docs,
             thunderstore::query_thunderstore_packages_api,
+            thunderstore::search::search_thunderstore_packages,
             util::close_application,
             util::force_panic,
             util::get_flightcore_version_number,
-            util::get_server_player_count,
             util::is_debug_mode,
             util::kill_northstar,
             util::open_repair_window,
         ])
-        .run(tauri::generate_context!());
+        .build(tauri::generate_context!())
+        .map(|app| {
+            app.run(|app_handle, event| {
+                if let tauri::RunEvent::Exit = event {
+                    dedicated_server::stop_all_dedicated_servers(app_handle);
+                }
+            })
+        });
 
     match tauri_builder_res {
         Ok(()) => (),
@@ -188,11 +292,14 @@ pub enum InstallType {
 /// - Install path
 /// - Active profile
 /// - Type of installation (Steam, Origin, ...)
+/// - Wine prefix the game runs in (Linux only)
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GameInstall {
     pub game_path: String,
     pub profile: String,
     pub install_type: InstallType,
+    #[serde(default)]
+    pub wine_prefix: Option<String>,
 }
 
 /// Object holding various information about a Northstar mod
@@ -0,0 +1,572 @@
+//! Queue for installing several packages at once
+//!
+//! Up to the configured number of packages are downloaded at the same time, each reporting its
+//! progress through `mod-install-progress` events. Extracting into a profile and removing older
+//! versions of a package only ever happens for one package per profile at a time.
+//!
+//! Packages only start once their dependencies are installed. If a dependency fails or is
+//! cancelled, so are the packages waiting for it.
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use ts_rs::TS;
+
+use crate::download::{download_file, ExpectedFile};
+use crate::mod_management::{
+    check_mod_installable, get_installable_dependencies, get_ns_mod_version,
+    install_downloaded_mod, TempFile,
+};
+use crate::util::{get_stored_value, set_stored_value};
+use crate::GameInstall;
+
+/// Store key of the number of packages downloaded at the same time
+const PARALLEL_LIMIT_KEY: &str = "install-queue-parallel-downloads";
+
+const DEFAULT_PARALLEL_LIMIT: usize = 3;
+
+const MAX_PARALLEL_LIMIT: usize = 8;
+
+/// Event emitted whenever a queued install changes
+const PROGRESS_EVENT: &str = "mod-install-progress";
+
+/// Minimum time between two download progress events of the same install
+const PROGRESS_EMIT_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+#[ts(export)]
+pub enum InstallQueueItemState {
+    Queued,
+    Downloading,
+    Extracting,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+/// A package install in the queue
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct InstallQueueItem {
+    pub id: u32,
+    pub thunderstore_mod_string: String,
+    pub game_path: String,
+    pub profile: String,
+    pub state: InstallQueueItemState,
+    pub downloaded: u64,
+    /// `0` while the size is unknown
+    pub total_size: u64,
+    /// Set if the install failed
+    pub error: Option<String>,
+    /// Ids of the queued installs that have to finish first
+    pub dependencies: Vec<u32>,
+}
+
+struct QueueEntry {
+    item: InstallQueueItem,
+    game_install: GameInstall,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Queued and running installs
+/// Finished installs are removed after their final progress event
+#[derive(Default)]
+pub struct InstallQueue {
+    entries: Mutex<Vec<QueueEntry>>,
+    next_id: AtomicU32,
+    /// Guards the steps changing files of a profile, keyed by profile directory
+    profile_locks: Mutex<HashMap<PathBuf, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+impl InstallQueue {
+    fn profile_lock(&self, game_install: &GameInstall) -> Arc<tokio::sync::Mutex<()>> {
+        let profile_directory = PathBuf::from(&game_install.game_path).join(&game_install.profile);
+        self.profile_locks
+            .lock()
+            .unwrap()
+            .entry(profile_directory)
+            .or_default()
+            .clone()
+    }
+}
+
+fn get_parallel_limit(app: &AppHandle) -> usize {
+    match get_stored_value::<usize>(app, PARALLEL_LIMIT_KEY) {
+        Ok(limit) => limit.unwrap_or(DEFAULT_PARALLEL_LIMIT),
+        Err(err) => {
+            log::warn!("Couldn't read install queue limit: {err}");
+            DEFAULT_PARALLEL_LIMIT
+        }
+    }
+    .clamp(1, MAX_PARALLEL_LIMIT)
+}
+
+/// Ids of the queued installs that can start downloading without exceeding `limit`
+/// Installs wait until all their dependencies finished and left the queue
+fn items_to_start(items: &[&InstallQueueItem], limit: usize) -> Vec<u32> {
+    let downloading = items
+        .iter()
+        .filter(|item| item.state == InstallQueueItemState::Downloading)
+        .count();
+    items
+        .iter()
+        .filter(|item| item.state == InstallQueueItemState::Queued)
+        .filter(|item| {
+            item.dependencies
+                .iter()
+                .all(|dependency| !items.iter().any(|other| other.id == *dependency))
+        })
+        .take(limit.saturating_sub(downloading))
+        .map(|item| item.id)
+        .collect()
+}
+
+/// Ids of the queued installs that directly or indirectly depend on `id`
+fn dependents_of(items: &[&InstallQueueItem], id: u32) -> Vec<u32> {
+    let mut dependents: Vec<u32> = vec![];
+    let mut pending = vec![id];
+    while let Some(current) = pending.pop() {
+        for item in items {
+            if item.state == InstallQueueItemState::Queued
+                && item.dependencies.contains(&current)
+                && !dependents.contains(&item.id)
+            {
+                dependents.push(item.id);
+                pending.push(item.id);
+            }
+        }
+    }
+    dependents
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Visit {
+    Pending,
+    InProgress,
+    Done,
+}
+
+/// Drops dependencies that would make packages wait for each other in a cycle
+/// `dependencies` holds the indices of the packages each package depends on
+fn remove_dependency_cycles(dependencies: &mut [Vec<usize>]) {
+    fn visit(package: usize, dependencies: &mut [Vec<usize>], visits: &mut [Visit]) {
+        visits[package] = Visit::InProgress;
+        let mut index = 0;
+        while index < dependencies[package].len() {
+            let dependency = dependencies[package][index];
+            match visits[dependency] {
+                Visit::InProgress => {
+                    dependencies[package].remove(index);
+                    continue;
+                }
+                Visit::Pending => visit(dependency, dependencies, visits),
+                Visit::Done => {}
+            }
+            index += 1;
+        }
+        visits[package] = Visit::Done;
+    }
+
+    let mut visits = vec![Visit::Pending; dependencies.len()];
+    for package in 0..dependencies.len() {
+        if visits[package] == Visit::Pending {
+            visit(package, dependencies, &mut visits);
+        }
+    }
+}
+
+fn emit_progress(app: &AppHandle, item: &InstallQueueItem) {
+    if let Err(err) = app.emit(PROGRESS_EVENT, item) {
+        log::warn!("Failed emitting install progress: {err}");
+    }
+}
+
+/// Applies `update` to the queued install and notifies the frontend
+fn update_item(app: &AppHandle, id: u32, update: impl FnOnce(&mut InstallQueueItem)) {
+    let install_queue = app.state::<InstallQueue>();
+    let mut entries = install_queue.entries.lock().unwrap();
+    if let Some(entry) = entries.iter_mut().find(|entry| entry.item.id == id) {
+        update(&mut entry.item);
+        emit_progress(app, &entry.item);
+    }
+}
+
+/// Starts downloading queued installs as long as the parallel limit allows
+fn start_pending(app: &AppHandle) {
+    let limit = get_parallel_limit(app);
+    let install_queue = app.state::<InstallQueue>();
+    let mut entries = install_queue.entries.lock().unwrap();
+
+    let items: Vec<&InstallQueueItem> = entries.iter().map(|entry| &entry.item).collect();
+    for id in items_to_start(&items, limit) {
+        if let Some(entry) = entries.iter_mut().find(|entry| entry.item.id == id) {
+            entry.item.state = InstallQueueItemState::Downloading;
+            emit_progress(app, &entry.item);
+
+            let app = app.clone();
+            let game_install = entry.game_install.clone();
+            let thunderstore_mod_string = entry.item.thunderstore_mod_string.clone();
+            let cancelled = entry.cancelled.clone();
+            tauri::async_runtime::spawn(async move {
+                let result = run_install(
+                    &app,
+                    id,
+                    &game_install,
+                    &thunderstore_mod_string,
+                    &cancelled,
+                )
+                .await;
+                finish_install(&app, id, result, &cancelled);
+            });
+        }
+    }
+}
+
+/// Removes the installs waiting for the given failed or cancelled one, they end the same way
+fn remove_dependents(
+    app: &AppHandle,
+    entries: &mut Vec<QueueEntry>,
+    dependency: &InstallQueueItem,
+) {
+    let items: Vec<&InstallQueueItem> = entries.iter().map(|entry| &entry.item).collect();
+    for id in dependents_of(&items, dependency.id) {
+        let Some(position) = entries.iter().position(|entry| entry.item.id == id) else {
+            continue;
+        };
+        let mut entry = entries.remove(position);
+        if dependency.state == InstallQueueItemState::Cancelled {
+            entry.item.state = InstallQueueItemState::Cancelled;
+        } else {
+            entry.item.state = InstallQueueItemState::Failed;
+            entry.item.error = Some(format!(
+                "Dependency {} failed to install",
+                dependency.thunderstore_mod_string
+            ));
+        }
+        emit_progress(app, &entry.item);
+    }
+}
+
+/// Reports the outcome of an install, removes it from the queue and starts the next ones
+fn finish_install(app: &AppHandle, id: u32, result: Result<(), String>, cancelled: &AtomicBool) {
+    {
+        let install_queue = app.state::<InstallQueue>();
+        let mut entries = install_queue.entries.lock().unwrap();
+        if let Some(position) = entries.iter().position(|entry| entry.item.id == id) {
+            let mut entry = entries.remove(position);
+            match result {
+                Ok(()) => entry.item.state = InstallQueueItemState::Done,
+                Err(_) if cancelled.load(Ordering::Relaxed) => {
+                    entry.item.state = InstallQueueItemState::Cancelled
+                }
+                Err(err) => {
+                    log::warn!(
+                        "Failed installing {}: {err}",
+                        entry.item.thunderstore_mod_string
+                    );
+                    entry.item.state = InstallQueueItemState::Failed;
+                    entry.item.error = Some(err);
+                }
+            }
+            emit_progress(app, &entry.item);
+            if entry.item.state != InstallQueueItemState::Done {
+                remove_dependents(app, &mut entries, &entry.item);
+            }
+        }
+    }
+    start_pending(app);
+}
+
+/// Downloads the package, then extracts it once no other install changes the profile
+async fn run_install(
+    app: &AppHandle,
+    id: u32,
+    game_install: &GameInstall,
+    thunderstore_mod_string: &str,
+    cancelled: &AtomicBool,
+) -> Result<(), String> {
+    let mod_version = get_ns_mod_version(thunderstore_mod_string).await?;
+
+    let download_directory = PathBuf::from(&game_install.game_path)
+        .join("___flightcore-temp")
+        .join("download-dir");
+    std::fs::create_dir_all(&download_directory).map_err(|err| err.to_string())?;
+    // The profile keeps installs of the same package into different profiles apart
+    let path = download_directory.join(format!(
+        "{thunderstore_mod_string}-{}.zip",
+        game_install.profile
+    ));
+
+    let expected = ExpectedFile {
+        size: (mod_version.file_size > 0).then_some(mod_version.file_size),
+        sha256: None,
+    };
+    let mut downloaded: u64 = 0;
+    let mut total_size: u64 = 0;
+    let mut last_emit = Instant::now();
+    download_file(
+        &mod_version.url,
+        &path,
+        &expected,
+        |current, total| {
+            downloaded = current;
+            total_size = total;
+            // Don't bombard the frontend with events on fast download speeds
+            if last_emit.elapsed() >= PROGRESS_EMIT_INTERVAL {
+                update_item(app, id, |item| {
+                    item.downloaded = current;
+                    item.total_size = total;
+                });
+                last_emit = Instant::now();
+            }
+        },
+        Some(cancelled),
+    )
+    .await
+    .map_err(|err| format!("{err:#}"))?;
+    let temp_file = TempFile::new(
+        std::fs::File::open(&path).map_err(|err| err.to_string())?,
+        path,
+    );
+
+    let profile_lock = app.state::<InstallQueue>().profile_lock(game_install);
+    let _profile_guard = profile_lock.lock().await;
+    // Cancelling is possible until extracting starts, never during it
+    {
+        let install_queue = app.state::<InstallQueue>();
+        let mut entries = install_queue.entries.lock().unwrap();
+        if cancelled.load(Ordering::Relaxed) {
+            return Err("Install was cancelled".to_string());
+        }
+        if let Some(entry) = entries.iter_mut().find(|entry| entry.item.id == id) {
+            entry.item.state = InstallQueueItemState::Extracting;
+            entry.item.downloaded = downloaded;
+            entry.item.total_size = total_size.max(downloaded);
+            emit_progress(app, &entry.item);
+        }
+    }
+
+    let game_install = game_install.clone();
+    let thunderstore_mod_string = thunderstore_mod_string.to_string();
+    tokio::task::spawn_blocking(move || {
+        install_downloaded_mod(&game_install, &thunderstore_mod_string, temp_file.file())
+    })
+    .await
+    .map_err(|err| err.to_string())?
+}
+
+/// Adds the given package and its dependencies to the install queue
+/// Returns the ids of the queued installs, packages already queued for the profile keep theirs
+#[tauri::command]
+pub async fn queue_mod_install(
+    app: AppHandle,
+    game_install: GameInstall,
+    thunderstore_mod_string: String,
+) -> Result<Vec<u32>, String> {
+    // Resolve everything first so that nothing is queued if any package can't be installed
+    let mut packages = vec![thunderstore_mod_string];
+    // Indices into `packages` of the dependencies of each package
+    let mut dependencies: Vec<Vec<usize>> = vec![];
+    let mut index = 0;
+    while let Some(package) = packages.get(index).cloned() {
+        check_mod_installable(&game_install, &package).await?;
+        let mut package_dependencies = vec![];
+        for dependency in get_installable_dependencies(&package).await? {
+            let dependency_index = match packages.iter().position(|other| *other == dependency) {
+                Some(dependency_index) => dependency_index,
+                None => {
+                    packages.push(dependency);
+                    packages.len() - 1
+                }
+            };
+            package_dependencies.push(dependency_index);
+        }
+        dependencies.push(package_dependencies);
+        index += 1;
+    }
+    remove_dependency_cycles(&mut dependencies);
+
+    let install_queue = app.state::<InstallQueue>();
+    let ids = {
+        let mut entries = install_queue.entries.lock().unwrap();
+        // Packages already queued for the profile are not queued again
+        let existing_ids: Vec<Option<u32>> = packages
+            .iter()
+            .map(|package| {
+                entries
+                    .iter()
+                    .find(|entry| {
+                        entry.item.thunderstore_mod_string == *package
+                            && entry.game_install.game_path == game_install.game_path
+                            && entry.game_install.profile == game_install.profile
+                    })
+                    .map(|entry| entry.item.id)
+            })
+            .collect();
+        let ids: Vec<u32> = existing_ids
+            .iter()
+            .map(|existing_id| {
+                existing_id.unwrap_or_else(|| install_queue.next_id.fetch_add(1, Ordering::Relaxed))
+            })
+            .collect();
+
+        for (index, package) in packages.into_iter().enumerate() {
+            if existing_ids[index].is_some() {
+                continue;
+            }
+
+            let item = InstallQueueItem {
+                id: ids[index],
+                thunderstore_mod_string: package,
+                game_path: game_install.game_path.clone(),
+                profile: game_install.profile.clone(),
+                state: InstallQueueItemState::Queued,
+                downloaded: 0,
+                total_size: 0,
+                error: None,
+                dependencies: dependencies[index]
+                    .iter()
+                    .map(|dependency_index| ids[*dependency_index])
+                    .collect(),
+            };
+            emit_progress(&app, &item);
+            entries.push(QueueEntry {
+                item,
+                game_install: game_install.clone(),
+                cancelled: Arc::new(AtomicBool::new(false)),
+            });
+        }
+        ids
+    };
+
+    start_pending(&app);
+    Ok(ids)
+}
+
+/// Cancels a queued or downloading install together with the installs waiting for it
+/// Installs that are already being extracted run to completion
+#[tauri::command]
+pub fn cancel_mod_install(app: AppHandle, id: u32) -> Result<(), String> {
+    let install_queue = app.state::<InstallQueue>();
+    let mut entries = install_queue.entries.lock().unwrap();
+    let Some(position) = entries.iter().position(|entry| entry.item.id == id) else {
+        return Err(format!("No queued install with id {id}"));
+    };
+
+    let entry = &mut entries[position];
+    match entry.item.state {
+        InstallQueueItemState::Queued => {
+            let mut entry = entries.remove(position);
+            entry.item.state = InstallQueueItemState::Cancelled;
+            emit_progress(&app, &entry.item);
+            remove_dependents(&app, &mut entries, &entry.item);
+            Ok(())
+        }
+        InstallQueueItemState::Downloading => {
+            entry.cancelled.store(true, Ordering::Relaxed);
+            Ok(())
+        }
+        _ => Err(format!(
+            "{} is already being installed",
+            entry.item.thunderstore_mod_string
+        )),
+    }
+}
+
+/// Returns the queued and running installs
+#[tauri::command]
+pub fn get_install_queue(app: AppHandle) -> Vec<InstallQueueItem> {
+    app.state::<InstallQueue>()
+        .entries
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|entry| entry.item.clone())
+        .collect()
+}
+
+/// Returns how many packages are downloaded at the same time
+#[tauri::command]
+pub fn get_install_queue_parallel_limit(app: AppHandle) -> usize {
+    get_parallel_limit(&app)
+}
+
+/// Sets how many packages are downloaded at the same time
+#[tauri::command]
+pub fn set_install_queue_parallel_limit(app: AppHandle, limit: usize) -> Result<(), String> {
+    if !(1..=MAX_PARALLEL_LIMIT).contains(&limit) {
+        return Err(format!(
+            "Limit needs to be between 1 and {MAX_PARALLEL_LIMIT}"
+        ));
+    }
+    set_stored_value(&app, PARALLEL_LIMIT_KEY, &limit).map_err(|err| err.to_string())?;
+    start_pending(&app);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: u32, state: InstallQueueItemState, dependencies: &[u32]) -> InstallQueueItem {
+        InstallQueueItem {
+            id,
+            thunderstore_mod_string: format!("Someone-Mod{id}-1.0.0"),
+            game_path: "/games/Titanfall2".to_string(),
+            profile: "R2Northstar".to_string(),
+            state,
+            downloaded: 0,
+            total_size: 0,
+            error: None,
+            dependencies: dependencies.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_items_to_start() {
+        let items = [
+            item(0, InstallQueueItemState::Extracting, &[]),
+            item(1, InstallQueueItemState::Downloading, &[]),
+            item(2, InstallQueueItemState::Queued, &[]),
+            item(3, InstallQueueItemState::Queued, &[]),
+            item(4, InstallQueueItemState::Queued, &[]),
+        ];
+        let items: Vec<&InstallQueueItem> = items.iter().collect();
+
+        assert_eq!(items_to_start(&items, 3), vec![2, 3]);
+        assert_eq!(items_to_start(&items, 1), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn test_items_wait_for_dependencies() {
+        let items = [
+            item(0, InstallQueueItemState::Extracting, &[]),
+            // Dependency 7 already finished
+            item(1, InstallQueueItemState::Queued, &[0, 7]),
+            item(2, InstallQueueItemState::Queued, &[7]),
+            item(3, InstallQueueItemState::Queued, &[1]),
+            item(4, InstallQueueItemState::Queued, &[]),
+        ];
+        let items: Vec<&InstallQueueItem> = items.iter().collect();
+
+        assert_eq!(items_to_start(&items, 8), vec![2, 4]);
+        assert_eq!(dependents_of(&items, 0), vec![1, 3]);
+        assert_eq!(dependents_of(&items, 4), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn test_remove_dependency_cycles() {
+        // 0 -> 1 -> 2 -> 0 and 0 -> 2, 3 depends on itself
+        let mut dependencies = vec![vec![1, 2], vec![2], vec![0], vec![3]];
+        remove_dependency_cycles(&mut dependencies);
+        assert_eq!(dependencies, vec![vec![1, 2], vec![2], vec![], vec![]]);
+
+        // Shared dependencies are not cycles
+        let mut dependencies = vec![vec![1, 2], vec![2], vec![]];
+        remove_dependency_cycles(&mut dependencies);
+        assert_eq!(dependencies, vec![vec![1, 2], vec![2], vec![]]);
+    }
+}
@@ -6,6 +6,9 @@ use crate::constants::{
 use async_recursion::async_recursion;
 use thermite::prelude::ThermiteError;
 
+use crate::archive::{self, ExtractLimits};
+use crate::download::{download_file, ExpectedFile};
+use crate::thunderstore::index::get_package_index;
 use crate::NorthstarMod;
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
@@ -14,15 +17,18 @@ use std::str::FromStr;
 use std::string::ToString;
 use std::{fs, path::PathBuf};
 
+pub mod install_queue;
 mod legacy;
+pub mod northstar_requirement;
 mod plugins;
+pub mod r2modman;
 use crate::GameInstall;
 
 #[derive(Debug, Clone)]
 pub struct ParsedThunderstoreModString {
-    author_name: String,
-    mod_name: String,
-    version: String,
+    pub author_name: String,
+    pub mod_name: String,
+    pub version: String,
 }
 
 impl std::str::FromStr for ParsedThunderstoreModString {
@@ -514,9 +520,11 @@ pub fn get_installed_mods_and_properties(
     Ok(installed_mods)
 }
 
-async fn get_ns_mod_download_url(thunderstore_mod_string: &str) -> Result<String, String> {
-    // TODO: This will crash the thread if not internet connection exist. `match` should be used instead
-    let index = thermite::api::get_package_index().unwrap().to_vec();
+/// Looks up the given package version (`author-name-version`) in the index
+pub async fn get_ns_mod_version(
+    thunderstore_mod_string: &str,
+) -> Result<thermite::model::ModVersion, String> {
+    let index = get_package_index().await?;
 
     // Parse mod string
     let parsed_ts_mod_string: ParsedThunderstoreModString = match thunderstore_mod_string.parse() {
@@ -532,11 +540,11 @@ async fn get_ns_mod_download_url(thunderstore_mod_string: &str) -> Result<String
         parsed_ts_mod_string.version
     );
 
-    for ns_mod in index {
+    for ns_mod in index.iter() {
         // Iterate over all versions of a given mod
         for ns_mod in ns_mod.versions.values() {
             if ns_mod.url.contains(&ts_mod_string_url) {
-                return Ok(ns_mod.url.clone());
+                return Ok(ns_mod.clone());
             }
         }
     }
@@ -548,13 +556,13 @@ async fn get_ns_mod_download_url(thunderstore_mod_string: &str) -> Result<String
 async fn get_mod_dependencies(thunderstore_mod_string: &str) -> Result<Vec<String>, anyhow::Error> {
     log::info!("Attempting to get dependencies for: {thunderstore_mod_string}");
 
-    let index = thermite::api::get_package_index()?.to_vec();
+    let index = get_package_index().await.map_err(|err| anyhow!(err))?;
 
     // String replace works but more care should be taken in the future
     let ts_mod_string_url = thunderstore_mod_string.replace('-', "/");
 
     // Iterate over index
-    for ns_mod in index {
+    for ns_mod in index.iter() {
         // Iterate over all versions of a given mod
         for ns_mod in ns_mod.versions.values() {
             if ns_mod.url.contains(&ts_mod_string_url) {
@@ -679,46 +687,16 @@ fn fc_sanity_check(input: &&fs::File) -> Result<(), Box<dyn Error + Send + Sync
     }
 }
 
-// Copied from `libtermite` source code and modified
-// Should be replaced with a library call to libthermite in the future
-/// Download and install mod to the specified target.
-#[async_recursion]
-pub async fn fc_download_mod_and_install(
+/// Checks whether the given package can be installed into the given install
+pub async fn check_mod_installable(
     game_install: &GameInstall,
     thunderstore_mod_string: &str,
 ) -> Result<(), String> {
-    log::info!("Attempting to install \"{thunderstore_mod_string}\" to {game_install:?}");
-    // Get mods and download directories
-    let download_directory = format!(
-        "{}/___flightcore-temp/download-dir/",
-        game_install.game_path
-    );
-
     // Early return on empty string
     if thunderstore_mod_string.is_empty() {
         return Err("Passed empty string".to_string());
     }
 
-    let deps = match get_mod_dependencies(thunderstore_mod_string).await {
-        Ok(deps) => deps,
-        Err(err) => return Err(err.to_string()),
-    };
-    log::info!("Mod dependencies: {deps:?}");
-
-    // Recursively install dependencies
-    for dep in deps {
-        match fc_download_mod_and_install(game_install, &dep).await {
-            Ok(()) => (),
-            Err(err) => {
-                if err == "Cannot install Northstar as a mod!" {
-                    continue; // For Northstar as a dependency, we just skip it
-                } else {
-                    return Err(err);
-                }
-            }
-        };
-    }
-
     // Prevent installing Northstar as a mod
     // While it would fail during install anyway, having explicit error message is nicer
     for blacklisted_mod in BLACKLISTED_MODS {
@@ -736,60 +714,80 @@ pub async fn fc_download_mod_and_install(
         }
     }
 
-    // Get download URL for the specified mod
-    let download_url = get_ns_mod_download_url(thunderstore_mod_string).await?;
+    // Northstar itself cannot be installed as a dependency, so check its version instead
+    let northstar_requirement =
+        northstar_requirement::get_northstar_requirement(game_install, thunderstore_mod_string)
+            .await?;
+    if !northstar_requirement.is_satisfied {
+        return Err(format!(
+            "{thunderstore_mod_string} requires Northstar {} or newer but {} is installed. Update Northstar first!",
+            northstar_requirement.required_version.unwrap_or_default(),
+            northstar_requirement.installed_version.unwrap_or_default()
+        ));
+    }
 
-    // Create download directory
-    match std::fs::create_dir_all(download_directory.clone()) {
-        Ok(()) => (),
-        Err(err) => return Err(err.to_string()),
-    };
+    Ok(())
+}
 
-    let path = format!(
-        "{}/___flightcore-temp/download-dir/{thunderstore_mod_string}.zip",
-        game_install.game_path
-    );
+/// Returns the dependencies of a package that are installed as mods
+pub async fn get_installable_dependencies(
+    thunderstore_mod_string: &str,
+) -> Result<Vec<String>, String> {
+    let deps = get_mod_dependencies(thunderstore_mod_string)
+        .await
+        .map_err(|err| err.to_string())?;
+    log::info!("Mod dependencies: {deps:?}");
 
-    // Download the mod
-    let temp_file = TempFile::new(
-        std::fs::File::options()
-            .read(true)
-            .write(true)
-            .truncate(true)
-            .create(true)
-            .open(&path)
-            .map_err(|e| e.to_string())?,
-        (&path).into(),
-    );
-    match thermite::core::manage::download(temp_file.file(), download_url) {
-        Ok(_written_bytes) => (),
-        Err(err) => return Err(err.to_string()),
-    };
+    Ok(deps
+        .into_iter()
+        .filter(|dep| {
+            let is_blacklisted = BLACKLISTED_MODS
+                .iter()
+                .any(|blacklisted_mod| dep.contains(blacklisted_mod));
+            if is_blacklisted {
+                log::info!("Skipping dependency {dep} as it cannot be installed as a mod");
+            }
+            !is_blacklisted
+        })
+        .collect())
+}
 
+/// Extracts a downloaded package into the profile and removes other versions of it
+pub fn install_downloaded_mod(
+    game_install: &GameInstall,
+    thunderstore_mod_string: &str,
+    package_file: &fs::File,
+) -> Result<(), String> {
     // Get directory to install to made up of packages directory and Thunderstore mod string
     let install_directory = format!(
         "{}/{}/packages/",
         game_install.game_path, game_install.profile
     );
 
+    // Check the package before touching the profile
+    if let Err(err) = fc_sanity_check(&package_file) {
+        log::warn!("Mod {thunderstore_mod_string} failed sanity check: {err}");
+        return Err(format!(
+            "Mod failed sanity check during install. It's probably not correctly formatted. {err}"
+        ));
+    }
+    if thunderstore_mod_string
+        .parse::<ParsedThunderstoreModString>()
+        .is_err()
+    {
+        return Err(format!("Invalid mod string {thunderstore_mod_string}"));
+    }
+
     // Extract the mod to the mods directory
-    match thermite::core::manage::install_with_sanity(
-        thunderstore_mod_string,
-        temp_file.file(),
-        std::path::Path::new(&install_directory),
-        fc_sanity_check,
+    if let Err(err) = archive::extract(
+        package_file,
+        &std::path::Path::new(&install_directory).join(thunderstore_mod_string),
+        &ExtractLimits::default(),
+        |path| Some(path.to_path_buf()),
     ) {
-        Ok(_) => (),
-        Err(err) => {
-            log::warn!("libthermite couldn't install mod {thunderstore_mod_string} due to {err:?}",);
-            return match err {
-                ThermiteError::SanityError(e) => Err(
-                    format!("Mod failed sanity check during install. It's probably not correctly formatted. {e}")
-                ),
-                _ => Err(err.to_string()),
-            };
-        }
-    };
+        log::warn!("Couldn't install mod {thunderstore_mod_string} due to {err:#}");
+        return Err(format!("{err:#}"));
+    }
 
     // Successful package install
     match legacy::delete_legacy_package_install(thunderstore_mod_string, game_install) {
@@ -811,6 +809,64 @@ pub async fn fc_download_mod_and_install(
     Ok(())
 }
 
+// Copied from `libtermite` source code and modified
+// Should be replaced with a library call to libthermite in the future
+/// Download and install mod to the specified target.
+#[async_recursion]
+pub async fn fc_download_mod_and_install(
+    game_install: &GameInstall,
+    thunderstore_mod_string: &str,
+) -> Result<(), String> {
+    log::info!("Attempting to install \"{thunderstore_mod_string}\" to {game_install:?}");
+    // Get mods and download directories
+    let download_directory = format!(
+        "{}/___flightcore-temp/download-dir/",
+        game_install.game_path
+    );
+
+    check_mod_installable(game_install, thunderstore_mod_string).await?;
+
+    // Recursively install dependencies
+    for dep in get_installable_dependencies(thunderstore_mod_string).await? {
+        fc_download_mod_and_install(game_install, &dep).await?;
+    }
+
+    // Get download URL and size of the specified mod
+    let mod_version = get_ns_mod_version(thunderstore_mod_string).await?;
+
+    // Create download directory
+    match std::fs::create_dir_all(download_directory.clone()) {
+        Ok(()) => (),
+        Err(err) => return Err(err.to_string()),
+    };
+
+    let path = format!(
+        "{}/___flightcore-temp/download-dir/{thunderstore_mod_string}.zip",
+        game_install.game_path
+    );
+
+    // Download the mod
+    let expected = ExpectedFile {
+        size: (mod_version.file_size > 0).then_some(mod_version.file_size),
+        sha256: None,
+    };
+    download_file(
+        &mod_version.url,
+        std::path::Path::new(&path),
+        &expected,
+        |_, _| {},
+        None,
+    )
+    .await
+    .map_err(|err| format!("{err:#}"))?;
+    let temp_file = TempFile::new(
+        std::fs::File::open(&path).map_err(|e| e.to_string())?,
+        (&path).into(),
+    );
+
+    install_downloaded_mod(game_install, thunderstore_mod_string, temp_file.file())
+}
+
 /// Deletes a given Northstar mod folder
 fn delete_mod_folder(ns_mod_directory: &str) -> Result<(), String> {
     let ns_mod_dir_path = std::path::Path::new(&ns_mod_directory);
@@ -0,0 +1,127 @@
+//! Northstar version required by packages
+//!
+//! Packages declare the Northstar version they need as a regular dependency on
+//! `northstar-Northstar-x.y.z`. Northstar is not installed like a mod, so instead of installing
+//! that dependency the installed Northstar version is compared against it.
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::northstar::get_northstar_version_number;
+use crate::thunderstore::index;
+use crate::GameInstall;
+
+/// Package name Northstar is published under on Thunderstore
+const NORTHSTAR_PACKAGE: &str = "northstar-Northstar";
+
+/// Northstar version a package requires compared to the installed one
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+#[ts(export)]
+pub struct NorthstarRequirement {
+    pub thunderstore_mod_string: String,
+    /// `None` if the package does not depend on Northstar
+    pub required_version: Option<String>,
+    /// `None` if the installed version could not be determined
+    pub installed_version: Option<String>,
+    /// Whether the installed Northstar is recent enough, if not Northstar should be updated first
+    pub is_satisfied: bool,
+}
+
+/// Highest Northstar version listed in the given dependencies
+fn required_northstar_version(dependencies: &[String]) -> Option<semver::Version> {
+    dependencies
+        .iter()
+        .filter_map(|dependency| dependency.rsplit_once('-'))
+        .filter(|(package, _)| *package == NORTHSTAR_PACKAGE)
+        .filter_map(|(_, version)| semver::Version::parse(version).ok())
+        .max()
+}
+
+/// Checks whether the installed Northstar version is at least `required`
+/// Pre-release suffixes are ignored so release candidates count as the version they precede
+fn is_requirement_satisfied(required: &semver::Version, installed: &str) -> bool {
+    match semver::Version::parse(installed.trim_start_matches('v')) {
+        Ok(installed) => {
+            semver::Version::new(installed.major, installed.minor, installed.patch) >= *required
+        }
+        Err(err) => {
+            // Development builds may not carry a proper version, let those through
+            log::warn!("Couldn't parse installed Northstar version {installed}: {err}");
+            true
+        }
+    }
+}
+
+/// Looks up the Northstar version required by the given package version (`author-name-version`)
+pub async fn get_northstar_requirement(
+    game_install: &GameInstall,
+    thunderstore_mod_string: &str,
+) -> Result<NorthstarRequirement, String> {
+    let packages = index::get_packages().await?;
+    let required_version = packages
+        .iter()
+        .flat_map(|package| package.versions.iter())
+        .find(|version| version.full_name == thunderstore_mod_string)
+        .and_then(|version| required_northstar_version(&version.dependencies));
+
+    let mut requirement = NorthstarRequirement {
+        thunderstore_mod_string: thunderstore_mod_string.to_string(),
+        required_version: required_version.as_ref().map(|version| version.to_string()),
+        installed_version: None,
+        is_satisfied: true,
+    };
+    let Some(required_version) = required_version else {
+        return Ok(requirement);
+    };
+
+    match get_northstar_version_number(game_install.clone()) {
+        Ok(installed_version) => {
+            requirement.is_satisfied =
+                is_requirement_satisfied(&required_version, &installed_version);
+            requirement.installed_version = Some(installed_version);
+        }
+        Err(err) => log::warn!(
+            "Couldn't check Northstar {required_version} requirement of {thunderstore_mod_string}: {err}"
+        ),
+    }
+    Ok(requirement)
+}
+
+/// Returns the Northstar version the given package version requires and whether it is installed
+/// Lets the frontend offer updating Northstar before installing the package
+#[tauri::command]
+pub async fn check_northstar_requirement(
+    game_install: GameInstall,
+    thunderstore_mod_string: String,
+) -> Result<NorthstarRequirement, String> {
+    get_northstar_requirement(&game_install, &thunderstore_mod_string).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_required_northstar_version() {
+        let dependencies = vec![
+            "northstar-Northstar-1.19.0".to_string(),
+            "northstar-NorthstarReleaseCandidate-1.25.0".to_string(),
+            "Someone-Library-2.0.0".to_string(),
+            "northstar-Northstar-1.9.3".to_string(),
+        ];
+        assert_eq!(
+            required_northstar_version(&dependencies),
+            Some(semver::Version::new(1, 19, 0))
+        );
+        assert_eq!(required_northstar_version(&dependencies[2..3]), None);
+    }
+
+    #[test]
+    fn test_is_requirement_satisfied() {
+        let required = semver::Version::new(1, 19, 0);
+        assert!(is_requirement_satisfied(&required, "1.19.0"));
+        assert!(is_requirement_satisfied(&required, "v1.20.1"));
+        assert!(is_requirement_satisfied(&required, "1.19.0-rc2"));
+        assert!(!is_requirement_satisfied(&required, "1.18.9"));
+        assert!(is_requirement_satisfied(&required, "dev"));
+    }
+}
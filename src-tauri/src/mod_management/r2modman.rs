@@ -0,0 +1,426 @@
+//! Importing of profiles exported by r2modman / Thunderstore Mod Manager
+//!
+//! r2modman exports profiles as `.r2z` files which are zip archives containing
+//! - `export.r2x`, a YAML file listing the installed packages and their versions
+//! - any config files that were part of the profile
+//!
+//! Profile codes are such `.r2z` archives uploaded to Thunderstore as base64 encoded text.
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::archive::{self, ExtractLimits};
+use crate::constants::{
+    APP_USER_AGENT, BLACKLISTED_MODS, CORE_MODS, NORTHSTAR_DEFAULT_PROFILE, NORTHSTAR_DLL,
+    PROFILE_NORTHSTAR_INSTALL_FILE,
+};
+use crate::mod_management::{
+    fc_download_mod_and_install, get_installed_mods_and_properties, set_mod_enabled_status,
+};
+use crate::northstar::profile::{is_valid_profile_name, validate_profile};
+use crate::thunderstore::index;
+use crate::GameInstall;
+
+/// Name of the mod list inside of an `.r2z` archive
+const R2X_FILE_NAME: &str = "export.r2x";
+
+/// Thunderstore endpoint r2modman profile codes are shared through
+const PROFILE_CODE_URL: &str = "https://thunderstore.io/api/experimental/legacyprofile/get";
+
+/// Header r2modman prepends to the base64 encoded profile
+const PROFILE_CODE_HEADER: &str = "#r2modman";
+
+/// Upper bound for the size of a base64 encoded profile code
+const MAX_PROFILE_CODE_SIZE: usize = 32 * 1024 * 1024;
+
+/// Parsed `export.r2x` file
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct R2xExport {
+    profile_name: String,
+    #[serde(default)]
+    mods: Vec<R2xMod>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct R2xMod {
+    name: String,
+    version: R2xVersion,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct R2xVersion {
+    major: u32,
+    minor: u32,
+    patch: u32,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl R2xMod {
+    /// Thunderstore mod string in the form of `AUTHOR-MOD-VERSION`
+    fn thunderstore_mod_string(&self) -> String {
+        format!(
+            "{}-{}.{}.{}",
+            self.name, self.version.major, self.version.minor, self.version.patch
+        )
+    }
+}
+
+/// A package from the r2modman profile that could not be installed
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct R2modmanImportFailure {
+    thunderstore_mod_string: String,
+    reason: String,
+}
+
+/// Outcome of importing an r2modman profile
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct R2modmanImportResult {
+    profile: String,
+    installed_packages: Vec<String>,
+    failed_packages: Vec<R2modmanImportFailure>,
+}
+
+/// Imports an r2modman `.r2z` profile export into a new profile
+#[tauri::command]
+pub async fn import_r2modman_profile_file(
+    game_install: GameInstall,
+    r2z_path: String,
+    profile_name: Option<String>,
+) -> Result<R2modmanImportResult, String> {
+    let file = match std::fs::File::open(&r2z_path) {
+        Ok(file) => file,
+        Err(err) => return Err(format!("Failed to open {r2z_path}: {err}")),
+    };
+
+    import_r2z(&game_install, file, profile_name)
+        .await
+        .map_err(|err| {
+            log::error!("Failed importing r2modman profile: {err}");
+            err.to_string()
+        })
+}
+
+/// Imports an r2modman profile shared via profile code into a new profile
+#[tauri::command]
+pub async fn import_r2modman_profile_code(
+    game_install: GameInstall,
+    profile_code: String,
+    profile_name: Option<String>,
+) -> Result<R2modmanImportResult, String> {
+    let archive = match fetch_profile_code(profile_code.trim()).await {
+        Ok(archive) => archive,
+        Err(err) => return Err(format!("Couldn't fetch profile code: {err}")),
+    };
+
+    // Use a temp file to store archive
+    let mut tmpfile = tempfile::tempfile().map_err(|err| err.to_string())?;
+    tmpfile.write_all(&archive).map_err(|err| err.to_string())?;
+
+    import_r2z(&game_install, tmpfile, profile_name)
+        .await
+        .map_err(|err| {
+            log::error!("Failed importing r2modman profile: {err}");
+            err.to_string()
+        })
+}
+
+/// Downloads the `.r2z` archive behind a profile code from Thunderstore
+async fn fetch_profile_code(profile_code: &str) -> Result<Vec<u8>> {
+    // Profile codes are UUIDs, reject anything else before building an URL from it
+    let re = regex::Regex::new(r"^[0-9a-fA-F-]+$").unwrap();
+    if !re.is_match(profile_code) {
+        return Err(anyhow!("Invalid profile code \"{profile_code}\""));
+    }
+
+    let url = format!("{PROFILE_CODE_URL}/{profile_code}/");
+    let client = reqwest::Client::builder()
+        .connect_timeout(index::CONNECT_TIMEOUT)
+        .timeout(index::FETCH_TIMEOUT)
+        .build()?;
+    let mut response = client
+        .get(url)
+        .header(reqwest::header::USER_AGENT, APP_USER_AGENT)
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!("Request unsuccessful: {}", response.status()));
+    }
+
+    // Profile codes are shared by anyone, don't read arbitrarily large responses into memory
+    let too_large = || anyhow!("Profile code is larger than {MAX_PROFILE_CODE_SIZE} bytes");
+    if response
+        .content_length()
+        .is_some_and(|length| length > MAX_PROFILE_CODE_SIZE as u64)
+    {
+        return Err(too_large());
+    }
+    let mut body: Vec<u8> = vec![];
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > MAX_PROFILE_CODE_SIZE {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    decode_profile_code_response(&String::from_utf8(body).context("Invalid profile code")?)
+}
+
+/// Turns the profile code API response into the raw `.r2z` archive
+fn decode_profile_code_response(response: &str) -> Result<Vec<u8>> {
+    let encoded = response
+        .trim()
+        .strip_prefix(PROFILE_CODE_HEADER)
+        .ok_or(anyhow!("Not an r2modman profile"))?;
+
+    // Encoded data may be wrapped over multiple lines
+    let encoded: String = encoded.chars().filter(|c| !c.is_whitespace()).collect();
+
+    base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .context("Failed decoding profile code")
+}
+
+/// Parses the `export.r2x` mod list
+fn parse_r2x(data: &str) -> Result<R2xExport> {
+    serde_yaml_ng::from_str(data).context("Failed parsing export.r2x")
+}
+
+/// Maps a path inside the `.r2z` archive to a path inside the profile.
+/// Returns `None` for files that should not be copied over.
+fn map_config_path(archive_path: &Path) -> Option<PathBuf> {
+    // r2modman keeps Northstar files in a subfolder named after the default profile
+    let relative_path = archive_path
+        .strip_prefix(NORTHSTAR_DEFAULT_PROFILE)
+        .unwrap_or(archive_path);
+
+    // Mods are installed from Thunderstore, only config files are taken over
+    // The profile gets Northstar and the list of enabled mods from FlightCore
+    if relative_path == Path::new(R2X_FILE_NAME)
+        || relative_path == Path::new(NORTHSTAR_DLL)
+        || relative_path == Path::new(PROFILE_NORTHSTAR_INSTALL_FILE)
+        || relative_path == Path::new("enabledmods.json")
+        || relative_path.starts_with("mods")
+        || relative_path.starts_with("packages")
+        || relative_path.starts_with("plugins")
+        || relative_path.as_os_str().is_empty()
+    {
+        return None;
+    }
+
+    Some(relative_path.to_path_buf())
+}
+
+/// Creates a new profile that shares the Northstar install of the given profile
+pub fn create_profile_from(game_install: &GameInstall, new_profile: &str) -> Result<()> {
+    let old_profile_path = Path::new(&game_install.game_path).join(&game_install.profile);
+    let new_profile_path = Path::new(&game_install.game_path).join(new_profile);
+
+    std::fs::create_dir_all(new_profile_path.join("mods"))?;
+
+    for core_mod in CORE_MODS {
+        let core_mod_path = old_profile_path.join("mods").join(core_mod);
+        if !core_mod_path.is_dir() {
+            log::warn!("{} not found, skipping", core_mod_path.display());
+            continue;
+        }
+        crate::util::copy_dir_all(core_mod_path, new_profile_path.join("mods").join(core_mod))?;
+    }
+
+    for file_name in [NORTHSTAR_DLL, PROFILE_NORTHSTAR_INSTALL_FILE] {
+        let file_path = old_profile_path.join(file_name);
+        if file_path.is_file() {
+            std::fs::copy(file_path, new_profile_path.join(file_name))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Imports the given `.r2z` archive into a new profile
+async fn import_r2z<R: Read + Seek>(
+    game_install: &GameInstall,
+    r2z: R,
+    profile_name: Option<String>,
+) -> Result<R2modmanImportResult> {
+    let mut archive = zip::ZipArchive::new(r2z).context("Unable to open r2z archive")?;
+
+    let mut r2x_data = String::new();
+    archive
+        .by_name(R2X_FILE_NAME)
+        .context("r2z archive is missing export.r2x")?
+        .read_to_string(&mut r2x_data)?;
+    let export = parse_r2x(&r2x_data)?;
+
+    // Use the name stored in the export unless one was passed explicitly
+    let profile = profile_name
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or(export.profile_name.clone());
+
+    if !is_valid_profile_name(&profile) {
+        return Err(anyhow!("\"{profile}\" is not a valid profile name"));
+    }
+
+    if validate_profile(game_install.clone(), profile.clone())
+        || Path::new(&game_install.game_path).join(&profile).exists()
+    {
+        return Err(anyhow!("{profile} already exists"));
+    }
+
+    log::info!(
+        "Importing r2modman profile \"{}\" as {} with {} packages",
+        export.profile_name,
+        profile,
+        export.mods.len()
+    );
+
+    let result = import_into_profile(game_install, archive.into_inner(), &export, &profile).await;
+    if result.is_err() {
+        // Don't leave a half imported profile behind
+        let profile_path = Path::new(&game_install.game_path).join(&profile);
+        if let Err(err) = std::fs::remove_dir_all(&profile_path) {
+            log::warn!("Failed removing {}: {err}", profile_path.display());
+        }
+    }
+    result
+}
+
+/// Creates the profile and fills it with the config files and packages of the export
+async fn import_into_profile<R: Read + Seek>(
+    game_install: &GameInstall,
+    r2z: R,
+    export: &R2xExport,
+    profile: &str,
+) -> Result<R2modmanImportResult> {
+    create_profile_from(game_install, profile)?;
+    let profile_path = Path::new(&game_install.game_path).join(profile);
+
+    // Copy over config files
+    archive::extract(r2z, &profile_path, &ExtractLimits::default(), map_config_path)?;
+
+    let profile_install = GameInstall {
+        profile: profile.to_string(),
+        ..game_install.clone()
+    };
+
+    let mut installed_packages: Vec<String> = vec![];
+    let mut failed_packages: Vec<R2modmanImportFailure> = vec![];
+
+    for r2x_mod in &export.mods {
+        // Northstar itself is listed as a package in r2modman profiles
+        if BLACKLISTED_MODS.contains(&r2x_mod.name.as_str()) {
+            continue;
+        }
+
+        let thunderstore_mod_string = r2x_mod.thunderstore_mod_string();
+        match fc_download_mod_and_install(&profile_install, &thunderstore_mod_string).await {
+            Ok(()) => installed_packages.push(thunderstore_mod_string),
+            Err(err) => {
+                log::warn!("Failed installing {thunderstore_mod_string}: {err}");
+                failed_packages.push(R2modmanImportFailure {
+                    thunderstore_mod_string,
+                    reason: err,
+                });
+            }
+        }
+    }
+
+    // Disable mods that were disabled in r2modman
+    let disabled_packages: Vec<String> = export
+        .mods
+        .iter()
+        .filter(|r2x_mod| !r2x_mod.enabled)
+        .map(|r2x_mod| r2x_mod.thunderstore_mod_string())
+        .filter(|mod_string| installed_packages.contains(mod_string))
+        .collect();
+
+    if !disabled_packages.is_empty() {
+        let installed_mods = get_installed_mods_and_properties(profile_install.clone())
+            .map_err(|err| anyhow!(err))?;
+        for installed_mod in installed_mods {
+            let Some(mod_string) = &installed_mod.thunderstore_mod_string else {
+                continue;
+            };
+            if disabled_packages.contains(mod_string) {
+                set_mod_enabled_status(
+                    profile_install.clone(),
+                    installed_mod.name,
+                    installed_mod.version.unwrap_or_default(),
+                    false,
+                )
+                .map_err(|err| anyhow!(err))?;
+            }
+        }
+    }
+
+    if let Err(err) = crate::repair_and_verify::clean_up_download_folder(&profile_install, false) {
+        log::info!("Failed to delete download folder due to {}", err);
+    }
+
+    Ok(R2modmanImportResult {
+        profile: profile.to_string(),
+        installed_packages,
+        failed_packages,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_r2x() {
+        let input = "profileName: Scrims\nmods:\n  - name: northstar-Northstar\n    version:\n      major: 1\n      minor: 24\n      patch: 1\n    enabled: true\n  - name: Alystrasz-Parkour\n    version:\n      major: 0\n      minor: 2\n      patch: 10\n    enabled: false\n";
+        let export = parse_r2x(input).unwrap();
+
+        assert_eq!(export.profile_name, "Scrims");
+        assert_eq!(export.mods.len(), 2);
+        assert_eq!(
+            export.mods[1].thunderstore_mod_string(),
+            "Alystrasz-Parkour-0.2.10"
+        );
+        assert!(!export.mods[1].enabled);
+    }
+
+    #[test]
+    fn test_decode_profile_code_response() {
+        let input = "#r2modman\nUEsDBA==\n";
+        let output = decode_profile_code_response(input).unwrap();
+        assert_eq!(output, vec![0x50, 0x4b, 0x03, 0x04]);
+    }
+
+    #[test]
+    fn test_map_config_path() {
+        assert_eq!(
+            map_config_path(Path::new("R2Northstar/save_data/config.json")),
+            Some(PathBuf::from("save_data/config.json"))
+        );
+        assert_eq!(
+            map_config_path(Path::new("R2Northstar/mods/Foo/mod.json")),
+            None
+        );
+        assert_eq!(map_config_path(Path::new("export.r2x")), None);
+        for file_name in [
+            "Northstar.dll",
+            "flightcore-northstar.json",
+            "enabledmods.json",
+        ] {
+            assert_eq!(
+                map_config_path(&Path::new("R2Northstar").join(file_name)),
+                None
+            );
+        }
+    }
+}
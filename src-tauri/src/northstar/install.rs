@@ -1,18 +1,17 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
-use std::{cell::RefCell, time::Instant};
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter};
 use ts_rs::TS;
 
+use crate::archive::{self, ExtractLimits};
 use crate::constants::{CORE_MODS, NORTHSTAR_DEFAULT_PROFILE, NORTHSTAR_DLL};
-use crate::{
-    util::{extract, move_dir_all},
-    GameInstall, InstallType,
+use crate::download::{download_file, ExpectedFile};
+use crate::northstar::profile::{
+    read_profile_northstar_install, write_profile_northstar_install, ProfileNorthstarInstall,
 };
-
-#[cfg(target_os = "windows")]
-use crate::platform_specific::windows;
+use crate::thunderstore::index::get_package_index;
+use crate::{util::move_dir_all, GameInstall};
 
 #[derive(Serialize, Deserialize, Debug, Clone, TS)]
 #[ts(export)]
@@ -30,7 +29,7 @@ struct InstallProgress {
     state: InstallState,
 }
 
-/// Installs Northstar to the given path
+/// Installs Northstar into the profile of the given game install
 #[tauri::command]
 pub async fn install_northstar_wrapper(
     app: AppHandle,
@@ -60,19 +59,45 @@ pub async fn install_northstar_wrapper(
     }
 }
 
-/// Update Northstar install in the given path
+/// Update Northstar install in the profile of the given game install
+/// Keeps the Northstar package the profile was installed with unless a different one is passed
 #[tauri::command]
 pub async fn update_northstar(
     app: AppHandle,
     game_install: GameInstall,
     northstar_package_name: Option<String>,
 ) -> Result<bool, String> {
-    log::info!("Updating Northstar");
+    log::info!("Updating Northstar in profile {}", game_install.profile);
+
+    let northstar_package_name = northstar_package_name.or_else(|| {
+        read_profile_northstar_install(&game_install)
+            .ok()
+            .map(|northstar_install| northstar_install.package_name)
+    });
 
     // Simply re-run install with up-to-date version for upate
     install_northstar_wrapper(app, game_install, northstar_package_name, None).await
 }
 
+/// Moves the DLL of the extracted Northstar release into the profile to install into
+/// Each profile owns its DLL so that profiles can run different Northstar versions
+fn prepare_profile(extract_directory: &str, profile: &str) -> Result<()> {
+    // Move DLL into the default R2Northstar Profile
+    let old_dll_path = format!("{extract_directory}/{NORTHSTAR_DLL}");
+    let new_dll_path = format!("{extract_directory}/{NORTHSTAR_DEFAULT_PROFILE}/{NORTHSTAR_DLL}");
+    std::fs::rename(old_dll_path, new_dll_path)?;
+
+    if profile != NORTHSTAR_DEFAULT_PROFILE {
+        // We are using a non standard Profile, we must rename the Profile
+        // to the profile we want to use
+        let old_profile_path = format!("{extract_directory}/{NORTHSTAR_DEFAULT_PROFILE}/");
+        let new_profile_path = format!("{extract_directory}/{profile}/");
+        std::fs::rename(old_profile_path, new_profile_path)?;
+    }
+
+    Ok(())
+}
+
 /// Copied from `papa` source code and modified
 ///Install N* from the provided mod
 ///
@@ -94,36 +119,35 @@ async fn do_install(
     let download_path = format!("{download_directory}/{filename}");
     log::info!("Download path: {download_path}");
 
-    let last_emit = RefCell::new(Instant::now()); // Keep track of the last time a signal was emitted
-    let mut nfile = std::fs::File::options()
-        .read(true)
-        .write(true)
-        .truncate(true)
-        .create(true)
-        .open(download_path)?;
-    thermite::core::manage::download_with_progress(
-        &mut nfile,
+    let mut last_emit = Instant::now(); // Keep track of the last time a signal was emitted
+    let expected = ExpectedFile {
+        size: (nmod.file_size > 0).then_some(nmod.file_size),
+        sha256: None,
+    };
+    download_file(
         &nmod.url,
-        |delta, current, total| {
-            if delta != 0 {
-                // Only emit a signal once every 100ms
-                // This way we don't bombard the frontend with events on fast download speeds
-                let time_since_last_emit = Instant::now().duration_since(*last_emit.borrow());
-                if time_since_last_emit >= Duration::from_millis(100) {
-                    app.emit(
-                        "northstar-install-download-progress",
-                        InstallProgress {
-                            current_downloaded: current,
-                            total_size: total,
-                            state: InstallState::Downloading,
-                        },
-                    )
-                    .unwrap();
-                    *last_emit.borrow_mut() = Instant::now();
-                }
+        std::path::Path::new(&download_path),
+        &expected,
+        |current, total| {
+            // Only emit a signal once every 100ms
+            // This way we don't bombard the frontend with events on fast download speeds
+            if last_emit.elapsed() >= Duration::from_millis(100) {
+                app.emit(
+                    "northstar-install-download-progress",
+                    InstallProgress {
+                        current_downloaded: current,
+                        total_size: total,
+                        state: InstallState::Downloading,
+                    },
+                )
+                .unwrap();
+                last_emit = Instant::now();
             }
         },
-    )?;
+        None,
+    )
+    .await?;
+    let nfile = std::fs::File::open(&download_path)?;
 
     app.emit(
         "northstar-install-download-progress",
@@ -136,26 +160,22 @@ async fn do_install(
     .unwrap();
 
     log::info!("Extracting Northstar...");
-    extract(nfile, std::path::Path::new(&extract_directory))?;
+    // This should work fine for N* because the dir structure *should* always be the same
+    archive::extract(
+        nfile,
+        std::path::Path::new(&extract_directory),
+        &ExtractLimits::default(),
+        |path| {
+            path.strip_prefix("Northstar")
+                .ok()
+                .map(std::path::Path::to_path_buf)
+        },
+    )?;
 
     // Prepare Northstar for Installation
     log::info!("Preparing Northstar...");
-    if game_install.profile != NORTHSTAR_DEFAULT_PROFILE {
-        // We are using a non standard Profile, we must:
-        // - move the DLL
-        // - rename the Profile
-
-        // Move DLL into the default R2Northstar Profile
-        let old_dll_path = format!("{extract_directory}/{NORTHSTAR_DLL}");
-        let new_dll_path =
-            format!("{extract_directory}/{NORTHSTAR_DEFAULT_PROFILE}/{NORTHSTAR_DLL}");
-        std::fs::rename(old_dll_path, new_dll_path)?;
-
-        // rename default R2Northstar Profile to the profile we want to use
-        let old_profile_path = format!("{extract_directory}/{NORTHSTAR_DEFAULT_PROFILE}/");
-        let new_profile_path = format!("{}/{}/", extract_directory, game_install.profile);
-        std::fs::rename(old_profile_path, new_profile_path)?;
-    }
+
+    prepare_profile(&extract_directory, &game_install.profile)?;
 
     log::info!("Installing Northstar...");
 
@@ -225,6 +245,15 @@ async fn do_install(
     log::info!("Delete temporary directory");
     std::fs::remove_dir_all(temp_dir).unwrap();
 
+    // Record which Northstar the profile now owns
+    write_profile_northstar_install(
+        &game_install,
+        &ProfileNorthstarInstall {
+            package_name: nmod.name.clone(),
+            version: nmod.version.clone(),
+        },
+    )?;
+
     log::info!("Done installing Northstar!");
     app.emit(
         "northstar-install-download-progress",
@@ -245,8 +274,8 @@ pub async fn install_northstar(
     northstar_package_name: String,
     version_number: Option<String>,
 ) -> Result<String, String> {
-    let index = match thermite::api::get_package_index() {
-        Ok(res) => res.to_vec(),
+    let index = match get_package_index().await {
+        Ok(res) => res,
         Err(err) => {
             log::warn!("Failed fetching package index due to: {err}");
             return Err("Failed to connect to Thunderstore.".to_string());
@@ -285,70 +314,93 @@ pub async fn install_northstar(
 }
 
 /// Attempts to find the game install location
+/// Returns the first install found, see `game_installs` for all of them
 #[tauri::command]
 pub fn find_game_install_location() -> Result<GameInstall, String> {
-    // Attempt parsing Steam library directly
-    match steamlocate::SteamDir::locate() {
-        Ok(steamdir) => {
-            #[cfg(target_os = "linux")]
-            {
-                let snap_dir = match std::env::var("SNAP_USER_DATA") {
-                    Ok(snap_dir) => std::path::PathBuf::from(snap_dir),
-                    Err(_) => match dirs::home_dir() {
-                        Some(path) => path,
-                        None => std::path::PathBuf::new(),
-                    }
-                    .join("snap"),
-                };
-
-                if steamdir.path().starts_with(snap_dir) {
-                    log::warn!("Found Steam installed via Snap, you may encounter issues");
-                }
-            }
+    match crate::game_installs::discover_game_installs()
+        .into_iter()
+        .next()
+    {
+        Some(game_install) => Ok(game_install),
+        None => Err(
+            "Could not auto-detect game install location! Please enter it manually.".to_string(),
+        ),
+    }
+}
 
-            match steamdir.find_app(thermite::TITANFALL2_STEAM_ID) {
-                Ok(Some((app, library))) => {
-                    let app_path = library
-                        .path()
-                        .join("steamapps")
-                        .join("common")
-                        .join(app.install_dir)
-                        .into_os_string()
-                        .into_string()
-                        .unwrap();
-
-                    let game_install = GameInstall {
-                        game_path: app_path,
-                        profile: "R2Northstar".to_string(),
-                        install_type: InstallType::STEAM,
-                    };
-                    return Ok(game_install);
-                }
-                Ok(None) => log::info!("Couldn't locate your Titanfall 2 Steam install."),
-                Err(err) => log::info!(
-                    "Something went wrong while trying to find Titanfall 2 {}",
-                    err
-                ),
-            }
-        }
-        Err(err) => log::info!("Couldn't locate Steam on this computer! {}", err),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InstallType;
+
+    /// Lays out an extracted Northstar release
+    fn extracted_release() -> tempfile::TempDir {
+        let extract_dir = tempfile::tempdir().unwrap();
+        let profile_dir = extract_dir.path().join(NORTHSTAR_DEFAULT_PROFILE);
+        std::fs::create_dir_all(profile_dir.join("mods")).unwrap();
+        std::fs::write(extract_dir.path().join(NORTHSTAR_DLL), "dll").unwrap();
+        std::fs::write(extract_dir.path().join("NorthstarLauncher.exe"), "exe").unwrap();
+        extract_dir
     }
 
-    // (On Windows only) try parsing Windows registry for Origin install path
-    #[cfg(target_os = "windows")]
-    match windows::origin_install_location_detection() {
-        Ok(game_path) => {
-            let game_install = GameInstall {
-                game_path,
-                profile: "R2Northstar".to_string(),
-                install_type: InstallType::ORIGIN,
-            };
-            return Ok(game_install);
-        }
-        Err(err) => {
-            log::info!("{}", err);
-        }
-    };
+    #[test]
+    fn test_prepare_default_profile() {
+        let extract_dir = extracted_release();
+        let extract_path = extract_dir.path().to_str().unwrap();
+
+        prepare_profile(extract_path, NORTHSTAR_DEFAULT_PROFILE).unwrap();
+
+        assert!(extract_dir
+            .path()
+            .join(NORTHSTAR_DEFAULT_PROFILE)
+            .join(NORTHSTAR_DLL)
+            .is_file());
+        assert!(!extract_dir.path().join(NORTHSTAR_DLL).exists());
+        assert!(extract_dir.path().join("NorthstarLauncher.exe").is_file());
+    }
+
+    #[test]
+    fn test_prepare_custom_profile() {
+        let extract_dir = extracted_release();
+        let extract_path = extract_dir.path().to_str().unwrap();
+
+        prepare_profile(extract_path, "RC").unwrap();
 
-    Err("Could not auto-detect game install location! Please enter it manually.".to_string())
+        assert!(extract_dir.path().join("RC").join(NORTHSTAR_DLL).is_file());
+        assert!(extract_dir.path().join("RC").join("mods").is_dir());
+        assert!(!extract_dir.path().join(NORTHSTAR_DEFAULT_PROFILE).exists());
+        assert!(!extract_dir.path().join(NORTHSTAR_DLL).exists());
+    }
+
+    #[test]
+    fn test_profile_northstar_install_record() {
+        let game_dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(game_dir.path().join("RC")).unwrap();
+        let game_install = GameInstall {
+            game_path: game_dir.path().to_str().unwrap().to_string(),
+            profile: "RC".to_string(),
+            install_type: InstallType::UNKNOWN,
+            wine_prefix: None,
+        };
+
+        assert!(read_profile_northstar_install(&game_install).is_err());
+
+        write_profile_northstar_install(
+            &game_install,
+            &ProfileNorthstarInstall {
+                package_name: "NorthstarReleaseCandidate".to_string(),
+                version: "1.20.1".to_string(),
+            },
+        )
+        .unwrap();
+
+        let northstar_install = read_profile_northstar_install(&game_install).unwrap();
+        assert_eq!(northstar_install.package_name, "NorthstarReleaseCandidate");
+        assert_eq!(northstar_install.version, "1.20.1");
+        assert!(game_dir
+            .path()
+            .join("RC")
+            .join(crate::constants::PROFILE_NORTHSTAR_INSTALL_FILE)
+            .is_file());
+    }
 }
@@ -0,0 +1,402 @@
+//! Checks run before launching Northstar
+//!
+//! Each check inspects one aspect of the install and reports whether launching is expected to work.
+//! New checks only need to be added to `LAUNCH_CHECKS`.
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::Manager;
+use ts_rs::TS;
+
+use crate::mod_management::{get_enabled_mods, get_installed_mods_and_properties};
+use crate::northstar::{
+    get_northstar_version_number, get_titanfall2_compat_tool, is_launched_via_steam,
+    NorthstarLaunchOptions,
+};
+use crate::platform_specific::get_host_os;
+use crate::process_monitor::{ProcessMonitor, ProcessSnapshot};
+use crate::GameInstall;
+
+/// Below this much free space the game is likely to fail writing logs and save data
+const MIN_FREE_DISK_SPACE: u64 = 256 * 1024 * 1024;
+
+/// Below this much free space the user is warned
+const LOW_FREE_DISK_SPACE: u64 = 2 * 1024 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+#[ts(export)]
+pub enum LaunchCheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// Outcome of a single pre-launch check
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct LaunchCheckResult {
+    pub name: String,
+    pub status: LaunchCheckStatus,
+    pub message: String,
+    pub fix_suggestion: Option<String>,
+}
+
+/// Everything a check may need to know about the upcoming launch
+pub struct LaunchCheckContext {
+    pub game_install: GameInstall,
+    pub launch_via_steam: bool,
+    pub process_snapshot: ProcessSnapshot,
+}
+
+/// Outcome of a check before it is labelled with the check's name
+struct LaunchCheckOutcome {
+    status: LaunchCheckStatus,
+    message: String,
+    fix_suggestion: Option<String>,
+}
+
+impl LaunchCheckOutcome {
+    fn pass(message: &str) -> Self {
+        LaunchCheckOutcome {
+            status: LaunchCheckStatus::Pass,
+            message: message.to_string(),
+            fix_suggestion: None,
+        }
+    }
+
+    fn warn(message: String, fix_suggestion: &str) -> Self {
+        LaunchCheckOutcome {
+            status: LaunchCheckStatus::Warn,
+            message,
+            fix_suggestion: Some(fix_suggestion.to_string()),
+        }
+    }
+
+    fn fail(message: String, fix_suggestion: &str) -> Self {
+        LaunchCheckOutcome {
+            status: LaunchCheckStatus::Fail,
+            message,
+            fix_suggestion: Some(fix_suggestion.to_string()),
+        }
+    }
+}
+
+type LaunchCheck = fn(&LaunchCheckContext) -> LaunchCheckOutcome;
+
+/// All checks in the order they are run
+const LAUNCH_CHECKS: [(&str, LaunchCheck); 7] = [
+    ("Core mods", check_core_mods_version),
+    ("EA App", check_ea_app_running),
+    ("Steam compatibility tool", check_steam_compat_tool),
+    ("Mod dependencies", check_mod_dependencies),
+    ("enabledmods.json", check_enabled_mods_json),
+    ("Temporary files", check_leftover_temp_dir),
+    ("Disk space", check_disk_space),
+];
+
+/// All core mods need to be installed and on the same version
+fn check_core_mods_version(context: &LaunchCheckContext) -> LaunchCheckOutcome {
+    match get_northstar_version_number(context.game_install.clone()) {
+        Ok(version) => LaunchCheckOutcome::pass(&format!("Northstar v{version} installed")),
+        Err(err) => LaunchCheckOutcome::fail(
+            format!("Core mods are missing or have mismatching versions: {err}"),
+            "Reinstall Northstar",
+        ),
+    }
+}
+
+/// Northstar needs the EA App or Origin for authentication
+fn check_ea_app_running(context: &LaunchCheckContext) -> LaunchCheckOutcome {
+    // Steam starts the EA App by itself
+    if context.launch_via_steam {
+        return LaunchCheckOutcome::pass("Started by Steam");
+    }
+
+    if context.process_snapshot.is_ea_app_running() {
+        LaunchCheckOutcome::pass("EA App is running")
+    } else {
+        LaunchCheckOutcome::fail(
+            "EA App not running".to_string(),
+            "Start EA App and log in before launching Northstar",
+        )
+    }
+}
+
+/// Steam on Linux needs to run Titanfall2 through Proton
+fn check_steam_compat_tool(context: &LaunchCheckContext) -> LaunchCheckOutcome {
+    if !context.launch_via_steam || get_host_os() == "windows" {
+        return LaunchCheckOutcome::pass("Not required");
+    }
+
+    // Steam might just not be found, e.g. when installed via Flatpak, leave it to Steam then
+    let steamdir = match steamlocate::SteamDir::locate() {
+        Ok(steamdir) => steamdir,
+        Err(err) => {
+            return LaunchCheckOutcome::warn(
+                format!("Couldn't locate Steam: {err}"),
+                "Make sure Titanfall2 is configured to use NorthstarProton in Steam",
+            )
+        }
+    };
+
+    match steamdir.compat_tool_mapping() {
+        Ok(map) => match get_titanfall2_compat_tool(&map) {
+            Some(compat_tool) => match &compat_tool.name {
+                Some(name) => LaunchCheckOutcome::pass(&format!("Using {name}")),
+                None => LaunchCheckOutcome::pass("Compatibility tool set"),
+            },
+            None => LaunchCheckOutcome::fail(
+                "Titanfall2 was not configured to use a compatibility tool".to_string(),
+                "Select NorthstarProton as compatibility tool in the Titanfall2 properties in Steam",
+            ),
+        },
+        Err(err) => LaunchCheckOutcome::warn(
+            format!("Could not get compatibility tool mapping: {err}"),
+            "Make sure Titanfall2 is configured to use NorthstarProton in Steam",
+        ),
+    }
+}
+
+/// Minimal part of a Thunderstore package manifest needed to resolve dependencies
+#[derive(Deserialize)]
+struct PackageDependencies {
+    #[serde(default)]
+    dependencies: Vec<String>,
+}
+
+/// Enabled packages should have all their dependencies installed and enabled
+fn check_mod_dependencies(context: &LaunchCheckContext) -> LaunchCheckOutcome {
+    let installed_mods = match get_installed_mods_and_properties(context.game_install.clone()) {
+        Ok(installed_mods) => installed_mods,
+        Err(err) => {
+            return LaunchCheckOutcome::warn(
+                format!("Failed reading installed mods: {err}"),
+                "Check the mods folder of the profile",
+            )
+        }
+    };
+
+    let enabled_packages: Vec<String> = installed_mods
+        .iter()
+        .filter(|installed_mod| installed_mod.enabled)
+        .filter_map(|installed_mod| installed_mod.thunderstore_mod_string.clone())
+        .collect();
+
+    let mut missing_dependencies: Vec<String> = vec![];
+    for package in &enabled_packages {
+        let manifest_path = format!(
+            "{}/{}/packages/{}/manifest.json",
+            context.game_install.game_path, context.game_install.profile, package
+        );
+        let Ok(data) = std::fs::read_to_string(manifest_path) else {
+            continue;
+        };
+        let Ok(manifest) = serde_json::from_str::<PackageDependencies>(&data) else {
+            continue;
+        };
+
+        for dependency in manifest.dependencies {
+            // Northstar itself is handled by the core mods check
+            if dependency
+                .to_lowercase()
+                .starts_with("northstar-northstar-")
+            {
+                continue;
+            }
+
+            // Any installed version of the dependency is accepted
+            let Some((dependency_name, _)) = dependency.rsplit_once('-') else {
+                continue;
+            };
+            let is_installed = enabled_packages.iter().any(|enabled_package| {
+                enabled_package
+                    .rsplit_once('-')
+                    .is_some_and(|(name, _)| name == dependency_name)
+            });
+            if !is_installed && !missing_dependencies.contains(&dependency) {
+                missing_dependencies.push(dependency);
+            }
+        }
+    }
+
+    if missing_dependencies.is_empty() {
+        LaunchCheckOutcome::pass("All dependencies installed")
+    } else {
+        LaunchCheckOutcome::warn(
+            format!(
+                "Missing or disabled dependencies: {}",
+                missing_dependencies.join(", ")
+            ),
+            "Install or enable the listed mods",
+        )
+    }
+}
+
+/// `enabledmods.json` needs to be valid JSON for Northstar to load mods
+fn check_enabled_mods_json(context: &LaunchCheckContext) -> LaunchCheckOutcome {
+    let enabledmods_json_path = format!(
+        "{}/{}/enabledmods.json",
+        context.game_install.game_path, context.game_install.profile
+    );
+
+    // Northstar creates the file on first launch
+    if !Path::new(&enabledmods_json_path).exists() {
+        return LaunchCheckOutcome::pass("Will be created by Northstar");
+    }
+
+    match get_enabled_mods(&context.game_install) {
+        Ok(enabled_mods) if enabled_mods.is_object() => LaunchCheckOutcome::pass("Valid"),
+        Ok(_) => LaunchCheckOutcome::fail(
+            "enabledmods.json does not contain an object".to_string(),
+            "Rebuild enabledmods.json from the repair window",
+        ),
+        Err(err) => {
+            LaunchCheckOutcome::fail(err, "Rebuild enabledmods.json from the repair window")
+        }
+    }
+}
+
+/// Leftovers of interrupted installs may end up being loaded as mods
+fn check_leftover_temp_dir(context: &LaunchCheckContext) -> LaunchCheckOutcome {
+    let temp_dir = format!("{}/___flightcore-temp", context.game_install.game_path);
+
+    if Path::new(&temp_dir).exists() {
+        LaunchCheckOutcome::warn(
+            "Found leftover files from an interrupted install".to_string(),
+            "Clean up the download folder from the repair window",
+        )
+    } else {
+        LaunchCheckOutcome::pass("No leftover files")
+    }
+}
+
+/// The game needs some free space for logs, save data and the shader cache
+fn check_disk_space(context: &LaunchCheckContext) -> LaunchCheckOutcome {
+    let game_path = match std::fs::canonicalize(&context.game_install.game_path) {
+        Ok(game_path) => game_path,
+        Err(err) => {
+            return LaunchCheckOutcome::fail(
+                format!("Couldn't access Titanfall2 directory: {err}"),
+                "Make sure the game location is correct",
+            )
+        }
+    };
+
+    // The disk holding the game is the one with the longest mount point containing the game path
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+    let Some(disk) = disks
+        .list()
+        .iter()
+        .filter(|disk| game_path.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+    else {
+        return LaunchCheckOutcome::pass("Couldn't determine free space");
+    };
+
+    let available_space = disk.available_space();
+    let message = format!("{} MiB free", available_space / 1024 / 1024);
+    if available_space < MIN_FREE_DISK_SPACE {
+        LaunchCheckOutcome::fail(
+            message,
+            "Free up space on the drive Titanfall2 is installed on",
+        )
+    } else if available_space < LOW_FREE_DISK_SPACE {
+        LaunchCheckOutcome::warn(
+            message,
+            "Free up space on the drive Titanfall2 is installed on",
+        )
+    } else {
+        LaunchCheckOutcome::pass(&message)
+    }
+}
+
+/// Runs all pre-launch checks
+pub fn run_launch_checks(context: &LaunchCheckContext) -> Vec<LaunchCheckResult> {
+    LAUNCH_CHECKS
+        .iter()
+        .map(|(name, check)| {
+            let outcome = check(context);
+            LaunchCheckResult {
+                name: name.to_string(),
+                status: outcome.status,
+                message: outcome.message,
+                fix_suggestion: outcome.fix_suggestion,
+            }
+        })
+        .collect()
+}
+
+/// Runs all pre-launch checks for the given install and launch options
+#[tauri::command]
+pub fn run_launch_checks_wrapper(
+    app: tauri::AppHandle,
+    game_install: GameInstall,
+    launch_options: NorthstarLaunchOptions,
+) -> Vec<LaunchCheckResult> {
+    let launch_via_steam = is_launched_via_steam(&game_install, &launch_options);
+
+    run_launch_checks(&LaunchCheckContext {
+        game_install,
+        launch_via_steam,
+        process_snapshot: app.state::<ProcessMonitor>().refresh(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InstallType;
+
+    fn context_for(game_path: &Path) -> LaunchCheckContext {
+        LaunchCheckContext {
+            game_install: GameInstall {
+                game_path: game_path.display().to_string(),
+                profile: "R2Northstar".to_string(),
+                install_type: InstallType::ORIGIN,
+                wine_prefix: None,
+            },
+            launch_via_steam: false,
+            process_snapshot: ProcessSnapshot::default(),
+        }
+    }
+
+    #[test]
+    fn test_check_enabled_mods_json() {
+        let game_dir = tempfile::tempdir().unwrap();
+        let context = context_for(game_dir.path());
+        std::fs::create_dir_all(game_dir.path().join("R2Northstar")).unwrap();
+
+        assert_eq!(
+            check_enabled_mods_json(&context).status,
+            LaunchCheckStatus::Pass
+        );
+
+        let enabledmods_json_path = game_dir.path().join("R2Northstar/enabledmods.json");
+        std::fs::write(&enabledmods_json_path, "{\"Northstar.Client\": true,").unwrap();
+        assert_eq!(
+            check_enabled_mods_json(&context).status,
+            LaunchCheckStatus::Fail
+        );
+
+        std::fs::write(&enabledmods_json_path, "{\"Northstar.Client\": true}").unwrap();
+        assert_eq!(
+            check_enabled_mods_json(&context).status,
+            LaunchCheckStatus::Pass
+        );
+    }
+
+    #[test]
+    fn test_check_leftover_temp_dir() {
+        let game_dir = tempfile::tempdir().unwrap();
+        let context = context_for(game_dir.path());
+
+        assert_eq!(
+            check_leftover_temp_dir(&context).status,
+            LaunchCheckStatus::Pass
+        );
+
+        std::fs::create_dir_all(game_dir.path().join("___flightcore-temp")).unwrap();
+        let outcome = check_leftover_temp_dir(&context);
+        assert_eq!(outcome.status, LaunchCheckStatus::Warn);
+        assert!(outcome.fix_suggestion.is_some());
+    }
+}
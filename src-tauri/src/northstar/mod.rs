@@ -1,13 +1,20 @@
 //! This module deals with handling things around Northstar such as
 //! - getting version number
 pub mod install;
+pub mod launch_checks;
 pub mod profile;
+pub mod relaunch;
+pub mod session;
 
-use crate::util::check_ea_app_or_origin_running;
+use crate::process_monitor::ProcessMonitor;
+use crate::thunderstore::index::get_package_index;
 use crate::{constants::CORE_MODS, platform_specific::get_host_os, GameInstall, InstallType};
 use crate::{NorthstarThunderstoreRelease, NorthstarThunderstoreReleaseWrapper};
 use anyhow::anyhow;
+use launch_checks::LaunchCheckStatus;
 use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::Manager;
 use ts_rs::TS;
 
 #[derive(Serialize, Deserialize, Debug, Clone, TS)]
@@ -17,17 +24,40 @@ pub struct NorthstarLaunchOptions {
     bypass_checks: bool,
 }
 
+/// A Northstar process spawned by FlightCore
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct NorthstarProcess {
+    pub pid: u32,
+    pub profile: String,
+    pub game_path: String,
+}
+
+#[derive(Default)]
+pub struct LaunchedNorthstarState {
+    /// Process FlightCore launched Northstar in, if any
+    /// Only set for launch methods where FlightCore spawns the game process directly
+    pub child: Option<std::process::Child>,
+    /// Install and options of the last launch, kept around for relaunching
+    pub launch: Option<(GameInstall, NorthstarLaunchOptions)>,
+    /// Whether the next detected session still has to be attributed to the last launch
+    pub awaiting_session: bool,
+}
+
+/// Northstar as last launched by FlightCore
+#[derive(Default)]
+pub struct LaunchedNorthstar(pub Mutex<LaunchedNorthstarState>);
+
 /// Gets list of available Northstar versions from Thunderstore
 #[tauri::command]
 pub async fn get_available_northstar_versions(
-) -> Result<Vec<NorthstarThunderstoreReleaseWrapper>, ()> {
+) -> Result<Vec<NorthstarThunderstoreReleaseWrapper>, String> {
     let northstar_package_name = "Northstar";
-    let index = thermite::api::get_package_index().unwrap().to_vec();
+    let index = get_package_index().await?;
     let nsmod = index
         .iter()
         .find(|f| f.name.to_lowercase() == northstar_package_name.to_lowercase())
-        .ok_or_else(|| panic!("Couldn't find Northstar on thunderstore???"))
-        .unwrap();
+        .ok_or("Couldn't find Northstar on thunderstore???")?;
 
     let mut releases: Vec<NorthstarThunderstoreReleaseWrapper> = vec![];
     for nsmod_version_obj in nsmod.versions.values() {
@@ -60,31 +90,30 @@ pub async fn get_available_northstar_versions(
 /// Checks if installed Northstar version is up-to-date
 /// false -> Northstar install is up-to-date
 /// true  -> Northstar install is outdated
+///
+/// Profiles are checked against the Northstar package they were installed with,
+/// the passed package name is only used for profiles without such a record.
 #[tauri::command]
 pub async fn check_is_northstar_outdated(
     game_install: GameInstall,
     northstar_package_name: Option<String>,
 ) -> Result<bool, String> {
-    let northstar_package_name = match northstar_package_name {
-        Some(northstar_package_name) => {
-            if northstar_package_name.len() <= 1 {
-                "Northstar".to_string()
-            } else {
-                northstar_package_name
-            }
-        }
-        None => "Northstar".to_string(),
+    let index = match get_package_index().await {
+        Ok(res) => res,
+        Err(err) => return Err(format!("Couldn't check if Northstar up-to-date: {err}")),
     };
 
-    let index = match thermite::api::get_package_index() {
-        Ok(res) => res.to_vec(),
-        Err(err) => return Err(format!("Couldn't check if Northstar up-to-date: {err}")),
+    let northstar_package_name =
+        get_profile_northstar_package_name(&game_install, northstar_package_name);
+
+    let latest_version = match get_latest_northstar_version(&index, &northstar_package_name) {
+        Some(latest_version) => latest_version,
+        None => {
+            return Err(format!(
+                "Couldn't find {northstar_package_name} on Thunderstore"
+            ))
+        }
     };
-    let nmod = index
-        .iter()
-        .find(|f| f.name.to_lowercase() == northstar_package_name.to_lowercase())
-        .expect("Couldn't find Northstar on thunderstore???");
-    // .ok_or_else(|| anyhow!("Couldn't find Northstar on thunderstore???"))?;
 
     let version_number = match get_northstar_version_number(game_install) {
         Ok(version_number) => version_number,
@@ -95,10 +124,7 @@ pub async fn check_is_northstar_outdated(
         }
     };
 
-    // Release candidate version numbers are different between `mods.json` and Thunderstore
-    let version_number = crate::util::convert_release_candidate_number(version_number);
-
-    if version_number != nmod.latest {
+    if is_northstar_version_outdated(version_number, &latest_version) {
         log::info!("Installed Northstar version outdated");
         Ok(true)
     } else {
@@ -107,6 +133,98 @@ pub async fn check_is_northstar_outdated(
     }
 }
 
+/// Northstar install state of a single profile
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct ProfileNorthstarStatus {
+    profile: String,
+    package_name: String,
+    installed_version: Option<String>,
+    latest_version: Option<String>,
+    is_outdated: bool,
+}
+
+/// Checks the Northstar install of every profile in the game install
+#[tauri::command]
+pub async fn check_profiles_northstar_outdated(
+    game_install: GameInstall,
+    northstar_package_name: Option<String>,
+) -> Result<Vec<ProfileNorthstarStatus>, String> {
+    let index = match get_package_index().await {
+        Ok(res) => res,
+        Err(err) => return Err(format!("Couldn't check if Northstar up-to-date: {err}")),
+    };
+
+    let mut statuses: Vec<ProfileNorthstarStatus> = vec![];
+    for profile in profile::fetch_profiles(game_install.clone())? {
+        if !profile::validate_profile(game_install.clone(), profile.clone()) {
+            continue;
+        }
+
+        let profile_install = GameInstall {
+            profile: profile.clone(),
+            ..game_install.clone()
+        };
+
+        let package_name =
+            get_profile_northstar_package_name(&profile_install, northstar_package_name.clone());
+        let latest_version = get_latest_northstar_version(&index, &package_name);
+        let installed_version = get_northstar_version_number(profile_install).ok();
+
+        let is_outdated = match (&installed_version, &latest_version) {
+            (Some(installed_version), Some(latest_version)) => {
+                is_northstar_version_outdated(installed_version.clone(), latest_version)
+            }
+            _ => false,
+        };
+
+        statuses.push(ProfileNorthstarStatus {
+            profile,
+            package_name,
+            installed_version,
+            latest_version,
+            is_outdated,
+        });
+    }
+
+    Ok(statuses)
+}
+
+/// Gets the Northstar package name (`Northstar` vs `NorthstarReleaseCandidate`) a profile should be checked against
+fn get_profile_northstar_package_name(
+    game_install: &GameInstall,
+    fallback_package_name: Option<String>,
+) -> String {
+    if let Ok(northstar_install) = profile::read_profile_northstar_install(game_install) {
+        return northstar_install.package_name;
+    }
+
+    match fallback_package_name {
+        Some(northstar_package_name) if northstar_package_name.len() > 1 => northstar_package_name,
+        _ => "Northstar".to_string(),
+    }
+}
+
+/// Gets the latest version of the given Northstar package from the package index
+fn get_latest_northstar_version(
+    index: &[thermite::model::Mod],
+    northstar_package_name: &str,
+) -> Option<String> {
+    index
+        .iter()
+        .find(|f| f.name.to_lowercase() == northstar_package_name.to_lowercase())
+        .map(|nmod| nmod.latest.clone())
+}
+
+/// Compares installed Northstar version against latest version on Thunderstore
+/// Newer installs such as release candidates or dev builds are not considered outdated
+fn is_northstar_version_outdated(version_number: String, latest_version: &str) -> bool {
+    // Release candidate version numbers are different between `mods.json` and Thunderstore
+    let version_number = crate::util::convert_release_candidate_number(version_number);
+
+    crate::util::is_update_available(&version_number, latest_version)
+}
+
 /// Check version number of a mod
 pub fn check_mod_version_number(path_to_mod_folder: &str) -> Result<String, anyhow::Error> {
     let data = std::fs::read_to_string(format!("{path_to_mod_folder}/mod.json"))?;
@@ -158,46 +276,82 @@ pub fn get_northstar_version_number(game_install: GameInstall) -> Result<String,
 /// Launches Northstar
 #[tauri::command]
 pub fn launch_northstar(
+    app: tauri::AppHandle,
     game_install: GameInstall,
     launch_options: NorthstarLaunchOptions,
 ) -> Result<String, String> {
     dbg!(game_install.clone());
 
-    if launch_options.launch_via_steam {
-        return launch_northstar_steam(game_install);
-    }
-
-    let host_os = get_host_os();
-
-    // Explicitly fail early certain (currently) unsupported install setups
-    if host_os != "windows" {
-        if !matches!(game_install.install_type, InstallType::STEAM) {
-            return Err(format!(
-                "Not yet implemented for \"{}\" with Titanfall2 installed via \"{:?}\"",
-                get_host_os(),
-                game_install.install_type
-            ));
-        }
-
-        return launch_northstar_steam(game_install);
-    }
+    // Includes the master server selected for the profile
+    let profile_launch_options = get_validated_profile_launch_options(&game_install)?;
+    let launch_via_steam = is_launched_via_steam(&game_install, &launch_options);
 
     // Only check guards if bypassing checks is not enabled
     if !launch_options.bypass_checks {
-        // Some safety checks before, should have more in the future
-        if get_northstar_version_number(game_install.clone()).is_err() {
-            return Err(anyhow!("Not all checks were met").to_string());
+        let check_results = launch_checks::run_launch_checks(&launch_checks::LaunchCheckContext {
+            game_install: game_install.clone(),
+            launch_via_steam,
+            process_snapshot: app.state::<ProcessMonitor>().refresh(),
+        });
+
+        let mut failed_checks: Vec<String> = vec![];
+        for check_result in check_results {
+            match check_result.status {
+                LaunchCheckStatus::Pass => {}
+                LaunchCheckStatus::Warn => {
+                    log::warn!("{}: {}", check_result.name, check_result.message)
+                }
+                LaunchCheckStatus::Fail => {
+                    failed_checks.push(format!("{}: {}", check_result.name, check_result.message))
+                }
+            }
         }
 
-        // Require EA App or Origin to be running to launch Northstar
-        let ea_app_is_running = check_ea_app_or_origin_running();
-        if !ea_app_is_running {
+        if !failed_checks.is_empty() {
             return Err(
-                anyhow!("EA App not running, start EA App before launching Northstar").to_string(),
+                anyhow!("Not all checks were met\n{}", failed_checks.join("\n")).to_string(),
             );
         }
     }
 
+    let result = start_northstar(
+        &app,
+        game_install.clone(),
+        launch_via_steam,
+        &profile_launch_options,
+    );
+
+    // Attribute the upcoming session to the launched profile
+    // Only after a successful launch, a failed one would claim the next session started elsewhere
+    if result.is_ok() {
+        let launched_northstar = app.state::<LaunchedNorthstar>();
+        let mut launched = launched_northstar.0.lock().unwrap();
+        launched.launch = Some((game_install, launch_options));
+        launched.awaiting_session = true;
+    }
+
+    result
+}
+
+/// Starts Northstar using the launch method matching the install
+fn start_northstar(
+    app: &tauri::AppHandle,
+    game_install: GameInstall,
+    launch_via_steam: bool,
+    profile_launch_options: &profile::ProfileLaunchOptions,
+) -> Result<String, String> {
+    if launch_via_steam {
+        return launch_northstar_steam(game_install, profile_launch_options);
+    }
+
+    let host_os = get_host_os();
+
+    // Non-Steam installs on Linux run through the selected Wine/Proton build
+    #[cfg(target_os = "linux")]
+    if host_os == "linux" {
+        return launch_northstar_linux(app, game_install, profile_launch_options);
+    }
+
     // Switch to Titanfall2 directory for launching
     // NorthstarLauncher.exe expects to be run from that folder
     if std::env::set_current_dir(game_install.game_path.clone()).is_err() {
@@ -214,11 +368,26 @@ pub fn launch_northstar(
         let ns_exe_path = format!("{}/NorthstarLauncher.exe", game_install.game_path);
         let ns_profile_arg = format!("-profile={}", game_install.profile);
 
-        let mut output = std::process::Command::new("C:\\Windows\\System32\\cmd.exe")
-            .args(["/C", "start", "", &ns_exe_path, &ns_profile_arg])
+        // Spawned directly rather than through `cmd /C start` so that arguments are never
+        // interpreted by the shell
+        let mut command = std::process::Command::new(ns_exe_path);
+        command
+            .arg(ns_profile_arg)
+            .envs(&profile_launch_options.environment);
+        for arg in profile_launch_options.to_args() {
+            // Passed as is, escaping would break the quotes around convar values
+            // `validate` rejects quotes and trailing backslashes that could break out of them
+            #[cfg(target_os = "windows")]
+            std::os::windows::process::CommandExt::raw_arg(&mut command, arg);
+            #[cfg(not(target_os = "windows"))]
+            command.arg(arg);
+        }
+        let child = command
             .spawn()
-            .expect("failed to execute process");
-        output.wait().expect("failed waiting on child process");
+            .map_err(|err| format!("Failed to launch Northstar: {err}"))?;
+
+        let launched_northstar = app.state::<LaunchedNorthstar>();
+        launched_northstar.0.lock().unwrap().child = Some(child);
         return Ok("Launched game".to_string());
     }
 
@@ -229,8 +398,87 @@ pub fn launch_northstar(
     ))
 }
 
+/// Whether the game is started by Steam rather than by FlightCore
+/// Steam installs on Linux are always launched through Steam
+pub fn is_launched_via_steam(
+    game_install: &GameInstall,
+    launch_options: &NorthstarLaunchOptions,
+) -> bool {
+    launch_options.launch_via_steam
+        || (get_host_os() != "windows" && matches!(game_install.install_type, InstallType::STEAM))
+}
+
+/// Launches Northstar through Wine/Proton and keeps track of the spawned process
+#[cfg(target_os = "linux")]
+fn launch_northstar_linux(
+    app: &tauri::AppHandle,
+    game_install: GameInstall,
+    profile_launch_options: &profile::ProfileLaunchOptions,
+) -> Result<String, String> {
+    use crate::platform_specific::{get_selected_runner, linux};
+    use tauri::Emitter;
+
+    let runner = linux::resolve_runner(get_selected_runner(app)?)?;
+    let child = linux::launch_northstar_wine(&runner, &game_install, profile_launch_options)?;
+
+    let northstar_process = NorthstarProcess {
+        pid: child.id(),
+        profile: game_install.profile,
+        game_path: game_install.game_path,
+    };
+    log::info!("Launched Northstar with PID {}", northstar_process.pid);
+
+    let launched_northstar = app.state::<LaunchedNorthstar>();
+    launched_northstar.0.lock().unwrap().child = Some(child);
+
+    if let Err(err) = app.emit("northstar-launched", &northstar_process) {
+        log::warn!("Failed emitting launch event: {err}");
+    }
+
+    Ok(format!("Launched game via {}", runner.name))
+}
+
+/// Reads the custom launch options of the profile to launch
+/// Re-validated as the file might have been edited by hand
+fn get_validated_profile_launch_options(
+    game_install: &GameInstall,
+) -> Result<profile::ProfileLaunchOptions, String> {
+    let profile_launch_options = profile::read_profile_launch_options(game_install)
+        .map_err(|err| format!("Failed reading launch options: {err}"))?;
+    profile_launch_options.validate()?;
+    Ok(profile_launch_options)
+}
+
+/// Key Steam stores the compatibility tool selected for all games under
+const STEAM_DEFAULT_COMPAT_TOOL_ID: u32 = 0;
+
+/// Compatibility tool Steam runs Titanfall2 with
+/// Falls back to the one selected for all games if Titanfall2 has none of its own
+pub fn get_titanfall2_compat_tool(
+    map: &std::collections::HashMap<u32, steamlocate::CompatTool>,
+) -> Option<&steamlocate::CompatTool> {
+    map.get(&thermite::TITANFALL2_STEAM_ID)
+        .or_else(|| map.get(&STEAM_DEFAULT_COMPAT_TOOL_ID))
+}
+
+/// Percent-encodes a launch argument for use in a `steam://run` URL
+/// Keeps `/` and `:` in values such as URLs from ending or breaking the URL
+fn encode_steam_launch_arg(arg: &str) -> String {
+    arg.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'=' | b'+' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
 /// Prepare Northstar and Launch through Steam using the Browser Protocol
-pub fn launch_northstar_steam(game_install: GameInstall) -> Result<String, String> {
+pub fn launch_northstar_steam(
+    game_install: GameInstall,
+    profile_launch_options: &profile::ProfileLaunchOptions,
+) -> Result<String, String> {
     if !matches!(game_install.install_type, InstallType::STEAM) {
         return Err("Titanfall2 was not installed via Steam".to_string());
     }
@@ -239,7 +487,7 @@ pub fn launch_northstar_steam(game_install: GameInstall) -> Result<String, Strin
         Ok(steamdir) => {
             if get_host_os() != "windows" {
                 match steamdir.compat_tool_mapping() {
-                    Ok(map) => match map.get(&thermite::TITANFALL2_STEAM_ID) {
+                    Ok(map) => match get_titanfall2_compat_tool(&map) {
                         Some(_) => {}
                         None => {
                             return Err(
@@ -259,18 +507,109 @@ pub fn launch_northstar_steam(game_install: GameInstall) -> Result<String, Strin
         }
     }
 
+    // Steam starts the game itself so environment variables have to be set in Steam instead
+    if !profile_launch_options.environment.is_empty() {
+        return Err(
+            "Environment variables can't be applied when launching via Steam, set them in the Steam launch options instead"
+                .to_string(),
+        );
+    }
+
     // Switch to Titanfall2 directory to set everything up
     if std::env::set_current_dir(game_install.game_path).is_err() {
         // We failed to get to Titanfall2 directory
         return Err("Couldn't access Titanfall2 directory".to_string());
     }
 
+    let mut ns_args = vec![format!("-profile={}", game_install.profile)];
+    ns_args.extend(profile_launch_options.to_args());
+    let ns_args: Vec<String> = ns_args
+        .iter()
+        .map(|arg| encode_steam_launch_arg(arg))
+        .collect();
+
     match open::that(format!(
-        "steam://run/{}//-profile={} --northstar/",
+        "steam://run/{}//{} --northstar/",
         thermite::TITANFALL2_STEAM_ID,
-        game_install.profile
+        ns_args.join(" ")
     )) {
         Ok(()) => Ok("Started game".to_string()),
         Err(_err) => Err("Failed to launch Titanfall 2 via Steam".to_string()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_titanfall2_compat_tool() {
+        let compat_tool = |name: &str| steamlocate::CompatTool {
+            name: Some(name.to_string()),
+            config: None,
+            priority: None,
+        };
+
+        let mut map = std::collections::HashMap::new();
+        assert!(get_titanfall2_compat_tool(&map).is_none());
+
+        map.insert(
+            STEAM_DEFAULT_COMPAT_TOOL_ID,
+            compat_tool("proton_experimental"),
+        );
+        assert_eq!(
+            get_titanfall2_compat_tool(&map).unwrap().name.as_deref(),
+            Some("proton_experimental")
+        );
+
+        map.insert(
+            thermite::TITANFALL2_STEAM_ID,
+            compat_tool("NorthstarProton8-28"),
+        );
+        assert_eq!(
+            get_titanfall2_compat_tool(&map).unwrap().name.as_deref(),
+            Some("NorthstarProton8-28")
+        );
+    }
+
+    #[test]
+    fn test_encode_steam_launch_arg() {
+        assert_eq!(
+            encode_steam_launch_arg("-profile=R2Northstar"),
+            "-profile=R2Northstar"
+        );
+        assert_eq!(encode_steam_launch_arg("+fps_max"), "+fps_max");
+        assert_eq!(
+            encode_steam_launch_arg("https://ms.example.com"),
+            "https%3A%2F%2Fms.example.com"
+        );
+        assert_eq!(encode_steam_launch_arg("a b&c"), "a%20b%26c");
+    }
+
+    #[test]
+    fn test_is_northstar_version_outdated() {
+        assert!(is_northstar_version_outdated(
+            "1.19.0".to_string(),
+            "1.19.1"
+        ));
+        assert!(!is_northstar_version_outdated(
+            "1.19.1".to_string(),
+            "1.19.1"
+        ));
+        // Newer release candidate than the latest release
+        assert!(!is_northstar_version_outdated(
+            "1.20.0-rc1".to_string(),
+            "1.19.10"
+        ));
+        // Same release candidate as on Thunderstore
+        assert!(!is_northstar_version_outdated(
+            "1.19.10-rc1".to_string(),
+            "1.19.1001"
+        ));
+        // Newer dev build
+        assert!(!is_northstar_version_outdated(
+            "1.20.0-dev".to_string(),
+            "1.19.10"
+        ));
+    }
+}
@@ -1,5 +1,126 @@
+use crate::constants::{PROFILE_LAUNCH_OPTIONS_FILE, PROFILE_NORTHSTAR_INSTALL_FILE};
 use crate::util::copy_dir_all;
 use crate::GameInstall;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use ts_rs::TS;
+
+/// Northstar package and version a profile owns
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct ProfileNorthstarInstall {
+    pub package_name: String,
+    pub version: String,
+}
+
+/// Custom options a profile is launched with
+/// - extra command line arguments such as `-novid` or `-multiple`
+/// - convars passed as `+name value`
+/// - environment variables (only applied when FlightCore spawns the game itself, e.g. via Proton)
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, TS)]
+#[ts(export)]
+pub struct ProfileLaunchOptions {
+    #[serde(default)]
+    pub arguments: Vec<String>,
+    #[serde(default)]
+    pub convars: BTreeMap<String, String>,
+    #[serde(default)]
+    pub environment: BTreeMap<String, String>,
+}
+
+/// Arguments FlightCore sets itself to select the profile and that may not be overridden
+const RESERVED_ARGUMENTS: [&str; 2] = ["-profile", "-vanilla"];
+
+/// Environment variables FlightCore sets itself to select the wine prefix and load Northstar
+const RESERVED_ENVIRONMENT: [&str; 4] = [
+    "WINEPREFIX",
+    "STEAM_COMPAT_DATA_PATH",
+    "STEAM_COMPAT_CLIENT_INSTALL_PATH",
+    "WINEDLLOVERRIDES",
+];
+
+impl ProfileLaunchOptions {
+    /// Rejects options that would break launching or the profile selection
+    pub fn validate(&self) -> Result<(), String> {
+        let identifier = Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$").unwrap();
+
+        for argument in &self.arguments {
+            if argument.is_empty() || argument.contains(char::is_whitespace) {
+                return Err(format!(
+                    "Invalid argument \"{argument}\", pass each argument separately"
+                ));
+            }
+            // Arguments are passed on as is on Windows, quotes would break the command line
+            if argument.contains('"') || argument.ends_with('\\') {
+                return Err(format!("Invalid argument \"{argument}\""));
+            }
+            if argument.starts_with('+') {
+                return Err(format!(
+                    "Invalid argument \"{argument}\", add convars as convars instead"
+                ));
+            }
+            let name = argument.split('=').next().unwrap_or_default();
+            if RESERVED_ARGUMENTS
+                .iter()
+                .any(|reserved| name.eq_ignore_ascii_case(reserved))
+            {
+                return Err(format!(
+                    "\"{argument}\" is set by FlightCore and would break profile selection"
+                ));
+            }
+        }
+
+        for (name, value) in &self.convars {
+            if !identifier.is_match(name) {
+                return Err(format!("Invalid convar name \"{name}\""));
+            }
+            // A trailing `\` would escape the quote added around the value
+            if value.is_empty()
+                || value.contains(|c: char| c.is_whitespace() || c == '"' || c == ';')
+                || value.ends_with('\\')
+            {
+                return Err(format!("Invalid value \"{value}\" for convar \"{name}\""));
+            }
+        }
+
+        for (name, value) in &self.environment {
+            if !identifier.is_match(name) {
+                return Err(format!("Invalid environment variable name \"{name}\""));
+            }
+            if RESERVED_ENVIRONMENT.contains(&name.as_str()) {
+                return Err(format!(
+                    "\"{name}\" is set by FlightCore and would break launching through Wine"
+                ));
+            }
+            if value.contains('\0') {
+                return Err(format!("Invalid value for environment variable \"{name}\""));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Command line arguments to append after `-profile=`
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = self.arguments.clone();
+        for (name, value) in &self.convars {
+            args.push(format!("+{name}"));
+            args.push(format_convar_value(value));
+        }
+        args
+    }
+}
+
+/// Quotes convar values the game would otherwise split or cut off
+/// Unquoted `//` starts a comment, e.g. in URLs
+fn format_convar_value(value: &str) -> String {
+    if value.contains(char::is_whitespace) || value.contains("//") {
+        format!("\"{value}\"")
+    } else {
+        value.to_string()
+    }
+}
 
 // These folders are part of Titanfall 2 and
 // should NEVER be used as a Profile
@@ -15,7 +136,7 @@ const SKIP_PATHS: [&str; 8] = [
 ];
 
 // A profile may have one of these to be detected
-const MAY_CONTAIN: [&str; 10] = [
+const MAY_CONTAIN: [&str; 12] = [
     "mods/",
     "plugins/",
     "packages/",
@@ -26,6 +147,8 @@ const MAY_CONTAIN: [&str; 10] = [
     "enabledmods.json",
     "placeholder.playerdata.pdata",
     "LEGAL.txt",
+    PROFILE_NORTHSTAR_INSTALL_FILE,
+    PROFILE_LAUNCH_OPTIONS_FILE,
 ];
 
 /// Returns a list of Profile names
@@ -59,6 +182,18 @@ pub fn fetch_profiles(game_install: GameInstall) -> Result<Vec<String>, String>
     Ok(profiles)
 }
 
+/// Checks whether the given name can be used for a new profile
+/// Only plain folder names are allowed, never ones that lead elsewhere or belong to the game
+pub fn is_valid_profile_name(profile: &str) -> bool {
+    let re = regex::Regex::new(r"^[a-zA-Z0-9_. -]+$").unwrap();
+    re.is_match(profile)
+        && !profile.trim().is_empty()
+        && !profile.contains("..")
+        && !SKIP_PATHS
+            .iter()
+            .any(|skip_path| skip_path.eq_ignore_ascii_case(profile.trim()))
+}
+
 /// Validates if a given profile is actually a valid profile
 #[tauri::command]
 pub fn validate_profile(game_install: GameInstall, profile: String) -> bool {
@@ -119,3 +254,176 @@ pub fn clone_profile(
 
     Ok(())
 }
+
+/// Reads which Northstar package and version was installed into the profile
+pub fn read_profile_northstar_install(
+    game_install: &GameInstall,
+) -> Result<ProfileNorthstarInstall, anyhow::Error> {
+    let path = format!(
+        "{}/{}/{}",
+        game_install.game_path, game_install.profile, PROFILE_NORTHSTAR_INSTALL_FILE
+    );
+    let data = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&data)?)
+}
+
+/// Records which Northstar package and version was installed into the profile
+pub fn write_profile_northstar_install(
+    game_install: &GameInstall,
+    northstar_install: &ProfileNorthstarInstall,
+) -> Result<(), anyhow::Error> {
+    let path = format!(
+        "{}/{}/{}",
+        game_install.game_path, game_install.profile, PROFILE_NORTHSTAR_INSTALL_FILE
+    );
+    std::fs::write(path, serde_json::to_string_pretty(northstar_install)?)?;
+    Ok(())
+}
+
+/// Reads the custom launch options of the profile, defaults if none were set
+pub fn read_profile_launch_options(
+    game_install: &GameInstall,
+) -> Result<ProfileLaunchOptions, anyhow::Error> {
+    let path = format!(
+        "{}/{}/{}",
+        game_install.game_path, game_install.profile, PROFILE_LAUNCH_OPTIONS_FILE
+    );
+    if !std::path::Path::new(&path).is_file() {
+        return Ok(ProfileLaunchOptions::default());
+    }
+    let data = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&data)?)
+}
+
+/// Returns the custom launch options of the profile
+#[tauri::command]
+pub fn get_profile_launch_options(
+    game_install: GameInstall,
+) -> Result<ProfileLaunchOptions, String> {
+    read_profile_launch_options(&game_install)
+        .map_err(|err| format!("Failed reading launch options: {err}"))
+}
+
+/// Validates and stores the custom launch options of the profile
+#[tauri::command]
+pub fn set_profile_launch_options(
+    game_install: GameInstall,
+    launch_options: ProfileLaunchOptions,
+) -> Result<(), String> {
+    if !validate_profile(game_install.clone(), game_install.profile.clone()) {
+        return Err(format!("{} is not a valid Profile", game_install.profile));
+    }
+    launch_options.validate()?;
+
+    let path = format!(
+        "{}/{}/{}",
+        game_install.game_path, game_install.profile, PROFILE_LAUNCH_OPTIONS_FILE
+    );
+    let data = serde_json::to_string_pretty(&launch_options).map_err(|err| err.to_string())?;
+    std::fs::write(path, data).map_err(|err| format!("Failed saving launch options: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_profile_name() {
+        assert!(is_valid_profile_name("R2Northstar"));
+        assert!(is_valid_profile_name("my profile 2"));
+        assert!(!is_valid_profile_name("vpk"));
+        assert!(!is_valid_profile_name("core"));
+        assert!(!is_valid_profile_name(".."));
+        assert!(!is_valid_profile_name("../x"));
+        assert!(!is_valid_profile_name("a/b"));
+        assert!(!is_valid_profile_name(" "));
+    }
+
+    #[test]
+    fn test_launch_options_to_args() {
+        let launch_options = ProfileLaunchOptions {
+            arguments: vec!["-novid".to_string(), "-multiple".to_string()],
+            convars: BTreeMap::from([("fps_max".to_string(), "144".to_string())]),
+            environment: BTreeMap::new(),
+        };
+
+        assert!(launch_options.validate().is_ok());
+        assert_eq!(
+            launch_options.to_args(),
+            vec!["-novid", "-multiple", "+fps_max", "144"]
+        );
+    }
+
+    #[test]
+    fn test_launch_options_to_args_quotes_urls() {
+        let launch_options = ProfileLaunchOptions {
+            convars: BTreeMap::from([(
+                "ns_masterserver_hostname".to_string(),
+                "https://ms.example.com".to_string(),
+            )]),
+            ..Default::default()
+        };
+
+        assert!(launch_options.validate().is_ok());
+        assert_eq!(
+            launch_options.to_args(),
+            vec!["+ns_masterserver_hostname", "\"https://ms.example.com\""]
+        );
+    }
+
+    #[test]
+    fn test_launch_options_reject_profile_selection() {
+        for argument in [
+            "-profile=other",
+            "-PROFILE",
+            "-vanilla",
+            "-novid -profile=x",
+        ] {
+            let launch_options = ProfileLaunchOptions {
+                arguments: vec![argument.to_string()],
+                ..Default::default()
+            };
+            assert!(launch_options.validate().is_err(), "{argument}");
+        }
+    }
+
+    #[test]
+    fn test_launch_options_reject_quotes() {
+        for argument in ["-foo\"", "\"-foo", "-path=C:\\Games\\"] {
+            let launch_options = ProfileLaunchOptions {
+                arguments: vec![argument.to_string()],
+                ..Default::default()
+            };
+            assert!(launch_options.validate().is_err(), "{argument}");
+        }
+
+        let launch_options = ProfileLaunchOptions {
+            convars: BTreeMap::from([("sv_path".to_string(), "C:\\Games\\".to_string())]),
+            ..Default::default()
+        };
+        assert!(launch_options.validate().is_err());
+    }
+
+    #[test]
+    fn test_launch_options_reject_invalid_convars_and_environment() {
+        let launch_options = ProfileLaunchOptions {
+            convars: BTreeMap::from([("fps_max".to_string(), "0; quit".to_string())]),
+            ..Default::default()
+        };
+        assert!(launch_options.validate().is_err());
+
+        for name in ["WINEPREFIX", "WINEDLLOVERRIDES"] {
+            let launch_options = ProfileLaunchOptions {
+                environment: BTreeMap::from([(name.to_string(), "/tmp".to_string())]),
+                ..Default::default()
+            };
+            assert!(launch_options.validate().is_err());
+        }
+
+        let launch_options = ProfileLaunchOptions {
+            environment: BTreeMap::from([("DXVK_HUD".to_string(), "fps".to_string())]),
+            ..Default::default()
+        };
+        assert!(launch_options.validate().is_ok());
+    }
+}
@@ -0,0 +1,255 @@
+//! Stopping and relaunching Northstar
+//!
+//! Mod changes made while the game is running only take effect after a restart. They can be
+//! queued and are applied by `relaunch_northstar` between stopping and starting the game again.
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::Manager;
+use tokio::time::sleep;
+use ts_rs::TS;
+
+use crate::mod_management::{fc_download_mod_and_install, set_mod_enabled_status};
+use crate::northstar::{launch_northstar, LaunchedNorthstar};
+use crate::process_monitor::ProcessMonitor;
+use crate::repair_and_verify::clean_up_download_folder;
+use crate::GameInstall;
+
+/// How long the game gets to shut down before it is killed unless specified otherwise
+const DEFAULT_STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often to check whether the game has exited while waiting
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A mod change to apply on the next relaunch
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+#[ts(export)]
+pub enum PendingModOperation {
+    SetEnabled {
+        mod_name: String,
+        mod_version: String,
+        is_enabled: bool,
+    },
+    Install {
+        thunderstore_mod_string: String,
+    },
+}
+
+/// Outcome of relaunching Northstar
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct RelaunchResult {
+    /// Result of launching the game again
+    pub message: String,
+    /// Errors of queued mod changes that failed, these stay queued
+    pub failed_mod_operations: Vec<String>,
+}
+
+/// Mod changes queued per install and profile
+#[derive(Default)]
+pub struct PendingModOperations(Mutex<Vec<(GameInstall, PendingModOperation)>>);
+
+/// Whether both refer to the same profile of the same install
+fn is_same_profile(a: &GameInstall, b: &GameInstall) -> bool {
+    a.game_path == b.game_path && a.profile == b.profile
+}
+
+/// Queues a mod change to be applied on the next relaunch
+#[tauri::command]
+pub fn queue_mod_operation(
+    app: tauri::AppHandle,
+    game_install: GameInstall,
+    operation: PendingModOperation,
+) {
+    let pending_mod_operations = app.state::<PendingModOperations>();
+    let mut pending = pending_mod_operations.0.lock().unwrap();
+
+    // A newer toggle of the same mod replaces the older one
+    if let PendingModOperation::SetEnabled { mod_name, .. } = &operation {
+        pending.retain(|(pending_install, pending_operation)| {
+            let toggles_same_mod = match pending_operation {
+                PendingModOperation::SetEnabled {
+                    mod_name: pending_mod_name,
+                    ..
+                } => pending_mod_name == mod_name,
+                PendingModOperation::Install { .. } => false,
+            };
+            !(is_same_profile(pending_install, &game_install) && toggles_same_mod)
+        });
+    }
+
+    let is_queued = pending.iter().any(|(pending_install, pending_operation)| {
+        is_same_profile(pending_install, &game_install) && pending_operation == &operation
+    });
+    if !is_queued {
+        pending.push((game_install, operation));
+    }
+}
+
+/// Returns the mod changes queued for the given install and profile
+#[tauri::command]
+pub fn get_pending_mod_operations(
+    app: tauri::AppHandle,
+    game_install: GameInstall,
+) -> Vec<PendingModOperation> {
+    let pending_mod_operations = app.state::<PendingModOperations>();
+    let pending = pending_mod_operations.0.lock().unwrap();
+    pending
+        .iter()
+        .filter(|(pending_install, _)| is_same_profile(pending_install, &game_install))
+        .map(|(_, operation)| operation.clone())
+        .collect()
+}
+
+/// Drops all mod changes queued for the given install and profile
+#[tauri::command]
+pub fn clear_pending_mod_operations(app: tauri::AppHandle, game_install: GameInstall) {
+    let pending_mod_operations = app.state::<PendingModOperations>();
+    let mut pending = pending_mod_operations.0.lock().unwrap();
+    pending.retain(|(pending_install, _)| !is_same_profile(pending_install, &game_install));
+}
+
+/// Removes a queued mod change once it has been applied
+fn remove_pending_mod_operation(
+    app: &tauri::AppHandle,
+    game_install: &GameInstall,
+    operation: &PendingModOperation,
+) {
+    let pending_mod_operations = app.state::<PendingModOperations>();
+    let mut pending = pending_mod_operations.0.lock().unwrap();
+    pending.retain(|(pending_install, pending_operation)| {
+        !(is_same_profile(pending_install, game_install) && pending_operation == operation)
+    });
+}
+
+/// Applies the queued mod changes of the given install and profile
+/// Applied changes are removed from the queue, failed ones stay queued
+/// Returns the errors of the failed changes
+async fn apply_pending_mod_operations(
+    app: &tauri::AppHandle,
+    game_install: &GameInstall,
+) -> Vec<String> {
+    let operations = get_pending_mod_operations(app.clone(), game_install.clone());
+
+    let mut failed_operations: Vec<String> = vec![];
+    for operation in operations {
+        log::info!("Applying pending {operation:?}");
+        let result = match &operation {
+            PendingModOperation::SetEnabled {
+                mod_name,
+                mod_version,
+                is_enabled,
+            } => set_mod_enabled_status(
+                game_install.clone(),
+                mod_name.clone(),
+                mod_version.clone(),
+                *is_enabled,
+            ),
+            PendingModOperation::Install {
+                thunderstore_mod_string,
+            } => fc_download_mod_and_install(game_install, thunderstore_mod_string).await,
+        };
+        match result {
+            Ok(()) => remove_pending_mod_operation(app, game_install, &operation),
+            Err(err) => {
+                log::warn!("Failed applying {operation:?}: {err}");
+                failed_operations.push(err);
+            }
+        }
+    }
+
+    if let Err(err) = clean_up_download_folder(game_install, false) {
+        log::info!("Failed to delete download folder due to {}", err);
+    }
+
+    failed_operations
+}
+
+/// Stops Northstar by first asking it to close and killing it if it did not exit within the timeout
+pub async fn stop_northstar_gracefully(
+    app: &tauri::AppHandle,
+    timeout: Duration,
+) -> Result<(), String> {
+    let process_monitor = app.state::<ProcessMonitor>();
+
+    if process_monitor.terminate_northstar() == 0 {
+        return Err("Northstar is not running".to_string());
+    }
+
+    let started_waiting = Instant::now();
+    while started_waiting.elapsed() < timeout {
+        sleep(STOP_POLL_INTERVAL).await;
+        if !process_monitor.refresh().is_northstar_running() {
+            log::info!("Northstar exited after {:?}", started_waiting.elapsed());
+            return Ok(());
+        }
+    }
+
+    log::warn!("Northstar did not exit within {timeout:?}, killing it");
+    process_monitor.kill_northstar();
+
+    // Also stop the Wine/Proton process the game was launched in
+    let launched_northstar = app.state::<LaunchedNorthstar>();
+    if let Some(mut child) = launched_northstar.0.lock().unwrap().child.take() {
+        if let Ok(None) = child.try_wait() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+
+    Ok(())
+}
+
+/// Asks Northstar to close, kills it if it is still running after `timeout_seconds`
+#[tauri::command]
+pub async fn stop_northstar(
+    app: tauri::AppHandle,
+    timeout_seconds: Option<u64>,
+) -> Result<(), String> {
+    let timeout = timeout_seconds
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_STOP_TIMEOUT);
+    stop_northstar_gracefully(&app, timeout).await
+}
+
+/// Stops the running game, applies queued mod changes and launches the same profile again
+/// using the same launch options as before
+#[tauri::command]
+pub async fn relaunch_northstar(
+    app: tauri::AppHandle,
+    timeout_seconds: Option<u64>,
+) -> Result<RelaunchResult, String> {
+    let last_launch = app
+        .state::<LaunchedNorthstar>()
+        .0
+        .lock()
+        .unwrap()
+        .launch
+        .clone();
+    let Some((game_install, launch_options)) = last_launch else {
+        return Err("Northstar was not launched by FlightCore".to_string());
+    };
+
+    let timeout = timeout_seconds
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_STOP_TIMEOUT);
+    if let Err(err) = stop_northstar_gracefully(&app, timeout).await {
+        // Not running is fine, we want to start it anyway
+        log::info!("{err}");
+    }
+
+    // The game is started again even if some changes failed, those stay queued
+    let failed_mod_operations = apply_pending_mod_operations(&app, &game_install).await;
+
+    match launch_northstar(app, game_install, launch_options) {
+        Ok(message) => Ok(RelaunchResult {
+            message,
+            failed_mod_operations,
+        }),
+        Err(err) if failed_mod_operations.is_empty() => Err(err),
+        Err(err) => Err(format!(
+            "{err}\nFailed applying mod changes: {}",
+            failed_mod_operations.join(", ")
+        )),
+    }
+}
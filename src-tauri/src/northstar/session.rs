@@ -0,0 +1,346 @@
+//! Tracks game sessions, i.e. the time between Northstar starting and exiting
+//!
+//! Finished sessions are persisted so that playtime can be shown per profile.
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+use ts_rs::TS;
+
+use crate::northstar::LaunchedNorthstar;
+use crate::process_monitor::ProcessSnapshot;
+use crate::server_browser::favorites::record_recent_servers;
+use crate::util::{get_stored_value, set_stored_value};
+use crate::GameInstall;
+
+/// Key under which finished sessions are persisted
+const GAME_SESSION_HISTORY_KEY: &str = "game-session-history";
+
+/// Oldest sessions are dropped once the history grows beyond this
+const MAX_GAME_SESSION_HISTORY: usize = 1000;
+
+/// Profile reported for sessions not started by FlightCore and without `-profile=` argument
+const UNKNOWN_PROFILE: &str = "unknown";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+#[ts(export)]
+pub enum GameSessionExitStatus {
+    Running,
+    Exited,
+    Crashed,
+}
+
+/// A single run of Northstar
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct GameSession {
+    pub profile: String,
+    pub game_path: Option<String>,
+    /// RFC 3339 timestamps
+    pub started_at: String,
+    pub ended_at: Option<String>,
+    pub duration_seconds: u64,
+    pub exit_code: Option<i32>,
+    pub exit_status: GameSessionExitStatus,
+}
+
+/// Accumulated playtime of a profile
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct ProfilePlaytime {
+    pub profile: String,
+    pub game_path: Option<String>,
+    pub total_playtime_seconds: u64,
+    pub session_count: usize,
+    pub last_played: String,
+}
+
+/// Keeps track of the currently running session
+#[derive(Default)]
+pub struct GameSessionTracker(Mutex<Option<GameSession>>);
+
+/// Checks whether the profile wrote a crash dump while the session was running
+fn has_crash_dump_since(
+    game_session: &GameSession,
+    started_at: chrono::DateTime<chrono::Utc>,
+) -> bool {
+    let Some(game_path) = &game_session.game_path else {
+        return false;
+    };
+
+    // Paths may contain glob syntax such as `Games [SSD]`
+    let pattern = format!(
+        "{}/logs/*.dmp",
+        glob::Pattern::escape(&format!("{}/{}", game_path, game_session.profile))
+    );
+    let Ok(dump_paths) = glob::glob(&pattern) else {
+        return false;
+    };
+    dump_paths
+        .flatten()
+        .filter_map(|path| path.metadata().ok()?.modified().ok())
+        .any(|modified| chrono::DateTime::<chrono::Utc>::from(modified) >= started_at)
+}
+
+/// Starts a new session for a freshly detected Northstar process
+fn start_session(app: &tauri::AppHandle, process_snapshot: &ProcessSnapshot) -> GameSession {
+    let last_launch = take_awaited_launch(app);
+    let profile = process_snapshot
+        .northstar_profile
+        .clone()
+        .or_else(|| last_launch.as_ref().map(|launch| launch.profile.clone()))
+        .unwrap_or(UNKNOWN_PROFILE.to_string());
+
+    GameSession {
+        profile,
+        game_path: last_launch.map(|launch| launch.game_path),
+        started_at: chrono::Utc::now().to_rfc3339(),
+        ended_at: None,
+        duration_seconds: 0,
+        exit_code: None,
+        exit_status: GameSessionExitStatus::Running,
+    }
+}
+
+/// Install FlightCore launched if the session is the first one since that launch
+fn take_awaited_launch(app: &tauri::AppHandle) -> Option<GameInstall> {
+    let launched_northstar = app.state::<LaunchedNorthstar>();
+    let mut launched = launched_northstar.0.lock().unwrap();
+    if !std::mem::take(&mut launched.awaiting_session) {
+        return None;
+    }
+    launched
+        .launch
+        .as_ref()
+        .map(|(game_install, _)| game_install.clone())
+}
+
+/// Finishes the session, using the exit code if FlightCore spawned the process itself
+fn end_session(mut game_session: GameSession, exit_code: Option<i32>) -> GameSession {
+    let now = chrono::Utc::now();
+    let started_at = chrono::DateTime::parse_from_rfc3339(&game_session.started_at)
+        .map(|started_at| started_at.with_timezone(&chrono::Utc))
+        .unwrap_or(now);
+
+    game_session.ended_at = Some(now.to_rfc3339());
+    game_session.duration_seconds = (now - started_at).num_seconds().max(0) as u64;
+    game_session.exit_code = exit_code;
+    game_session.exit_status = match exit_code {
+        Some(code) if code != 0 => GameSessionExitStatus::Crashed,
+        _ if has_crash_dump_since(&game_session, started_at) => GameSessionExitStatus::Crashed,
+        _ => GameSessionExitStatus::Exited,
+    };
+    game_session
+}
+
+/// Exit code of the process FlightCore spawned, if it spawned one and it has exited
+fn take_launched_exit_code(app: &tauri::AppHandle) -> Option<i32> {
+    let launched_northstar = app.state::<LaunchedNorthstar>();
+    let mut launched = launched_northstar.0.lock().unwrap();
+    let exit_status = launched.child.as_mut()?.try_wait().ok()??;
+    launched.child = None;
+    exit_status.code()
+}
+
+/// Appends a finished session to the persisted history
+fn store_session(app: &tauri::AppHandle, game_session: &GameSession) -> Result<(), String> {
+    let mut history: Vec<GameSession> = get_stored_value(app, GAME_SESSION_HISTORY_KEY)
+        .map_err(|err| format!("Failed reading session history: {err}"))?
+        .unwrap_or_default();
+
+    history.push(game_session.clone());
+    if history.len() > MAX_GAME_SESSION_HISTORY {
+        history.drain(..history.len() - MAX_GAME_SESSION_HISTORY);
+    }
+
+    set_stored_value(app, GAME_SESSION_HISTORY_KEY, &history)
+        .map_err(|err| format!("Failed saving session history: {err}"))
+}
+
+/// Detects Northstar starting or exiting since the previous snapshot
+/// Emits `northstar-session-started`/`northstar-session-ended`
+pub fn update_game_session(app: &tauri::AppHandle, process_snapshot: &ProcessSnapshot) {
+    let game_session_tracker = app.state::<GameSessionTracker>();
+
+    // Only the state change happens under the lock, the ended session is stored and checked for
+    // crash dumps after releasing it
+    let ended_session = {
+        let mut active_session = game_session_tracker.0.lock().unwrap();
+        match (
+            active_session.is_some(),
+            process_snapshot.is_northstar_running(),
+        ) {
+            (false, true) => {
+                let game_session = start_session(app, process_snapshot);
+                *active_session = Some(game_session.clone());
+                drop(active_session);
+                log::info!("Northstar started with profile {}", game_session.profile);
+                if let Err(err) = app.emit("northstar-session-started", &game_session) {
+                    log::warn!("Failed emitting session event: {err}");
+                }
+                None
+            }
+            (true, false) => active_session.take(),
+            _ => None,
+        }
+    };
+
+    if let Some(game_session) = ended_session {
+        let game_session = end_session(game_session, take_launched_exit_code(app));
+        log::info!(
+            "Northstar exited after {}s ({:?})",
+            game_session.duration_seconds,
+            game_session.exit_status
+        );
+        if let Err(err) = store_session(app, &game_session) {
+            log::warn!("{err}");
+        }
+        if let Err(err) = record_recent_servers(app, &game_session) {
+            log::warn!("Failed recording recent servers: {err}");
+        }
+        if let Err(err) = app.emit("northstar-session-ended", &game_session) {
+            log::warn!("Failed emitting session event: {err}");
+        }
+    }
+}
+
+/// Sums up playtime per profile
+fn summarize_playtime(history: &[GameSession]) -> Vec<ProfilePlaytime> {
+    let mut playtimes: Vec<ProfilePlaytime> = vec![];
+
+    for game_session in history {
+        let ended_at = game_session.ended_at.clone().unwrap_or_default();
+        match playtimes.iter_mut().find(|playtime| {
+            playtime.profile == game_session.profile && playtime.game_path == game_session.game_path
+        }) {
+            Some(playtime) => {
+                playtime.total_playtime_seconds += game_session.duration_seconds;
+                playtime.session_count += 1;
+                if ended_at > playtime.last_played {
+                    playtime.last_played = ended_at;
+                }
+            }
+            None => playtimes.push(ProfilePlaytime {
+                profile: game_session.profile.clone(),
+                game_path: game_session.game_path.clone(),
+                total_playtime_seconds: game_session.duration_seconds,
+                session_count: 1,
+                last_played: ended_at,
+            }),
+        }
+    }
+
+    playtimes
+}
+
+/// Whether a session is running or expected to start after a launch by FlightCore
+/// The process list only has to be watched for sessions while this is the case
+pub fn is_session_pending(app: &tauri::AppHandle) -> bool {
+    let game_session_tracker = app.state::<GameSessionTracker>();
+    if game_session_tracker.0.lock().unwrap().is_some() {
+        return true;
+    }
+    let launched_northstar = app.state::<LaunchedNorthstar>();
+    let awaiting_session = launched_northstar.0.lock().unwrap().awaiting_session;
+    awaiting_session
+}
+
+/// Returns the currently running session if any
+#[tauri::command]
+pub fn get_active_game_session(app: tauri::AppHandle) -> Option<GameSession> {
+    let game_session_tracker = app.state::<GameSessionTracker>();
+    let active_session = game_session_tracker.0.lock().unwrap();
+    active_session.clone()
+}
+
+/// Returns finished sessions, optionally only those of the given profile
+#[tauri::command]
+pub fn get_game_session_history(
+    app: tauri::AppHandle,
+    profile: Option<String>,
+) -> Result<Vec<GameSession>, String> {
+    let history: Vec<GameSession> = get_stored_value(&app, GAME_SESSION_HISTORY_KEY)
+        .map_err(|err| format!("Failed reading session history: {err}"))?
+        .unwrap_or_default();
+
+    Ok(history
+        .into_iter()
+        .filter(|game_session| {
+            profile
+                .as_ref()
+                .is_none_or(|profile| &game_session.profile == profile)
+        })
+        .collect())
+}
+
+/// Returns the accumulated playtime of every profile that was played
+#[tauri::command]
+pub fn get_profile_playtime(app: tauri::AppHandle) -> Result<Vec<ProfilePlaytime>, String> {
+    let history = get_game_session_history(app, None)?;
+    Ok(summarize_playtime(&history))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finished_session(profile: &str, ended_at: &str, duration_seconds: u64) -> GameSession {
+        GameSession {
+            profile: profile.to_string(),
+            game_path: Some("/games/Titanfall2".to_string()),
+            started_at: ended_at.to_string(),
+            ended_at: Some(ended_at.to_string()),
+            duration_seconds,
+            exit_code: None,
+            exit_status: GameSessionExitStatus::Exited,
+        }
+    }
+
+    #[test]
+    fn test_summarize_playtime() {
+        let history = vec![
+            finished_session("R2Northstar", "2024-01-01T10:00:00+00:00", 600),
+            finished_session("R2Coop", "2024-01-02T10:00:00+00:00", 60),
+            finished_session("R2Northstar", "2024-01-03T10:00:00+00:00", 120),
+        ];
+
+        let playtimes = summarize_playtime(&history);
+
+        assert_eq!(playtimes.len(), 2);
+        assert_eq!(playtimes[0].profile, "R2Northstar");
+        assert_eq!(playtimes[0].total_playtime_seconds, 720);
+        assert_eq!(playtimes[0].session_count, 2);
+        assert_eq!(playtimes[0].last_played, "2024-01-03T10:00:00+00:00");
+    }
+
+    #[test]
+    fn test_end_session_with_exit_code() {
+        let mut game_session = finished_session("R2Northstar", "2024-01-01T10:00:00+00:00", 0);
+        game_session.game_path = None;
+
+        let ended = end_session(game_session.clone(), Some(0));
+        assert_eq!(ended.exit_status, GameSessionExitStatus::Exited);
+        assert!(ended.duration_seconds > 0);
+
+        let ended = end_session(game_session, Some(-1));
+        assert_eq!(ended.exit_status, GameSessionExitStatus::Crashed);
+    }
+
+    #[test]
+    fn test_has_crash_dump_since_with_glob_characters() {
+        let games_dir = tempfile::tempdir().unwrap();
+        let started_at = chrono::Utc::now() - chrono::TimeDelta::minutes(1);
+
+        for game_dir in ["Games [SSD]", "Games [SSD"] {
+            let game_path = games_dir.path().join(game_dir);
+            let mut game_session = finished_session("R2Northstar", "2024-01-01T10:00:00+00:00", 0);
+            game_session.game_path = Some(game_path.display().to_string());
+
+            let logs_path = game_path.join("R2Northstar/logs");
+            std::fs::create_dir_all(&logs_path).unwrap();
+            assert!(!has_crash_dump_since(&game_session, started_at));
+
+            std::fs::write(logs_path.join("crash.dmp"), "").unwrap();
+            assert!(has_crash_dump_since(&game_session, started_at));
+        }
+    }
+}
@@ -1,4 +1,17 @@
 // Linux specific code
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::constants::{APP_USER_AGENT, NORTHSTAR_DEFAULT_PROFILE};
+use crate::download::{download_file, ExpectedFile};
+use crate::northstar::profile::ProfileLaunchOptions;
+use crate::platform_specific::{LinuxRunner, LinuxRunnerKind};
+use crate::{GameInstall, InstallType};
+
+/// GitHub API endpoint of the NorthstarProton releases
+const NS_PROTON_RELEASES_API_URL: &str =
+    "https://api.github.com/repos/R2NorthstarTools/NorthstarProton/releases";
 
 fn get_proton_dir() -> Result<String, String> {
     let steam_dir = match steamlocate::SteamDir::locate() {
@@ -10,27 +23,69 @@ fn get_proton_dir() -> Result<String, String> {
     Ok(compat_dir)
 }
 
+/// NorthstarProton release on GitHub
+#[derive(Deserialize)]
+struct ProtonRelease {
+    assets: Vec<ProtonReleaseAsset>,
+}
+
+#[derive(Deserialize)]
+struct ProtonReleaseAsset {
+    name: String,
+    browser_download_url: String,
+    size: u64,
+    /// `sha256:<hex>`, only present for assets uploaded after GitHub started hashing them
+    digest: Option<String>,
+}
+
+/// Looks up download URL, size and checksum of the NorthstarProton archive of the given release
+async fn get_ns_proton_asset(tag: &str) -> Result<(String, ExpectedFile), String> {
+    let asset_name = format!("NorthstarProton{}.tar.gz", tag.trim_matches('v'));
+    let release: ProtonRelease = reqwest::Client::new()
+        .get(format!("{NS_PROTON_RELEASES_API_URL}/tags/{tag}"))
+        .header(reqwest::header::USER_AGENT, APP_USER_AGENT)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| format!("Failed to fetch NorthstarProton release {tag}: {err}"))?
+        .json()
+        .await
+        .map_err(|err| format!("Failed to parse NorthstarProton release {tag}: {err}"))?;
+
+    let asset = release
+        .assets
+        .into_iter()
+        .find(|asset| asset.name == asset_name)
+        .ok_or(format!("NorthstarProton release {tag} has no {asset_name}"))?;
+    let expected = ExpectedFile {
+        size: Some(asset.size),
+        sha256: asset
+            .digest
+            .and_then(|digest| digest.strip_prefix("sha256:").map(str::to_string)),
+    };
+    Ok((asset.browser_download_url, expected))
+}
+
 /// Downloads and installs NS proton
 /// Assumes Steam install
-pub fn install_ns_proton() -> Result<(), String> {
+pub async fn install_ns_proton() -> Result<(), String> {
     // Get latest NorthstarProton release
     let latest = match thermite::core::latest_release() {
         Ok(result) => result,
         Err(_) => return Err("Failed to fetch latest NorthstarProton release".to_string()),
     };
+    let (download_url, expected) = get_ns_proton_asset(&latest).await?;
 
     let temp_dir = std::env::temp_dir();
     let path = format!("{}/nsproton-{}.tar.gz", temp_dir.display(), latest);
-    let archive = match std::fs::File::create(path.clone()) {
-        Ok(result) => result,
-        Err(_) => return Err("Failed to allocate NorthstarProton archive on disk".to_string()),
-    };
 
     // Download the latest Proton release
     log::info!("Downloading NorthstarProton to {}", path);
-    match thermite::core::download_ns_proton(latest, archive) {
-        Ok(_) => {}
-        Err(_) => return Err("Failed to download NorthstarProton".to_string()),
+    if let Err(err) =
+        download_file(&download_url, Path::new(&path), &expected, |_, _| {}, None).await
+    {
+        log::error!("{err:#}");
+        return Err("Failed to download NorthstarProton".to_string());
     }
 
     log::info!("Finished Download");
@@ -96,3 +151,825 @@ pub fn get_local_ns_proton_version() -> Result<String, String> {
 
     Err("Northstar Proton is not installed".to_string())
 }
+
+/// Steam install locations relative to the home directory
+/// Covers native Steam (including Steam Deck) as well as Flatpak Steam
+const STEAM_ROOTS: [&str; 4] = [
+    ".local/share/Steam",
+    ".steam/steam",
+    ".var/app/com.valvesoftware.Steam/.local/share/Steam",
+    ".var/app/com.valvesoftware.Steam/.steam/steam",
+];
+
+/// Mount points of removable media such as Steam Deck SD cards
+const REMOVABLE_MEDIA_ROOT: &str = "/run/media";
+
+/// Heroic config locations relative to the home directory (native and Flatpak)
+const HEROIC_CONFIG_DIRS: [&str; 2] = [
+    ".config/heroic",
+    ".var/app/com.heroicgameslauncher.hgl/config/heroic",
+];
+
+/// Directory Heroic creates wine prefixes in by default, relative to the home directory
+const HEROIC_DEFAULT_PREFIXES_DIR: &str = "Games/Heroic/Prefixes";
+
+/// Lutris game config locations relative to the home directory (native and Flatpak)
+const LUTRIS_GAME_CONFIG_DIRS: [&str; 4] = [
+    ".config/lutris/games",
+    ".local/share/lutris/games",
+    ".var/app/net.lutris.Lutris/config/lutris/games",
+    ".var/app/net.lutris.Lutris/data/lutris/games",
+];
+
+/// Directory Lutris installs games into by default, relative to the home directory
+const LUTRIS_DEFAULT_GAMES_DIR: &str = "Games";
+
+/// Locations the EA App and Origin install Titanfall2 to inside of a wine prefix
+const WINE_PREFIX_GAME_PATHS: [(&str, InstallType); 4] = [
+    (
+        "drive_c/Program Files/EA Games/Titanfall2",
+        InstallType::EAPLAY,
+    ),
+    (
+        "drive_c/Program Files (x86)/EA Games/Titanfall2",
+        InstallType::EAPLAY,
+    ),
+    (
+        "drive_c/Program Files/Origin Games/Titanfall2",
+        InstallType::ORIGIN,
+    ),
+    (
+        "drive_c/Program Files (x86)/Origin Games/Titanfall2",
+        InstallType::ORIGIN,
+    ),
+];
+
+/// Finds Titanfall2 installs in Linux specific layouts:
+/// - Steam (native, Flatpak, Steam Deck SD cards)
+/// - EA App / Origin running in Heroic or Lutris wine prefixes
+pub fn discover_game_installs() -> Vec<GameInstall> {
+    let home_dir = match dirs::home_dir() {
+        Some(home_dir) => home_dir,
+        None => {
+            log::warn!("Couldn't determine home directory");
+            return vec![];
+        }
+    };
+
+    discover_game_installs_in(&home_dir, Path::new(REMOVABLE_MEDIA_ROOT))
+}
+
+/// Same as `discover_game_installs` but relative to the given home directory and media root
+fn discover_game_installs_in(home_dir: &Path, media_root: &Path) -> Vec<GameInstall> {
+    let mut game_installs: Vec<GameInstall> = vec![];
+
+    game_installs.extend(discover_steam_installs(home_dir, media_root));
+
+    let mut wine_prefixes = find_heroic_wine_prefixes(home_dir);
+    wine_prefixes.extend(find_lutris_wine_prefixes(home_dir));
+    for wine_prefix in wine_prefixes {
+        if let Some(game_install) = find_game_in_wine_prefix(&wine_prefix) {
+            game_installs.push(game_install);
+        }
+    }
+
+    game_installs
+}
+
+/// Builds a Steam game install entry for the given library
+fn steam_game_install(library: &steamlocate::Library) -> Option<GameInstall> {
+    let app = match library.app(thermite::TITANFALL2_STEAM_ID)? {
+        Ok(app) => app,
+        Err(err) => {
+            log::info!("Failed parsing Titanfall2 app manifest: {err}");
+            return None;
+        }
+    };
+
+    // Proton keeps a separate prefix per game inside the library it is installed in
+    let wine_prefix = library
+        .path()
+        .join("steamapps/compatdata")
+        .join(thermite::TITANFALL2_STEAM_ID.to_string())
+        .join("pfx");
+
+    Some(GameInstall {
+        game_path: library.resolve_app_dir(&app).display().to_string(),
+        profile: NORTHSTAR_DEFAULT_PROFILE.to_string(),
+        install_type: InstallType::STEAM,
+        wine_prefix: wine_prefix
+            .is_dir()
+            .then(|| wine_prefix.display().to_string()),
+    })
+}
+
+/// Finds Titanfall2 in native and Flatpak Steam libraries as well as libraries on removable media
+fn discover_steam_installs(home_dir: &Path, media_root: &Path) -> Vec<GameInstall> {
+    let mut game_installs: Vec<GameInstall> = vec![];
+
+    for steam_root in STEAM_ROOTS {
+        let steam_path = home_dir.join(steam_root);
+        if !steam_path.join("steamapps/libraryfolders.vdf").is_file() {
+            continue;
+        }
+
+        let libraries = match steamlocate::SteamDir::from_dir(&steam_path)
+            .and_then(|steamdir| steamdir.libraries())
+        {
+            Ok(libraries) => libraries,
+            Err(err) => {
+                log::info!(
+                    "Couldn't read Steam libraries in {}: {err}",
+                    steam_path.display()
+                );
+                continue;
+            }
+        };
+
+        game_installs.extend(
+            libraries
+                .filter_map(Result::ok)
+                .filter_map(|library| steam_game_install(&library)),
+        );
+    }
+
+    // Steam Deck mounts SD cards as `/run/media/mmcblk0p1` or `/run/media/<user>/<label>`
+    for library_path in find_steam_libraries_on_media(media_root) {
+        match steamlocate::Library::from_dir(&library_path) {
+            Ok(library) => game_installs.extend(steam_game_install(&library)),
+            Err(err) => log::info!(
+                "Couldn't read Steam library {}: {err}",
+                library_path.display()
+            ),
+        }
+    }
+
+    game_installs
+}
+
+/// Lists directories up to two levels below the media root that contain a Steam library
+fn find_steam_libraries_on_media(media_root: &Path) -> Vec<PathBuf> {
+    let mut libraries: Vec<PathBuf> = vec![];
+
+    for entry in list_directories(media_root) {
+        if entry.join("steamapps").is_dir() {
+            libraries.push(entry);
+            continue;
+        }
+        for nested_entry in list_directories(&entry) {
+            if nested_entry.join("steamapps").is_dir() {
+                libraries.push(nested_entry);
+            }
+        }
+    }
+
+    libraries
+}
+
+/// Lists all directories directly inside the given path, empty if it cannot be read
+fn list_directories(path: &Path) -> Vec<PathBuf> {
+    match std::fs::read_dir(path) {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+/// Collects wine prefixes of games set up in Heroic
+fn find_heroic_wine_prefixes(home_dir: &Path) -> Vec<PathBuf> {
+    let mut wine_prefixes: Vec<PathBuf> = vec![];
+
+    // Every game has its own config containing the prefix it uses
+    for heroic_dir in HEROIC_CONFIG_DIRS {
+        let pattern = format!(
+            "{}/GamesConfig/*.json",
+            glob::Pattern::escape(&home_dir.join(heroic_dir).display().to_string())
+        );
+        let Ok(game_configs) = glob::glob(&pattern) else {
+            continue;
+        };
+        for game_config in game_configs.flatten() {
+            let Ok(data) = std::fs::read_to_string(&game_config) else {
+                continue;
+            };
+            let parsed: serde_json::Value = match serde_json::from_str(&data) {
+                Ok(parsed) => parsed,
+                Err(err) => {
+                    log::warn!("Failed parsing {}: {err}", game_config.display());
+                    continue;
+                }
+            };
+            let Some(games) = parsed.as_object() else {
+                continue;
+            };
+            for game in games.values() {
+                if let Some(wine_prefix) = game.get("winePrefix").and_then(|value| value.as_str()) {
+                    wine_prefixes.push(PathBuf::from(wine_prefix));
+                }
+            }
+        }
+    }
+
+    wine_prefixes.extend(list_directories(
+        &home_dir.join(HEROIC_DEFAULT_PREFIXES_DIR),
+    ));
+
+    wine_prefixes
+}
+
+/// Collects wine prefixes of games set up in Lutris
+fn find_lutris_wine_prefixes(home_dir: &Path) -> Vec<PathBuf> {
+    let mut wine_prefixes: Vec<PathBuf> = vec![];
+
+    // Lutris stores the prefix under `game: prefix:` in the per-game YAML config
+    for config_dir in LUTRIS_GAME_CONFIG_DIRS {
+        let pattern = format!(
+            "{}/*.yml",
+            glob::Pattern::escape(&home_dir.join(config_dir).display().to_string())
+        );
+        let Ok(game_configs) = glob::glob(&pattern) else {
+            continue;
+        };
+        for game_config in game_configs.flatten() {
+            let Ok(data) = std::fs::read_to_string(&game_config) else {
+                continue;
+            };
+            let parsed: serde_yaml_ng::Value = match serde_yaml_ng::from_str(&data) {
+                Ok(parsed) => parsed,
+                Err(err) => {
+                    log::warn!("Failed parsing {}: {err}", game_config.display());
+                    continue;
+                }
+            };
+            if let Some(wine_prefix) = parsed
+                .get("game")
+                .and_then(|game| game.get("prefix"))
+                .and_then(|prefix| prefix.as_str())
+            {
+                wine_prefixes.push(PathBuf::from(wine_prefix));
+            }
+        }
+    }
+
+    wine_prefixes.extend(list_directories(&home_dir.join(LUTRIS_DEFAULT_GAMES_DIR)));
+
+    wine_prefixes
+}
+
+/// Checks whether the EA App or Origin installed Titanfall2 into the given wine prefix
+fn find_game_in_wine_prefix(wine_prefix: &Path) -> Option<GameInstall> {
+    WINE_PREFIX_GAME_PATHS
+        .iter()
+        .map(|(game_path, install_type)| (wine_prefix.join(game_path), install_type))
+        .find(|(game_path, _)| game_path.join("Titanfall2.exe").is_file())
+        .map(|(game_path, install_type)| GameInstall {
+            game_path: game_path.display().to_string(),
+            profile: NORTHSTAR_DEFAULT_PROFILE.to_string(),
+            install_type: install_type.clone(),
+            wine_prefix: Some(wine_prefix.display().to_string()),
+        })
+}
+
+/// Locations Heroic keeps its own Wine and Proton downloads in, relative to the Heroic config dir
+const HEROIC_TOOLS_DIRS: [&str; 2] = ["tools/proton", "tools/wine"];
+
+/// Wine builds downloaded by Lutris, relative to the home directory (native and Flatpak)
+const LUTRIS_WINE_RUNNER_DIRS: [&str; 2] = [
+    ".local/share/lutris/runners/wine",
+    ".var/app/net.lutris.Lutris/data/lutris/runners/wine",
+];
+
+/// DLLs Northstar needs to load instead of the Wine builtins
+const NORTHSTAR_WINE_DLL_OVERRIDES: &str = "wsock32=n,b";
+
+/// Lists Wine and Proton builds found on this computer
+/// Covers Steam (official Proton and `compatibilitytools.d`, incl. NorthstarProton),
+/// Heroic, Lutris and the system Wine
+pub fn find_runners() -> Vec<LinuxRunner> {
+    let mut runners = match dirs::home_dir() {
+        Some(home_dir) => find_runners_in(&home_dir),
+        None => {
+            log::warn!("Couldn't determine home directory");
+            vec![]
+        }
+    };
+
+    if let Some(system_wine) = find_system_wine() {
+        runners.push(LinuxRunner {
+            kind: LinuxRunnerKind::Wine,
+            name: "System Wine".to_string(),
+            path: system_wine.display().to_string(),
+        });
+    }
+
+    runners
+}
+
+/// Same as `find_runners` but relative to the given home directory and without the system Wine
+fn find_runners_in(home_dir: &Path) -> Vec<LinuxRunner> {
+    let mut runners: Vec<LinuxRunner> = vec![];
+
+    for steam_root in STEAM_ROOTS {
+        let steam_path = home_dir.join(steam_root);
+        for tools_dir in ["compatibilitytools.d", "steamapps/common"] {
+            runners.extend(find_runners_of_kind(
+                &steam_path.join(tools_dir),
+                LinuxRunnerKind::Proton,
+            ));
+        }
+    }
+
+    for heroic_dir in HEROIC_CONFIG_DIRS {
+        for tools_dir in HEROIC_TOOLS_DIRS {
+            let tools_path = home_dir.join(heroic_dir).join(tools_dir);
+            runners.extend(find_runners_of_kind(&tools_path, LinuxRunnerKind::Proton));
+            runners.extend(find_runners_of_kind(&tools_path, LinuxRunnerKind::Wine));
+        }
+    }
+
+    for runner_dir in LUTRIS_WINE_RUNNER_DIRS {
+        runners.extend(find_runners_of_kind(
+            &home_dir.join(runner_dir),
+            LinuxRunnerKind::Wine,
+        ));
+    }
+
+    // `~/.steam/steam` is usually a symlink to `~/.local/share/Steam`
+    let mut unique_runners: Vec<LinuxRunner> = vec![];
+    for runner in runners {
+        let canonical_path =
+            std::fs::canonicalize(&runner.path).unwrap_or_else(|_| PathBuf::from(&runner.path));
+        if unique_runners.iter().any(|known| {
+            std::fs::canonicalize(&known.path).unwrap_or_else(|_| PathBuf::from(&known.path))
+                == canonical_path
+        }) {
+            continue;
+        }
+        unique_runners.push(runner);
+    }
+
+    unique_runners
+}
+
+/// Lists all builds of the given kind directly inside the given directory
+fn find_runners_of_kind(tools_dir: &Path, kind: LinuxRunnerKind) -> Vec<LinuxRunner> {
+    let executable = match kind {
+        LinuxRunnerKind::Proton => "proton",
+        LinuxRunnerKind::Wine => "bin/wine",
+    };
+
+    list_directories(tools_dir)
+        .into_iter()
+        .filter(|runner_dir| runner_dir.join(executable).is_file())
+        .filter_map(|runner_dir| {
+            Some(LinuxRunner {
+                kind: kind.clone(),
+                name: runner_dir.file_name()?.to_string_lossy().to_string(),
+                path: runner_dir.join(executable).display().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Looks up `wine` in `PATH`
+fn find_system_wine() -> Option<PathBuf> {
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join("wine"))
+        .find(|wine| wine.is_file())
+}
+
+/// Picks the build to launch with, falling back to NorthstarProton if none was selected
+pub fn resolve_runner(selected_runner: Option<LinuxRunner>) -> Result<LinuxRunner, String> {
+    if let Some(runner) = selected_runner {
+        if !Path::new(&runner.path).is_file() {
+            return Err(format!(
+                "Selected Wine/Proton build \"{}\" no longer exists at {}",
+                runner.name, runner.path
+            ));
+        }
+        return Ok(runner);
+    }
+
+    find_runners()
+        .into_iter()
+        .find(|runner| {
+            runner.kind == LinuxRunnerKind::Proton && runner.name.starts_with("NorthstarProton")
+        })
+        .ok_or_else(|| {
+            "No Wine/Proton build selected and NorthstarProton is not installed".to_string()
+        })
+}
+
+/// Gets the Proton compat data path of a Steam prefix (`compatdata/<app id>/pfx`)
+/// Returns `None` for prefixes not managed by Steam
+fn get_steam_compat_data_path(wine_prefix: &Path) -> Option<&Path> {
+    if wine_prefix.file_name()? != "pfx" {
+        return None;
+    }
+    let compat_data_path = wine_prefix.parent()?;
+    if compat_data_path.parent()?.file_name()? != "compatdata" {
+        return None;
+    }
+    Some(compat_data_path)
+}
+
+/// Finds the Wine binary shipped inside of a Proton build
+/// Newer builds keep it in `files`, older ones in `dist`
+fn get_proton_wine_path(proton_path: &Path) -> Option<PathBuf> {
+    let proton_dir = proton_path.parent()?;
+    ["files/bin/wine", "dist/bin/wine"]
+        .into_iter()
+        .map(|wine| proton_dir.join(wine))
+        .find(|wine| wine.is_file())
+}
+
+/// Builds the command running `NorthstarLauncher.exe` through the given Wine/Proton build
+/// `steam_path` is only required by Proton which expects to be run by the Steam client
+pub fn build_launch_command(
+    runner: &LinuxRunner,
+    game_install: &GameInstall,
+    profile_launch_options: &ProfileLaunchOptions,
+    steam_path: Option<&Path>,
+) -> Result<std::process::Command, String> {
+    let wine_prefix = match &game_install.wine_prefix {
+        Some(wine_prefix) => PathBuf::from(wine_prefix),
+        None => {
+            return Err(format!(
+                "No wine prefix known for Titanfall2 install at {}",
+                game_install.game_path
+            ))
+        }
+    };
+
+    let ns_exe_path = Path::new(&game_install.game_path).join("NorthstarLauncher.exe");
+    let ns_profile_arg = format!("-profile={}", game_install.profile);
+
+    let mut command = match runner.kind {
+        LinuxRunnerKind::Proton => match get_steam_compat_data_path(&wine_prefix) {
+            Some(compat_data_path) => {
+                let Some(steam_path) = steam_path else {
+                    return Err("Proton requires Steam to be installed".to_string());
+                };
+
+                let mut command = std::process::Command::new(&runner.path);
+                command
+                    .arg("run")
+                    .env("STEAM_COMPAT_DATA_PATH", compat_data_path)
+                    .env("STEAM_COMPAT_CLIENT_INSTALL_PATH", steam_path)
+                    .env("SteamAppId", thermite::TITANFALL2_STEAM_ID.to_string())
+                    .env("SteamGameId", thermite::TITANFALL2_STEAM_ID.to_string());
+                command
+            }
+            None => {
+                // Proton would create a new prefix in a `pfx` folder inside of the given one,
+                // missing the EA App login, so the Wine bundled with Proton is used instead
+                let Some(wine_path) = get_proton_wine_path(Path::new(&runner.path)) else {
+                    return Err(format!(
+                        "{} can only be used with Steam prefixes, select a Wine build for the prefix at {}",
+                        runner.name,
+                        wine_prefix.display()
+                    ));
+                };
+
+                let mut command = std::process::Command::new(wine_path);
+                command.env("WINEPREFIX", &wine_prefix);
+                command
+            }
+        },
+        LinuxRunnerKind::Wine => {
+            let mut command = std::process::Command::new(&runner.path);
+            command.env("WINEPREFIX", &wine_prefix);
+            command
+        }
+    };
+
+    command
+        .current_dir(&game_install.game_path)
+        .env("WINEDLLOVERRIDES", NORTHSTAR_WINE_DLL_OVERRIDES);
+
+    command
+        .arg(ns_exe_path)
+        .arg(ns_profile_arg)
+        .args(profile_launch_options.to_args())
+        .envs(&profile_launch_options.environment);
+    Ok(command)
+}
+
+/// Launches Northstar for a non-Steam install through the given Wine/Proton build
+/// Returns the spawned runner process
+pub fn launch_northstar_wine(
+    runner: &LinuxRunner,
+    game_install: &GameInstall,
+    profile_launch_options: &ProfileLaunchOptions,
+) -> Result<std::process::Child, String> {
+    let steam_path = match steamlocate::SteamDir::locate() {
+        Ok(steamdir) => Some(steamdir.path().to_path_buf()),
+        Err(_) => None,
+    };
+
+    let mut command = build_launch_command(
+        runner,
+        game_install,
+        profile_launch_options,
+        steam_path.as_deref(),
+    )?;
+    log::info!("Launching Northstar via {} ({})", runner.name, runner.path);
+
+    command
+        .spawn()
+        .map_err(|err| format!("Failed to launch Northstar via {}: {err}", runner.name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates an empty file including all parent directories
+    fn touch(path: &Path) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, "").unwrap();
+    }
+
+    /// Creates a Steam library containing Titanfall2
+    fn create_steam_library(library_path: &Path) {
+        std::fs::create_dir_all(library_path.join("steamapps")).unwrap();
+        std::fs::write(
+            library_path.join("steamapps/appmanifest_1237970.acf"),
+            "\"AppState\"\n{\n\t\"appid\"\t\t\"1237970\"\n\t\"installdir\"\t\t\"Titanfall2\"\n}\n",
+        )
+        .unwrap();
+        touch(&library_path.join("steamapps/common/Titanfall2/Titanfall2.exe"));
+        std::fs::create_dir_all(library_path.join("steamapps/compatdata/1237970/pfx")).unwrap();
+    }
+
+    #[test]
+    fn test_discover_flatpak_steam() {
+        let home_dir = tempfile::tempdir().unwrap();
+        let steam_path = home_dir
+            .path()
+            .join(".var/app/com.valvesoftware.Steam/.local/share/Steam");
+        create_steam_library(&steam_path);
+        std::fs::write(
+            steam_path.join("steamapps/libraryfolders.vdf"),
+            format!(
+                "\"libraryfolders\"\n{{\n\t\"0\"\n\t{{\n\t\t\"path\"\t\t\"{}\"\n\t}}\n}}\n",
+                steam_path.display()
+            ),
+        )
+        .unwrap();
+
+        let game_installs = discover_game_installs_in(home_dir.path(), Path::new("/nonexistent"));
+
+        assert_eq!(game_installs.len(), 1);
+        assert!(matches!(game_installs[0].install_type, InstallType::STEAM));
+        assert_eq!(
+            game_installs[0].game_path,
+            steam_path
+                .join("steamapps/common/Titanfall2")
+                .display()
+                .to_string()
+        );
+        assert_eq!(
+            game_installs[0].wine_prefix,
+            Some(
+                steam_path
+                    .join("steamapps/compatdata/1237970/pfx")
+                    .display()
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_discover_steam_deck_sd_card() {
+        let home_dir = tempfile::tempdir().unwrap();
+        let media_root = tempfile::tempdir().unwrap();
+        let library_path = media_root.path().join("deck/sdcard");
+        create_steam_library(&library_path);
+
+        let game_installs = discover_game_installs_in(home_dir.path(), media_root.path());
+
+        assert_eq!(game_installs.len(), 1);
+        assert!(matches!(game_installs[0].install_type, InstallType::STEAM));
+    }
+
+    #[test]
+    fn test_discover_heroic_prefix() {
+        let home_dir = tempfile::tempdir().unwrap();
+        let wine_prefix = home_dir.path().join("prefixes/ea-app");
+        touch(&wine_prefix.join("drive_c/Program Files/EA Games/Titanfall2/Titanfall2.exe"));
+        touch(
+            &home_dir
+                .path()
+                .join(".config/heroic/GamesConfig/ea-app.json"),
+        );
+        std::fs::write(
+            home_dir
+                .path()
+                .join(".config/heroic/GamesConfig/ea-app.json"),
+            format!(
+                "{{\"ea-app\": {{\"winePrefix\": \"{}\"}}}}",
+                wine_prefix.display()
+            ),
+        )
+        .unwrap();
+
+        let game_installs = discover_game_installs_in(home_dir.path(), Path::new("/nonexistent"));
+
+        assert_eq!(game_installs.len(), 1);
+        assert!(matches!(game_installs[0].install_type, InstallType::EAPLAY));
+        assert_eq!(
+            game_installs[0].wine_prefix,
+            Some(wine_prefix.display().to_string())
+        );
+    }
+
+    #[test]
+    fn test_discover_lutris_prefix() {
+        let home_dir = tempfile::tempdir().unwrap();
+        let wine_prefix = home_dir.path().join("wine/origin");
+        touch(
+            &wine_prefix.join("drive_c/Program Files (x86)/Origin Games/Titanfall2/Titanfall2.exe"),
+        );
+        touch(&home_dir.path().join(".config/lutris/games/origin.yml"));
+        std::fs::write(
+            home_dir.path().join(".config/lutris/games/origin.yml"),
+            format!(
+                "game:\n  exe: Origin.exe\n  prefix: {}\n",
+                wine_prefix.display()
+            ),
+        )
+        .unwrap();
+
+        let game_installs = discover_game_installs_in(home_dir.path(), Path::new("/nonexistent"));
+
+        assert_eq!(game_installs.len(), 1);
+        assert!(matches!(game_installs[0].install_type, InstallType::ORIGIN));
+        assert!(game_installs[0]
+            .game_path
+            .ends_with("Origin Games/Titanfall2"));
+    }
+
+    /// Looks up the value of an environment variable set on a command
+    fn get_env(command: &std::process::Command, key: &str) -> Option<String> {
+        command
+            .get_envs()
+            .find(|(env_key, _)| *env_key == key)
+            .and_then(|(_, value)| value)
+            .map(|value| value.to_string_lossy().to_string())
+    }
+
+    fn wine_game_install(wine_prefix: &Path) -> GameInstall {
+        GameInstall {
+            game_path: wine_prefix
+                .join("drive_c/Program Files/EA Games/Titanfall2")
+                .display()
+                .to_string(),
+            profile: "R2Northstar".to_string(),
+            install_type: InstallType::ORIGIN,
+            wine_prefix: Some(wine_prefix.display().to_string()),
+        }
+    }
+
+    #[test]
+    fn test_find_runners() {
+        let home_dir = tempfile::tempdir().unwrap();
+        touch(
+            &home_dir
+                .path()
+                .join(".local/share/Steam/compatibilitytools.d/NorthstarProton8-28/proton"),
+        );
+        touch(
+            &home_dir
+                .path()
+                .join(".local/share/lutris/runners/wine/wine-ge-8-26/bin/wine"),
+        );
+        // Not a runner
+        std::fs::create_dir_all(
+            home_dir
+                .path()
+                .join(".local/share/Steam/steamapps/common/Titanfall2"),
+        )
+        .unwrap();
+
+        let runners = find_runners_in(home_dir.path());
+
+        assert_eq!(runners.len(), 2);
+        assert_eq!(runners[0].kind, LinuxRunnerKind::Proton);
+        assert_eq!(runners[0].name, "NorthstarProton8-28");
+        assert_eq!(runners[1].kind, LinuxRunnerKind::Wine);
+        assert_eq!(runners[1].name, "wine-ge-8-26");
+    }
+
+    #[test]
+    fn test_build_proton_launch_command() {
+        let runner = LinuxRunner {
+            kind: LinuxRunnerKind::Proton,
+            name: "NorthstarProton8-28".to_string(),
+            path: "/proton/proton".to_string(),
+        };
+        let game_install = wine_game_install(Path::new("/compatdata/1237970/pfx"));
+
+        let command = build_launch_command(
+            &runner,
+            &game_install,
+            &ProfileLaunchOptions::default(),
+            Some(Path::new("/steam")),
+        )
+        .unwrap();
+
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(args[0], "run");
+        assert!(args[1].to_string_lossy().ends_with("NorthstarLauncher.exe"));
+        assert_eq!(args[2], "-profile=R2Northstar");
+        assert_eq!(
+            get_env(&command, "STEAM_COMPAT_DATA_PATH"),
+            Some("/compatdata/1237970".to_string())
+        );
+        assert_eq!(
+            get_env(&command, "STEAM_COMPAT_CLIENT_INSTALL_PATH"),
+            Some("/steam".to_string())
+        );
+        assert_eq!(get_env(&command, "WINEPREFIX"), None);
+    }
+
+    #[test]
+    fn test_build_proton_launch_command_non_steam_prefix() {
+        let proton_dir = tempfile::tempdir().unwrap();
+        touch(&proton_dir.path().join("proton"));
+        let runner = LinuxRunner {
+            kind: LinuxRunnerKind::Proton,
+            name: "NorthstarProton8-28".to_string(),
+            path: proton_dir.path().join("proton").display().to_string(),
+        };
+        let game_install = wine_game_install(Path::new("/prefixes/heroic/pfx"));
+
+        // Without a bundled Wine there is no way to run inside the prefix
+        assert!(build_launch_command(
+            &runner,
+            &game_install,
+            &ProfileLaunchOptions::default(),
+            Some(Path::new("/steam")),
+        )
+        .is_err());
+
+        let wine_path = proton_dir.path().join("files/bin/wine");
+        touch(&wine_path);
+        let command = build_launch_command(
+            &runner,
+            &game_install,
+            &ProfileLaunchOptions::default(),
+            Some(Path::new("/steam")),
+        )
+        .unwrap();
+
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(command.get_program(), wine_path.as_os_str());
+        assert!(args[0].to_string_lossy().ends_with("NorthstarLauncher.exe"));
+        assert_eq!(args[1], "-profile=R2Northstar");
+        assert_eq!(
+            get_env(&command, "WINEPREFIX"),
+            Some("/prefixes/heroic/pfx".to_string())
+        );
+        assert_eq!(get_env(&command, "STEAM_COMPAT_DATA_PATH"), None);
+    }
+
+    #[test]
+    fn test_build_wine_launch_command() {
+        let runner = LinuxRunner {
+            kind: LinuxRunnerKind::Wine,
+            name: "System Wine".to_string(),
+            path: "/usr/bin/wine".to_string(),
+        };
+        let game_install = wine_game_install(Path::new("/prefixes/ea-app"));
+
+        let profile_launch_options = ProfileLaunchOptions {
+            arguments: vec!["-novid".to_string()],
+            environment: std::collections::BTreeMap::from([(
+                "DXVK_HUD".to_string(),
+                "fps".to_string(),
+            )]),
+            ..Default::default()
+        };
+
+        let command =
+            build_launch_command(&runner, &game_install, &profile_launch_options, None).unwrap();
+
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(args.len(), 3);
+        assert_eq!(args[1], "-profile=R2Northstar");
+        assert_eq!(args[2], "-novid");
+        assert_eq!(get_env(&command, "DXVK_HUD"), Some("fps".to_string()));
+        assert_eq!(
+            get_env(&command, "WINEPREFIX"),
+            Some("/prefixes/ea-app".to_string())
+        );
+        assert_eq!(
+            get_env(&command, "WINEDLLOVERRIDES"),
+            Some(NORTHSTAR_WINE_DLL_OVERRIDES.to_string())
+        );
+    }
+}
@@ -4,6 +4,31 @@ pub mod windows;
 #[cfg(target_os = "linux")]
 pub mod linux;
 
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::util::{get_stored_value, set_stored_value};
+
+/// Key under which the Wine/Proton build used to launch non-Steam installs is persisted
+const LINUX_RUNNER_KEY: &str = "linux-runner";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+#[ts(export)]
+pub enum LinuxRunnerKind {
+    Proton,
+    Wine,
+}
+
+/// A Wine or Proton build that can run Titanfall2 on Linux
+/// `path` points to the `proton` script or `wine` binary
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+#[ts(export)]
+pub struct LinuxRunner {
+    pub kind: LinuxRunnerKind,
+    pub name: String,
+    pub path: String,
+}
+
 /// Returns identifier of host OS FlightCore is running on
 #[tauri::command]
 pub fn get_host_os() -> String {
@@ -15,7 +40,7 @@ pub fn get_host_os() -> String {
 #[tauri::command]
 pub async fn install_northstar_proton_wrapper() -> Result<(), String> {
     #[cfg(target_os = "linux")]
-    return linux::install_ns_proton().map_err(|err| err.to_string());
+    return linux::install_ns_proton().await;
 
     #[cfg(target_os = "windows")]
     Err("Not supported on Windows".to_string())
@@ -39,6 +64,46 @@ pub async fn get_local_northstar_proton_wrapper_version() -> Result<String, Stri
     Err("Not supported on Windows".to_string())
 }
 
+/// Lists Wine and Proton builds found on this computer
+#[tauri::command]
+pub async fn get_linux_runners() -> Result<Vec<LinuxRunner>, String> {
+    #[cfg(target_os = "linux")]
+    return Ok(linux::find_runners());
+
+    #[cfg(target_os = "windows")]
+    Err("Not supported on Windows".to_string())
+}
+
+/// Returns the Wine/Proton build selected for launching non-Steam installs
+#[tauri::command]
+pub async fn get_selected_linux_runner(
+    app: tauri::AppHandle,
+) -> Result<Option<LinuxRunner>, String> {
+    get_selected_runner(&app)
+}
+
+pub fn get_selected_runner(app: &tauri::AppHandle) -> Result<Option<LinuxRunner>, String> {
+    get_stored_value(app, LINUX_RUNNER_KEY)
+        .map_err(|err| format!("Failed reading selected Wine/Proton build: {err}"))
+}
+
+/// Selects the Wine/Proton build used to launch non-Steam installs
+/// Passing `None` falls back to NorthstarProton if installed
+#[tauri::command]
+pub async fn set_selected_linux_runner(
+    app: tauri::AppHandle,
+    runner: Option<LinuxRunner>,
+) -> Result<(), String> {
+    if let Some(runner) = &runner {
+        if !std::path::Path::new(&runner.path).is_file() {
+            return Err(format!("{} does not exist", runner.path));
+        }
+    }
+
+    set_stored_value(&app, LINUX_RUNNER_KEY, &runner)
+        .map_err(|err| format!("Failed saving selected Wine/Proton build: {err}"))
+}
+
 /// Check whether the current device might be behind a CGNAT
 #[tauri::command]
 pub async fn check_cgnat() -> Result<String, String> {
@@ -0,0 +1,215 @@
+//! Shared view on the processes FlightCore cares about
+//!
+//! Instead of every check scanning the whole system, a single monitor keeps one process list
+//! that is periodically refreshed and answers all queries (EA App running, Northstar running,
+//! killing Northstar) from the same snapshot.
+use std::sync::Mutex;
+use std::time::Duration;
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, UpdateKind};
+use tauri::Manager;
+
+use crate::util::{get_stored_value, set_stored_value};
+
+/// Key under which the refresh interval is persisted
+const PROCESS_MONITOR_INTERVAL_KEY: &str = "process-monitor-interval";
+
+/// Refresh interval used unless configured otherwise
+const DEFAULT_INTERVAL: Duration = Duration::from_millis(2000);
+
+/// Refreshing more often than this gives no benefit but costs CPU time
+const MIN_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Processes of the EA App and its predecessor Origin
+const EA_APP_PROCESS_NAMES: [&str; 2] = ["Origin.exe", "EADesktop.exe"];
+
+/// Processes of Northstar and the game itself
+const NORTHSTAR_PROCESS_NAMES: [&str; 2] = ["NorthstarLauncher.exe", "Titanfall2.exe"];
+
+/// Relevant processes found during the last refresh
+#[derive(Debug, Clone, Default)]
+pub struct ProcessSnapshot {
+    pub ea_app_pids: Vec<u32>,
+    pub northstar_pids: Vec<u32>,
+    /// Profile passed via `-profile=` to the running Northstar, if it could be read
+    pub northstar_profile: Option<String>,
+}
+
+impl ProcessSnapshot {
+    pub fn is_ea_app_running(&self) -> bool {
+        !self.ea_app_pids.is_empty()
+    }
+
+    pub fn is_northstar_running(&self) -> bool {
+        !self.northstar_pids.is_empty()
+    }
+}
+
+struct ProcessMonitorState {
+    system: sysinfo::System,
+    interval: Duration,
+}
+
+/// Periodically refreshed list of relevant processes, managed as Tauri state
+pub struct ProcessMonitor(Mutex<ProcessMonitorState>);
+
+impl Default for ProcessMonitor {
+    fn default() -> Self {
+        ProcessMonitor(Mutex::new(ProcessMonitorState {
+            system: sysinfo::System::new(),
+            interval: DEFAULT_INTERVAL,
+        }))
+    }
+}
+
+impl ProcessMonitor {
+    /// Refreshes the process list and returns a snapshot of the relevant processes
+    /// Only processes are refreshed, command lines only once per process
+    pub fn refresh(&self) -> ProcessSnapshot {
+        let mut state = self.0.lock().unwrap();
+        state.system.refresh_processes_specifics(
+            ProcessesToUpdate::All,
+            true,
+            ProcessRefreshKind::nothing().with_cmd(UpdateKind::OnlyIfNotSet),
+        );
+
+        let mut snapshot = ProcessSnapshot::default();
+        for (pid, process) in state.system.processes() {
+            let name = process.name().to_string_lossy();
+            if EA_APP_PROCESS_NAMES
+                .iter()
+                .any(|ea_app_name| name.contains(ea_app_name))
+            {
+                snapshot.ea_app_pids.push(pid.as_u32());
+            }
+            if NORTHSTAR_PROCESS_NAMES.contains(&name.as_ref()) {
+                snapshot.northstar_pids.push(pid.as_u32());
+                if snapshot.northstar_profile.is_none() {
+                    snapshot.northstar_profile = parse_profile_argument(process.cmd());
+                }
+            }
+        }
+
+        snapshot
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.0.lock().unwrap().interval
+    }
+
+    pub fn set_interval(&self, interval: Duration) {
+        self.0.lock().unwrap().interval = interval.max(MIN_INTERVAL);
+    }
+
+    /// Asks all Northstar processes to close, returns the number of processes asked
+    /// Unlike `kill_northstar` this gives the game a chance to shut down cleanly
+    pub fn terminate_northstar(&self) -> usize {
+        let snapshot = self.refresh();
+        let state = self.0.lock().unwrap();
+
+        let mut terminated = 0;
+        for pid in snapshot.northstar_pids {
+            let Some(process) = state.system.process(sysinfo::Pid::from_u32(pid)) else {
+                continue;
+            };
+            log::info!("Terminating Process {}", pid);
+            if request_termination(process) {
+                terminated += 1;
+            }
+        }
+        terminated
+    }
+
+    /// Kills all Northstar processes, returns the number of killed processes
+    pub fn kill_northstar(&self) -> usize {
+        let snapshot = self.refresh();
+        let state = self.0.lock().unwrap();
+
+        let mut killed = 0;
+        for pid in snapshot.northstar_pids {
+            if let Some(process) = state.system.process(sysinfo::Pid::from_u32(pid)) {
+                log::info!("Killing Process {}", pid);
+                if process.kill() {
+                    killed += 1;
+                }
+            }
+        }
+        killed
+    }
+}
+
+/// Sends a close request to the process
+/// Windows has no terminate signal, `taskkill` without `/F` asks the window to close instead
+fn request_termination(process: &sysinfo::Process) -> bool {
+    #[cfg(target_os = "windows")]
+    return std::process::Command::new("taskkill")
+        .args(["/PID", &process.pid().to_string()])
+        .status()
+        .is_ok_and(|status| status.success());
+
+    #[cfg(not(target_os = "windows"))]
+    process.kill_with(sysinfo::Signal::Term).unwrap_or(false)
+}
+
+/// Extracts the value of `-profile=` from a command line
+pub fn parse_profile_argument<S: AsRef<std::ffi::OsStr>>(cmd: &[S]) -> Option<String> {
+    cmd.iter().find_map(|arg| {
+        arg.as_ref()
+            .to_string_lossy()
+            .strip_prefix("-profile=")
+            .map(|profile| profile.to_string())
+    })
+}
+
+/// Whether no FlightCore window is currently shown, in which case no status events are sent
+pub fn is_window_hidden(app: &tauri::AppHandle) -> bool {
+    app.webview_windows().values().all(|window| {
+        !window.is_visible().unwrap_or(true) || window.is_minimized().unwrap_or(false)
+    })
+}
+
+/// Applies the persisted refresh interval
+pub fn load_interval(app: &tauri::AppHandle) {
+    match get_stored_value::<u64>(app, PROCESS_MONITOR_INTERVAL_KEY) {
+        Ok(Some(interval_ms)) => app
+            .state::<ProcessMonitor>()
+            .set_interval(Duration::from_millis(interval_ms)),
+        Ok(None) => {}
+        Err(err) => log::warn!("Failed reading process monitor interval: {err}"),
+    }
+}
+
+/// Returns how often the process list is refreshed in milliseconds
+#[tauri::command]
+pub fn get_process_monitor_interval(app: tauri::AppHandle) -> u64 {
+    app.state::<ProcessMonitor>().interval().as_millis() as u64
+}
+
+/// Sets how often the process list is refreshed in milliseconds
+#[tauri::command]
+pub fn set_process_monitor_interval(app: tauri::AppHandle, interval_ms: u64) -> Result<(), String> {
+    let interval = Duration::from_millis(interval_ms);
+    if interval < MIN_INTERVAL {
+        return Err(format!(
+            "Interval must be at least {}ms",
+            MIN_INTERVAL.as_millis()
+        ));
+    }
+
+    app.state::<ProcessMonitor>().set_interval(interval);
+    set_stored_value(&app, PROCESS_MONITOR_INTERVAL_KEY, &interval_ms)
+        .map_err(|err| format!("Failed saving process monitor interval: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_profile_argument() {
+        assert_eq!(
+            parse_profile_argument(&["NorthstarLauncher.exe", "-profile=R2Coop", "-novid"]),
+            Some("R2Coop".to_string())
+        );
+        assert_eq!(parse_profile_argument(&["Titanfall2.exe"]), None);
+    }
+}
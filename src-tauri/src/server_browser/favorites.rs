@@ -0,0 +1,306 @@
+//! Favorite and recently played servers
+//!
+//! Recently played servers are picked up from the Northstar logs once a game session ended.
+//! Server ids change when a server re-registers with the master server, so saved servers are
+//! matched against the live server list by name if their id is no longer listed.
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::northstar::session::GameSession;
+use crate::server_browser::master_server::get_master_server_url;
+use crate::server_browser::{fetch_server_list, NorthstarServer};
+use crate::util::{get_stored_value, set_stored_value};
+use crate::GameInstall;
+
+/// Key under which favorite servers are persisted
+const FAVORITE_SERVERS_KEY: &str = "favorite-servers";
+
+/// Key under which recently played servers are persisted
+const RECENT_SERVERS_KEY: &str = "recent-servers";
+
+/// Oldest servers are dropped once more than this were played recently
+const MAX_RECENT_SERVERS: usize = 50;
+
+/// Logged by Northstar when joining a server listed on the master server
+const CONNECT_LOG_PREFIX: &str = "Attempting authentication with server of id \"";
+
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct FavoriteServer {
+    pub id: String,
+    pub name: String,
+    /// RFC 3339 timestamp
+    pub added_at: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct RecentServer {
+    pub id: String,
+    /// Empty until the server was seen in the server list
+    pub name: String,
+    pub profile: String,
+    /// RFC 3339 timestamp of the end of the session the server was joined in
+    pub last_played: String,
+}
+
+/// A favorite or recent server together with its current entry in the server list
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct SavedServerStatus {
+    pub id: String,
+    pub name: String,
+    pub is_favorite: bool,
+    pub last_played: Option<String>,
+    /// `None` if the server is currently not listed
+    pub live: Option<NorthstarServer>,
+}
+
+/// Extracts the ids of the servers joined from a Northstar log, in order of joining
+fn parse_connected_server_ids(log: &str) -> Vec<String> {
+    log.lines()
+        .filter_map(|line| {
+            let (_, rest) = line.split_once(CONNECT_LOG_PREFIX)?;
+            let (id, _) = rest.split_once('"')?;
+            Some(id.to_string())
+        })
+        .collect()
+}
+
+/// Finds the live entry of a saved server, by id or by name if the id changed
+fn find_live_server<'a>(
+    servers: &'a [NorthstarServer],
+    id: &str,
+    name: &str,
+) -> Option<&'a NorthstarServer> {
+    servers.iter().find(|server| server.id == id).or_else(|| {
+        servers
+            .iter()
+            .find(|server| !name.is_empty() && server.name == name)
+    })
+}
+
+fn load_favorite_servers(app: &tauri::AppHandle) -> Result<Vec<FavoriteServer>, String> {
+    get_stored_value(app, FAVORITE_SERVERS_KEY)
+        .map(Option::unwrap_or_default)
+        .map_err(|err| format!("Failed reading favorite servers: {err}"))
+}
+
+fn save_favorite_servers(
+    app: &tauri::AppHandle,
+    favorite_servers: &[FavoriteServer],
+) -> Result<(), String> {
+    set_stored_value(app, FAVORITE_SERVERS_KEY, &favorite_servers)
+        .map_err(|err| format!("Failed saving favorite servers: {err}"))
+}
+
+fn load_recent_servers(app: &tauri::AppHandle) -> Result<Vec<RecentServer>, String> {
+    get_stored_value(app, RECENT_SERVERS_KEY)
+        .map(Option::unwrap_or_default)
+        .map_err(|err| format!("Failed reading recent servers: {err}"))
+}
+
+fn save_recent_servers(
+    app: &tauri::AppHandle,
+    recent_servers: &[RecentServer],
+) -> Result<(), String> {
+    set_stored_value(app, RECENT_SERVERS_KEY, &recent_servers)
+        .map_err(|err| format!("Failed saving recent servers: {err}"))
+}
+
+/// Adds the servers joined during the finished session to the recent servers
+pub fn record_recent_servers(
+    app: &tauri::AppHandle,
+    game_session: &GameSession,
+) -> Result<(), String> {
+    let (Some(game_path), Some(ended_at)) = (&game_session.game_path, &game_session.ended_at)
+    else {
+        return Ok(());
+    };
+    let started_at = chrono::DateTime::parse_from_rfc3339(&game_session.started_at)
+        .map_err(|err| format!("Invalid session start: {err}"))?
+        .with_timezone(&chrono::Utc);
+
+    // Paths may contain glob syntax such as `Games [SSD]`
+    let pattern = format!(
+        "{}/logs/nslog*.txt",
+        glob::Pattern::escape(&format!("{}/{}", game_path, game_session.profile))
+    );
+    let log_paths =
+        glob::glob(&pattern).map_err(|err| format!("Failed listing Northstar logs: {err}"))?;
+    let mut server_ids: Vec<String> = vec![];
+    for log_path in log_paths.flatten() {
+        let written_during_session = log_path
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .is_ok_and(|modified| chrono::DateTime::<chrono::Utc>::from(modified) >= started_at);
+        if !written_during_session {
+            continue;
+        }
+        match std::fs::read_to_string(&log_path) {
+            Ok(log) => server_ids.extend(parse_connected_server_ids(&log)),
+            Err(err) => log::warn!("Failed reading {}: {err}", log_path.display()),
+        }
+    }
+    if server_ids.is_empty() {
+        return Ok(());
+    }
+
+    let mut recent_servers = load_recent_servers(app)?;
+    for id in server_ids {
+        let name = recent_servers
+            .iter()
+            .find(|recent_server| recent_server.id == id)
+            .map(|recent_server| recent_server.name.clone())
+            .unwrap_or_default();
+        recent_servers.retain(|recent_server| recent_server.id != id);
+        recent_servers.insert(
+            0,
+            RecentServer {
+                id,
+                name,
+                profile: game_session.profile.clone(),
+                last_played: ended_at.clone(),
+            },
+        );
+    }
+    recent_servers.truncate(MAX_RECENT_SERVERS);
+
+    save_recent_servers(app, &recent_servers)
+}
+
+/// Returns the favorite servers
+#[tauri::command]
+pub fn get_favorite_servers(app: tauri::AppHandle) -> Result<Vec<FavoriteServer>, String> {
+    load_favorite_servers(&app)
+}
+
+/// Adds the server to the favorites
+#[tauri::command]
+pub fn add_favorite_server(app: tauri::AppHandle, server: NorthstarServer) -> Result<(), String> {
+    let mut favorite_servers = load_favorite_servers(&app)?;
+    if favorite_servers
+        .iter()
+        .any(|favorite_server| favorite_server.id == server.id)
+    {
+        return Ok(());
+    }
+
+    favorite_servers.push(FavoriteServer {
+        id: server.id,
+        name: server.name,
+        added_at: chrono::Utc::now().to_rfc3339(),
+    });
+    save_favorite_servers(&app, &favorite_servers)
+}
+
+/// Removes the server with the given id from the favorites
+#[tauri::command]
+pub fn remove_favorite_server(app: tauri::AppHandle, id: String) -> Result<(), String> {
+    let mut favorite_servers = load_favorite_servers(&app)?;
+    favorite_servers.retain(|favorite_server| favorite_server.id != id);
+    save_favorite_servers(&app, &favorite_servers)
+}
+
+/// Returns the recently played servers, most recent first
+#[tauri::command]
+pub fn get_recent_servers(app: tauri::AppHandle) -> Result<Vec<RecentServer>, String> {
+    load_recent_servers(&app)
+}
+
+/// Looks up favorite and recent servers in the current server list
+/// Favorites come first, followed by recent servers that are not favorites
+/// Stored ids and names are updated to the ones currently listed
+/// Uses the master server of the given profile, the official one if none is given
+#[tauri::command]
+pub async fn get_saved_server_status(
+    app: tauri::AppHandle,
+    game_install: Option<GameInstall>,
+) -> Result<Vec<SavedServerStatus>, String> {
+    let ns_servers = fetch_server_list(&get_master_server_url(game_install.as_ref()))
+        .await
+        .map_err(|err| format!("Failed fetching server list: {err}"))?;
+
+    let mut favorite_servers = load_favorite_servers(&app)?;
+    let mut recent_servers = load_recent_servers(&app)?;
+    let mut statuses: Vec<SavedServerStatus> = vec![];
+
+    for favorite_server in favorite_servers.iter_mut() {
+        let live = find_live_server(&ns_servers, &favorite_server.id, &favorite_server.name);
+        if let Some(live) = live {
+            favorite_server.id = live.id.clone();
+            favorite_server.name = live.name.clone();
+        }
+        statuses.push(SavedServerStatus {
+            id: favorite_server.id.clone(),
+            name: favorite_server.name.clone(),
+            is_favorite: true,
+            last_played: None,
+            live: live.cloned(),
+        });
+    }
+
+    for recent_server in recent_servers.iter_mut() {
+        let live = find_live_server(&ns_servers, &recent_server.id, &recent_server.name);
+        if let Some(live) = live {
+            recent_server.id = live.id.clone();
+            recent_server.name = live.name.clone();
+        }
+
+        match statuses
+            .iter_mut()
+            .find(|status| status.is_favorite && status.id == recent_server.id)
+        {
+            Some(status) => status.last_played = Some(recent_server.last_played.clone()),
+            None => statuses.push(SavedServerStatus {
+                id: recent_server.id.clone(),
+                name: recent_server.name.clone(),
+                is_favorite: false,
+                last_played: Some(recent_server.last_played.clone()),
+                live: live.cloned(),
+            }),
+        }
+    }
+
+    save_favorite_servers(&app, &favorite_servers)?;
+    save_recent_servers(&app, &recent_servers)?;
+
+    Ok(statuses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_connected_server_ids() {
+        let log = r#"[12:00:01] [NORTHSTAR] [info] Loading mods
+[12:03:10] [NORTHSTAR] [info] Attempting authentication with server of id "abc123"
+[12:03:11] [NORTHSTAR] [info] Successfully connected to server
+[12:20:45] [NORTHSTAR] [info] Attempting authentication with server of id "def456"
+"#;
+        assert_eq!(parse_connected_server_ids(log), vec!["abc123", "def456"]);
+        assert!(parse_connected_server_ids("[info] Loading mods").is_empty());
+    }
+
+    #[test]
+    fn test_find_live_server() {
+        let servers: Vec<NorthstarServer> = serde_json::from_str(
+            r#"[
+                { "id": "new-id", "name": "[EU] Attrition", "playerCount": 3 },
+                { "id": "other", "name": "Parkour", "playerCount": 0 }
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            find_live_server(&servers, "other", "Renamed").map(|server| server.name.as_str()),
+            Some("Parkour")
+        );
+        assert_eq!(
+            find_live_server(&servers, "old-id", "[EU] Attrition").map(|server| server.id.as_str()),
+            Some("new-id")
+        );
+        assert!(find_live_server(&servers, "old-id", "").is_none());
+    }
+}
@@ -0,0 +1,231 @@
+//! Selection of the master server used for the server list, statistics and by the game itself
+//!
+//! The known master servers are shared by all profiles while each profile selects its own one
+//! through the `ns_masterserver_hostname` convar of its launch options.
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::constants::MASTER_SERVER_URL;
+use crate::game_installs::{get_active_game_install, get_game_installs};
+use crate::northstar::profile::{
+    fetch_profiles, read_profile_launch_options, set_profile_launch_options,
+};
+use crate::util::{get_stored_value, set_stored_value};
+use crate::GameInstall;
+
+/// Key under which the custom master servers are persisted
+const MASTER_SERVER_SETTINGS_KEY: &str = "master-servers";
+
+/// Convar Northstar reads the master server from
+pub const MASTER_SERVER_CONVAR: &str = "ns_masterserver_hostname";
+
+/// A master server the user can pick from
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+#[ts(export)]
+pub struct MasterServer {
+    pub name: String,
+    pub url: String,
+}
+
+/// Known master servers and which one a profile uses
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct MasterServerSettings {
+    pub selected_url: String,
+    pub master_servers: Vec<MasterServer>,
+}
+
+/// Persisted part of the settings, the official master server is always known
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct StoredMasterServers {
+    custom_master_servers: Vec<MasterServer>,
+}
+
+fn official_master_server() -> MasterServer {
+    MasterServer {
+        name: "Northstar".to_string(),
+        url: MASTER_SERVER_URL.to_string(),
+    }
+}
+
+/// Checks that the URL is a plain http(s) URL and strips trailing slashes
+fn normalize_master_server_url(url: &str) -> Result<String, String> {
+    let parsed = reqwest::Url::parse(url.trim()).map_err(|err| format!("Invalid URL: {err}"))?;
+    if !["http", "https"].contains(&parsed.scheme()) {
+        return Err(format!("{url} is not an http(s) URL"));
+    }
+    if parsed.query().is_some() || parsed.fragment().is_some() {
+        return Err(format!("{url} may not contain a query or fragment"));
+    }
+
+    Ok(parsed.as_str().trim_end_matches('/').to_string())
+}
+
+fn load_stored_master_servers(app: &tauri::AppHandle) -> Result<StoredMasterServers, String> {
+    get_stored_value(app, MASTER_SERVER_SETTINGS_KEY)
+        .map(Option::unwrap_or_default)
+        .map_err(|err| format!("Failed reading master server settings: {err}"))
+}
+
+fn save_stored_master_servers(
+    app: &tauri::AppHandle,
+    stored: &StoredMasterServers,
+) -> Result<(), String> {
+    set_stored_value(app, MASTER_SERVER_SETTINGS_KEY, stored)
+        .map_err(|err| format!("Failed saving master server settings: {err}"))
+}
+
+/// URL of the master server the profile uses, the official one if no profile is given
+pub fn get_master_server_url(game_install: Option<&GameInstall>) -> String {
+    let Some(game_install) = game_install else {
+        return MASTER_SERVER_URL.to_string();
+    };
+    match read_profile_launch_options(game_install) {
+        Ok(launch_options) => launch_options
+            .convars
+            .get(MASTER_SERVER_CONVAR)
+            .cloned()
+            .unwrap_or(MASTER_SERVER_URL.to_string()),
+        Err(err) => {
+            log::warn!("Failed reading launch options: {err}");
+            MASTER_SERVER_URL.to_string()
+        }
+    }
+}
+
+/// URLs of the master servers selected by the profiles of all known installs
+/// The one of the active install's profile comes first
+pub async fn get_selected_master_server_urls(app: &tauri::AppHandle) -> Vec<String> {
+    let active_game_install = get_active_game_install(app.clone())
+        .await
+        .unwrap_or_else(|err| {
+            log::warn!("{err}");
+            None
+        });
+    let mut master_server_urls = vec![get_master_server_url(active_game_install.as_ref())];
+
+    let game_installs = get_game_installs(app.clone()).await.unwrap_or_else(|err| {
+        log::warn!("{err}");
+        vec![]
+    });
+    for registered_install in game_installs.iter().filter(|install| install.is_valid) {
+        let game_install = registered_install.to_game_install();
+        for profile in fetch_profiles(game_install.clone()).unwrap_or_default() {
+            let url = get_master_server_url(Some(&GameInstall {
+                profile,
+                ..game_install.clone()
+            }));
+            if !master_server_urls.contains(&url) {
+                master_server_urls.push(url);
+            }
+        }
+    }
+
+    master_server_urls
+}
+
+/// Returns the known master servers and the one the profile uses
+#[tauri::command]
+pub fn get_master_servers(
+    app: tauri::AppHandle,
+    game_install: GameInstall,
+) -> Result<MasterServerSettings, String> {
+    let stored = load_stored_master_servers(&app)?;
+
+    let mut master_servers = vec![official_master_server()];
+    master_servers.extend(stored.custom_master_servers);
+
+    Ok(MasterServerSettings {
+        selected_url: get_master_server_url(Some(&game_install)),
+        master_servers,
+    })
+}
+
+/// Adds a master server to the list of known ones
+#[tauri::command]
+pub fn add_master_server(
+    app: tauri::AppHandle,
+    name: String,
+    url: String,
+) -> Result<MasterServer, String> {
+    let url = normalize_master_server_url(&url)?;
+
+    let mut stored = load_stored_master_servers(&app)?;
+    if url == MASTER_SERVER_URL
+        || stored
+            .custom_master_servers
+            .iter()
+            .any(|master_server| master_server.url == url)
+    {
+        return Err(format!("{url} is already known"));
+    }
+
+    let master_server = MasterServer { name, url };
+    stored.custom_master_servers.push(master_server.clone());
+    save_stored_master_servers(&app, &stored)?;
+    Ok(master_server)
+}
+
+/// Removes a custom master server
+/// Profiles using it keep doing so until another master server is selected for them
+#[tauri::command]
+pub fn remove_master_server(app: tauri::AppHandle, url: String) -> Result<(), String> {
+    if url == MASTER_SERVER_URL {
+        return Err("The official master server cannot be removed".to_string());
+    }
+
+    let mut stored = load_stored_master_servers(&app)?;
+    stored
+        .custom_master_servers
+        .retain(|master_server| master_server.url != url);
+    save_stored_master_servers(&app, &stored)
+}
+
+/// Selects the master server the profile uses
+#[tauri::command]
+pub fn set_master_server(
+    app: tauri::AppHandle,
+    game_install: GameInstall,
+    url: String,
+) -> Result<(), String> {
+    let stored = load_stored_master_servers(&app)?;
+    let mut launch_options = read_profile_launch_options(&game_install)
+        .map_err(|err| format!("Failed reading launch options: {err}"))?;
+
+    if url == MASTER_SERVER_URL {
+        // Northstar uses the official master server by default
+        launch_options.convars.remove(MASTER_SERVER_CONVAR);
+    } else if stored
+        .custom_master_servers
+        .iter()
+        .any(|master_server| master_server.url == url)
+    {
+        launch_options
+            .convars
+            .insert(MASTER_SERVER_CONVAR.to_string(), url);
+    } else {
+        return Err(format!("{url} is not a known master server"));
+    }
+
+    set_profile_launch_options(game_install, launch_options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_master_server_url() {
+        assert_eq!(
+            normalize_master_server_url("https://ms.example.com/").unwrap(),
+            "https://ms.example.com"
+        );
+        assert_eq!(
+            normalize_master_server_url("http://localhost:8080").unwrap(),
+            "http://localhost:8080"
+        );
+        assert!(normalize_master_server_url("ftp://ms.example.com").is_err());
+        assert!(normalize_master_server_url("not a url").is_err());
+        assert!(normalize_master_server_url("https://ms.example.com/?a=b").is_err());
+    }
+}
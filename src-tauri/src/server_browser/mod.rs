@@ -0,0 +1,371 @@
+//! Server list of the Northstar master server
+pub mod favorites;
+pub mod master_server;
+pub mod required_mods;
+pub mod statistics;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::constants::{APP_USER_AGENT, SERVER_BROWSER_ENDPOINT};
+use crate::thunderstore::index;
+use crate::GameInstall;
+use master_server::get_master_server_url;
+
+/// Mod a server runs as reported to the master server
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct NorthstarServerMod {
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Version", default)]
+    pub version: String,
+    /// Whether clients need the mod installed to join
+    #[serde(rename = "RequiredOnClient", default)]
+    pub required_on_client: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, TS)]
+#[ts(export)]
+pub struct NorthstarServerModInfo {
+    #[serde(rename = "Mods", default)]
+    pub mods: Vec<NorthstarServerMod>,
+}
+
+/// A server as listed by the master server
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct NorthstarServer {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(rename = "playerCount")]
+    pub player_count: i32,
+    #[serde(rename = "maxPlayers", default)]
+    pub max_players: i32,
+    #[serde(default)]
+    pub map: String,
+    #[serde(default)]
+    pub playlist: String,
+    #[serde(default)]
+    pub region: String,
+    #[serde(rename = "hasPassword", default)]
+    pub has_password: bool,
+    #[serde(rename = "modInfo", default)]
+    pub mod_info: NorthstarServerModInfo,
+}
+
+impl NorthstarServer {
+    /// Mods clients need to have installed to join the server
+    pub fn required_mods(&self) -> impl Iterator<Item = &NorthstarServerMod> {
+        self.mod_info
+            .mods
+            .iter()
+            .filter(|server_mod| server_mod.required_on_client)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, TS)]
+#[ts(export)]
+pub enum ServerSortField {
+    #[default]
+    Players,
+    Name,
+    Region,
+    Map,
+    RequiredMods,
+}
+
+/// Filters and sorting applied to the server list
+/// Text filters are case-insensitive, unset fields do not filter
+#[derive(Serialize, Deserialize, Debug, Clone, Default, TS)]
+#[ts(export)]
+#[serde(default)]
+pub struct ServerListFilter {
+    /// Matched against name and description
+    pub search: Option<String>,
+    pub region: Option<String>,
+    pub map: Option<String>,
+    pub playlist: Option<String>,
+    pub hide_full: bool,
+    pub hide_empty: bool,
+    pub hide_password_protected: bool,
+    /// Only show servers that can be joined without installing additional mods
+    pub hide_with_required_mods: bool,
+    /// Only show servers requiring the given mod
+    pub required_mod: Option<String>,
+    pub sort_by: ServerSortField,
+    pub sort_descending: bool,
+}
+
+/// Case-insensitive substring match
+fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+impl ServerListFilter {
+    fn matches(&self, server: &NorthstarServer) -> bool {
+        if let Some(search) = &self.search {
+            if !contains_ignore_case(&server.name, search)
+                && !contains_ignore_case(&server.description, search)
+            {
+                return false;
+            }
+        }
+        if let Some(region) = &self.region {
+            if !server.region.eq_ignore_ascii_case(region) {
+                return false;
+            }
+        }
+        if let Some(map) = &self.map {
+            if !server.map.eq_ignore_ascii_case(map) {
+                return false;
+            }
+        }
+        if let Some(playlist) = &self.playlist {
+            if !server.playlist.eq_ignore_ascii_case(playlist) {
+                return false;
+            }
+        }
+        if let Some(required_mod) = &self.required_mod {
+            if !server
+                .required_mods()
+                .any(|server_mod| server_mod.name.eq_ignore_ascii_case(required_mod))
+            {
+                return false;
+            }
+        }
+
+        let is_full = server.max_players > 0 && server.player_count >= server.max_players;
+        !(self.hide_full && is_full
+            || self.hide_empty && server.player_count == 0
+            || self.hide_password_protected && server.has_password
+            || self.hide_with_required_mods && server.required_mods().next().is_some())
+    }
+
+    /// Filters and sorts the given servers
+    pub fn apply(&self, servers: Vec<NorthstarServer>) -> Vec<NorthstarServer> {
+        let mut servers: Vec<NorthstarServer> = servers
+            .into_iter()
+            .filter(|server| self.matches(server))
+            .collect();
+
+        servers.sort_by(|a, b| {
+            let ordering = match self.sort_by {
+                ServerSortField::Players => a.player_count.cmp(&b.player_count),
+                ServerSortField::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+                ServerSortField::Region => a.region.to_lowercase().cmp(&b.region.to_lowercase()),
+                ServerSortField::Map => a.map.to_lowercase().cmp(&b.map.to_lowercase()),
+                ServerSortField::RequiredMods => {
+                    a.required_mods().count().cmp(&b.required_mods().count())
+                }
+            };
+            if self.sort_descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+
+        servers
+    }
+}
+
+/// Fetches `/client/servers` endpoint from the given master server
+pub async fn fetch_server_list(
+    master_server_url: &str,
+) -> Result<Vec<NorthstarServer>, anyhow::Error> {
+    let url = format!("{master_server_url}{SERVER_BROWSER_ENDPOINT}");
+    // Custom master servers may stall, don't let them block the statistics loop
+    let client = reqwest::Client::builder()
+        .connect_timeout(index::CONNECT_TIMEOUT)
+        .timeout(index::FETCH_TIMEOUT)
+        .build()?;
+    let res = client
+        .get(url)
+        .header(reqwest::header::USER_AGENT, APP_USER_AGENT)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+
+    Ok(serde_json::from_str(&res)?)
+}
+
+/// Gets server and playercount from master server API
+/// Uses the master server of the given profile, the official one if none is given
+#[tauri::command]
+pub async fn get_server_player_count(
+    game_install: Option<GameInstall>,
+) -> Result<(i32, usize), String> {
+    let ns_servers = match fetch_server_list(&get_master_server_url(game_install.as_ref())).await {
+        Ok(ns_servers) => ns_servers,
+        Err(err) => return Err(err.to_string()),
+    };
+
+    // Get server count
+    let server_count = ns_servers.len();
+
+    // Sum up player count
+    let total_player_count: i32 = ns_servers.iter().map(|server| server.player_count).sum();
+
+    log::info!("total_player_count: {}", total_player_count);
+    log::info!("server_count:       {}", server_count);
+
+    Ok((total_player_count, server_count))
+}
+
+/// Returns the servers listed by the master server, filtered and sorted as requested
+/// Uses the master server of the given profile, the official one if none is given
+#[tauri::command]
+pub async fn get_server_list(
+    game_install: Option<GameInstall>,
+    filter: ServerListFilter,
+) -> Result<Vec<NorthstarServer>, String> {
+    let ns_servers = fetch_server_list(&get_master_server_url(game_install.as_ref()))
+        .await
+        .map_err(|err| format!("Failed fetching server list: {err}"))?;
+
+    Ok(filter.apply(ns_servers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERVER_LIST: &str = r#"[
+        {
+            "lastHeartbeat": 1700000000000,
+            "id": "1",
+            "name": "[EU] Attrition",
+            "description": "Vanilla attrition",
+            "playerCount": 12,
+            "maxPlayers": 16,
+            "map": "mp_forwardbase_kodai",
+            "playlist": "aitdm",
+            "region": "EU West",
+            "hasPassword": false,
+            "modInfo": { "Mods": [
+                { "Name": "Northstar.Custom", "Version": "1.20.0", "RequiredOnClient": true, "Pdiff": "" }
+            ] }
+        },
+        {
+            "id": "2",
+            "name": "Parkour",
+            "description": "Custom maps",
+            "playerCount": 16,
+            "maxPlayers": 16,
+            "map": "mp_glitch",
+            "playlist": "private_match",
+            "region": "US East",
+            "hasPassword": true,
+            "modInfo": { "Mods": [
+                { "Name": "Parkour", "Version": "0.3.1", "RequiredOnClient": true },
+                { "Name": "Server.Utils", "Version": "1.0.0", "RequiredOnClient": false }
+            ] }
+        },
+        {
+            "id": "3",
+            "name": "Empty",
+            "playerCount": 0,
+            "maxPlayers": 8,
+            "map": "mp_glitch",
+            "playlist": "ps",
+            "region": "EU West",
+            "hasPassword": false,
+            "modInfo": { "Mods": [] }
+        }
+    ]"#;
+
+    fn server_ids(servers: &[NorthstarServer]) -> Vec<&str> {
+        servers.iter().map(|server| server.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_parse_server_list() {
+        let servers: Vec<NorthstarServer> = serde_json::from_str(SERVER_LIST).unwrap();
+
+        assert_eq!(servers.len(), 3);
+        assert_eq!(servers[1].max_players, 16);
+        assert!(servers[1].has_password);
+        assert_eq!(servers[1].required_mods().count(), 1);
+        assert_eq!(servers[2].description, "");
+    }
+
+    #[test]
+    fn test_filter_server_list() {
+        let servers: Vec<NorthstarServer> = serde_json::from_str(SERVER_LIST).unwrap();
+
+        let filter = ServerListFilter {
+            region: Some("eu west".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(server_ids(&filter.apply(servers.clone())), vec!["3", "1"]);
+
+        let filter = ServerListFilter {
+            hide_full: true,
+            hide_empty: true,
+            ..Default::default()
+        };
+        assert_eq!(server_ids(&filter.apply(servers.clone())), vec!["1"]);
+
+        let filter = ServerListFilter {
+            required_mod: Some("parkour".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(server_ids(&filter.apply(servers.clone())), vec!["2"]);
+
+        let filter = ServerListFilter {
+            hide_with_required_mods: true,
+            ..Default::default()
+        };
+        assert_eq!(server_ids(&filter.apply(servers)), vec!["3"]);
+    }
+
+    #[test]
+    fn test_sort_server_list() {
+        let servers: Vec<NorthstarServer> = serde_json::from_str(SERVER_LIST).unwrap();
+
+        let filter = ServerListFilter {
+            sort_by: ServerSortField::Players,
+            sort_descending: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            server_ids(&filter.apply(servers.clone())),
+            vec!["2", "1", "3"]
+        );
+
+        let filter = ServerListFilter {
+            sort_by: ServerSortField::Name,
+            ..Default::default()
+        };
+        assert_eq!(
+            server_ids(&filter.apply(servers.clone())),
+            vec!["1", "3", "2"]
+        );
+
+        // Region and map ignore case like the name does
+        let mut servers = servers;
+        servers[2].region = "eu central".to_string();
+        servers[1].map = "MP_Glitch".to_string();
+        let filter = ServerListFilter {
+            sort_by: ServerSortField::Region,
+            ..Default::default()
+        };
+        assert_eq!(
+            server_ids(&filter.apply(servers.clone())),
+            vec!["3", "1", "2"]
+        );
+
+        let filter = ServerListFilter {
+            sort_by: ServerSortField::Map,
+            ..Default::default()
+        };
+        assert_eq!(server_ids(&filter.apply(servers)), vec!["1", "2", "3"]);
+    }
+}
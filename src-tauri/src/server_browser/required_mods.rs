@@ -0,0 +1,236 @@
+//! Installing the mods a server requires clients to have
+//!
+//! Servers only advertise the `Name` and `Version` from each mod's `mod.json`, which usually
+//! differ from the Thunderstore package name, so packages are matched by name and author.
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use ts_rs::TS;
+
+use crate::constants::{BLACKLISTED_MODS, CORE_MODS};
+use crate::mod_management::r2modman::create_profile_from;
+use crate::mod_management::{
+    fc_download_mod_and_install, get_installed_mods_and_properties, set_mod_enabled_status,
+};
+use crate::northstar::profile::{is_valid_profile_name, validate_profile};
+use crate::server_browser::{NorthstarServer, NorthstarServerMod};
+use crate::thunderstore::index::get_package_index;
+use crate::GameInstall;
+
+/// A required mod that could not be provided
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct ServerModInstallFailure {
+    mod_name: String,
+    version: String,
+    reason: String,
+}
+
+/// Outcome of installing the required mods of a server
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct ServerModsInstallResult {
+    /// Profile the mods were installed into
+    profile: String,
+    /// Thunderstore mod strings of newly installed packages
+    installed_packages: Vec<String>,
+    /// Names of already installed mods that were enabled
+    enabled_mods: Vec<String>,
+    failed_mods: Vec<ServerModInstallFailure>,
+}
+
+/// Normalises a name for comparison, Thunderstore only allows `[a-zA-Z0-9_]` in package names
+fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Finds the Thunderstore package most likely providing the given mod in the given version
+/// Returns the mod string (`author-name-version`) of the matching package version
+fn find_thunderstore_package(
+    index: &[thermite::model::Mod],
+    mod_name: &str,
+    version: &str,
+) -> Option<String> {
+    let normalized_mod_name = normalize_name(mod_name);
+
+    // Mods are often named `Author.ModName`
+    let (mod_author, mod_short_name) = match mod_name.split_once('.') {
+        Some((author, name)) => (Some(normalize_name(author)), normalize_name(name)),
+        None => (None, normalized_mod_name.clone()),
+    };
+
+    let author_and_name_match = index.iter().find(|package| {
+        mod_author
+            .as_ref()
+            .is_some_and(|author| &normalize_name(&package.author) == author)
+            && normalize_name(&package.name) == mod_short_name
+    });
+
+    // Matching the name alone would pick packages of unrelated authors,
+    // so it's only done if a single package of that name exists
+    let name_matches: Vec<&thermite::model::Mod> = index
+        .iter()
+        .filter(|package| normalize_name(&package.name) == normalized_mod_name)
+        .collect();
+    let exact_match = match name_matches.as_slice() {
+        [package] => Some(*package),
+        _ => None,
+    };
+
+    author_and_name_match
+        .or(exact_match)
+        .and_then(|package| package.versions.get(version))
+        .map(|package_version| package_version.full_name.clone())
+}
+
+/// Installs and enables the mods required by the given server
+/// If `server_profile` is set, a profile of that name is created (if needed) from the given install's
+/// profile and the mods are installed into it instead
+#[tauri::command]
+pub async fn install_server_required_mods(
+    game_install: GameInstall,
+    server: NorthstarServer,
+    server_profile: Option<String>,
+) -> Result<ServerModsInstallResult, String> {
+    let mut game_install = game_install;
+    if let Some(server_profile) = server_profile {
+        if !is_valid_profile_name(&server_profile) {
+            return Err(format!("\"{server_profile}\" is not a valid profile name"));
+        }
+        if !validate_profile(game_install.clone(), server_profile.clone()) {
+            log::info!("Creating profile {server_profile} for {}", server.name);
+            create_profile_from(&game_install, &server_profile).map_err(|err| err.to_string())?;
+        }
+        game_install.profile = server_profile;
+    }
+
+    let installed_mods = get_installed_mods_and_properties(game_install.clone())?;
+    let required_mods: Vec<&NorthstarServerMod> = server.required_mods().collect();
+
+    let mut result = ServerModsInstallResult {
+        profile: game_install.profile.clone(),
+        installed_packages: vec![],
+        enabled_mods: vec![],
+        failed_mods: vec![],
+    };
+    let mut index: Option<Arc<Vec<thermite::model::Mod>>> = None;
+
+    for required_mod in required_mods {
+        // Every server lists Northstar's own mods, those come with Northstar
+        if CORE_MODS.contains(&required_mod.name.as_str()) {
+            continue;
+        }
+
+        let failure = |reason: String| ServerModInstallFailure {
+            mod_name: required_mod.name.clone(),
+            version: required_mod.version.clone(),
+            reason,
+        };
+
+        // Already installed in the right version, might only need enabling
+        if let Some(installed_mod) = installed_mods.iter().find(|installed_mod| {
+            installed_mod.name == required_mod.name
+                && installed_mod.version.as_deref() == Some(required_mod.version.as_str())
+        }) {
+            if !installed_mod.enabled {
+                match set_mod_enabled_status(
+                    game_install.clone(),
+                    installed_mod.name.clone(),
+                    required_mod.version.clone(),
+                    true,
+                ) {
+                    Ok(()) => result.enabled_mods.push(installed_mod.name.clone()),
+                    Err(err) => result.failed_mods.push(failure(err)),
+                }
+            }
+            continue;
+        }
+
+        let index = match &index {
+            Some(index) => index,
+            None => {
+                let package_index = get_package_index()
+                    .await
+                    .map_err(|err| format!("Failed fetching Thunderstore index: {err}"))?;
+                index.insert(package_index)
+            }
+        };
+
+        let Some(thunderstore_mod_string) =
+            find_thunderstore_package(index, &required_mod.name, &required_mod.version)
+        else {
+            result.failed_mods.push(failure(
+                "Could not find matching package on Thunderstore".to_string(),
+            ));
+            continue;
+        };
+
+        if BLACKLISTED_MODS
+            .iter()
+            .any(|blacklisted| thunderstore_mod_string.starts_with(blacklisted))
+        {
+            result.failed_mods.push(failure(format!(
+                "{thunderstore_mod_string} may not be installed via FlightCore"
+            )));
+            continue;
+        }
+
+        match fc_download_mod_and_install(&game_install, &thunderstore_mod_string).await {
+            Ok(()) => result.installed_packages.push(thunderstore_mod_string),
+            Err(err) => result.failed_mods.push(failure(err)),
+        }
+    }
+
+    if let Err(err) = crate::repair_and_verify::clean_up_download_folder(&game_install, false) {
+        log::info!("Failed to delete download folder due to {}", err);
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::thunderstore::index::to_thermite_mod;
+    use crate::thunderstore::test_package;
+
+    fn package(author: &str, name: &str, versions: &[&str]) -> thermite::model::Mod {
+        to_thermite_mod(&test_package(author, name, versions)).unwrap()
+    }
+
+    #[test]
+    fn test_find_thunderstore_package() {
+        let index = vec![
+            package("Fifty", "Frostbite", &["1.1.0", "1.0.0"]),
+            package("Someone", "Parkour", &["0.3.1"]),
+            package("Other", "Parkour", &["0.2.0"]),
+        ];
+
+        assert_eq!(
+            find_thunderstore_package(&index, "Fifty.Frostbite", "1.1.0"),
+            Some("Fifty-Frostbite-1.1.0".to_string())
+        );
+        assert_eq!(
+            find_thunderstore_package(&index, "Frostbite", "1.0.0"),
+            Some("Fifty-Frostbite-1.0.0".to_string())
+        );
+        assert_eq!(
+            find_thunderstore_package(&index, "Other.Parkour", "0.2.0"),
+            Some("Other-Parkour-0.2.0".to_string())
+        );
+        // Ambiguous without an author
+        assert_eq!(find_thunderstore_package(&index, "Parkour", "0.3.1"), None);
+        assert_eq!(find_thunderstore_package(&index, "Parkour", "0.2.0"), None);
+        assert_eq!(
+            find_thunderstore_package(&index, "Fifty.Frostbite", "9.9.9"),
+            None
+        );
+        // Packages of other authors are not picked by name alone
+        assert_eq!(
+            find_thunderstore_package(&index, "Stranger.Parkour", "0.3.1"),
+            None
+        );
+    }
+}
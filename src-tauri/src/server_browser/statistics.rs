@@ -0,0 +1,375 @@
+//! Historical player and server counts
+//!
+//! Every statistics refresh is stored as a sample, including per-playlist and per-region
+//! breakdowns, so the history can be aggregated to see when people are online. Samples record
+//! the master server they were taken from so histories of different master servers don't mix.
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tauri_plugin_store::StoreExt;
+use ts_rs::TS;
+
+use crate::constants::MASTER_SERVER_URL;
+use crate::server_browser::master_server::get_master_server_url;
+use crate::server_browser::{fetch_server_list, NorthstarServer};
+use crate::GameInstall;
+
+/// File the samples are persisted in, kept apart from the settings as it grows much larger
+const STATISTICS_STORE: &str = "flight-core-statistics.json";
+
+/// Key under which the samples are persisted
+const STATISTICS_SAMPLES_KEY: &str = "samples";
+
+/// Samples older than this are dropped, a bit more than the longest history range
+const STATISTICS_RETENTION: chrono::TimeDelta = chrono::TimeDelta::days(8);
+
+/// Players and servers of a single playlist or region
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, TS)]
+#[ts(export)]
+pub struct StatisticsBreakdown {
+    pub player_count: i32,
+    pub server_count: u32,
+}
+
+/// Player and server counts at a single point in time
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct StatisticsSample {
+    /// RFC 3339 timestamp
+    pub timestamp: String,
+    /// Master server the server list was fetched from
+    #[serde(default = "default_master_server_url")]
+    pub master_server_url: String,
+    pub player_count: i32,
+    pub server_count: u32,
+    pub playlists: BTreeMap<String, StatisticsBreakdown>,
+    pub regions: BTreeMap<String, StatisticsBreakdown>,
+}
+
+/// Samples taken before the master server was recorded all come from the official one
+fn default_master_server_url() -> String {
+    MASTER_SERVER_URL.to_string()
+}
+
+impl StatisticsSample {
+    fn from_servers(
+        servers: &[NorthstarServer],
+        master_server_url: &str,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    ) -> Self {
+        let mut sample = StatisticsSample {
+            timestamp: timestamp.to_rfc3339(),
+            master_server_url: master_server_url.to_string(),
+            player_count: 0,
+            server_count: 0,
+            playlists: BTreeMap::new(),
+            regions: BTreeMap::new(),
+        };
+
+        for server in servers {
+            sample.player_count += server.player_count;
+            sample.server_count += 1;
+            for (breakdowns, key) in [
+                (&mut sample.playlists, &server.playlist),
+                (&mut sample.regions, &server.region),
+            ] {
+                let breakdown = breakdowns.entry(key.clone()).or_default();
+                breakdown.player_count += server.player_count;
+                breakdown.server_count += 1;
+            }
+        }
+
+        sample
+    }
+
+    fn parsed_timestamp(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        chrono::DateTime::parse_from_rfc3339(&self.timestamp)
+            .map(|timestamp| timestamp.with_timezone(&chrono::Utc))
+            .ok()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, TS)]
+#[ts(export)]
+pub enum StatisticsHistoryRange {
+    Day,
+    Week,
+}
+
+impl StatisticsHistoryRange {
+    fn duration(self) -> chrono::TimeDelta {
+        match self {
+            StatisticsHistoryRange::Day => chrono::TimeDelta::days(1),
+            StatisticsHistoryRange::Week => chrono::TimeDelta::weeks(1),
+        }
+    }
+
+    /// Length of the time slots samples are grouped into
+    fn bucket_size(self) -> chrono::TimeDelta {
+        match self {
+            StatisticsHistoryRange::Day => chrono::TimeDelta::hours(1),
+            StatisticsHistoryRange::Week => chrono::TimeDelta::hours(6),
+        }
+    }
+}
+
+/// Aggregated samples of one time slot
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct StatisticsBucket {
+    /// RFC 3339 timestamp of the start of the time slot
+    pub start: String,
+    pub sample_count: u32,
+    pub average_player_count: f64,
+    pub peak_player_count: i32,
+    pub average_server_count: f64,
+    /// Average player count per playlist
+    pub playlists: BTreeMap<String, f64>,
+    /// Average player count per region
+    pub regions: BTreeMap<String, f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct StatisticsHistory {
+    pub range: StatisticsHistoryRange,
+    /// Oldest first, time slots without samples are left out
+    pub buckets: Vec<StatisticsBucket>,
+}
+
+/// Groups the samples of the master server within the range before `now` into time slots and
+/// averages them
+fn aggregate_samples(
+    samples: &[StatisticsSample],
+    master_server_url: &str,
+    range: StatisticsHistoryRange,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Vec<StatisticsBucket> {
+    let bucket_seconds = range.bucket_size().num_seconds();
+    let range_start = now - range.duration();
+
+    let mut grouped: BTreeMap<i64, Vec<&StatisticsSample>> = BTreeMap::new();
+    for sample in samples {
+        if sample.master_server_url != master_server_url {
+            continue;
+        }
+        let Some(timestamp) = sample.parsed_timestamp() else {
+            continue;
+        };
+        if timestamp < range_start || timestamp > now {
+            continue;
+        }
+        let bucket_start = timestamp.timestamp() - timestamp.timestamp().rem_euclid(bucket_seconds);
+        grouped.entry(bucket_start).or_default().push(sample);
+    }
+
+    grouped
+        .into_iter()
+        .map(|(bucket_start, samples)| {
+            let sample_count = samples.len() as f64;
+            let average_breakdown = |breakdowns: Vec<&BTreeMap<String, StatisticsBreakdown>>| {
+                let mut totals: BTreeMap<String, f64> = BTreeMap::new();
+                for (key, breakdown) in breakdowns.into_iter().flatten() {
+                    *totals.entry(key.clone()).or_default() += breakdown.player_count as f64;
+                }
+                totals
+                    .into_iter()
+                    .map(|(key, total)| (key, total / sample_count))
+                    .collect()
+            };
+
+            StatisticsBucket {
+                start: chrono::DateTime::from_timestamp(bucket_start, 0)
+                    .unwrap_or_default()
+                    .to_rfc3339(),
+                sample_count: samples.len() as u32,
+                average_player_count: samples
+                    .iter()
+                    .map(|sample| sample.player_count as f64)
+                    .sum::<f64>()
+                    / sample_count,
+                peak_player_count: samples
+                    .iter()
+                    .map(|sample| sample.player_count)
+                    .max()
+                    .unwrap_or_default(),
+                average_server_count: samples
+                    .iter()
+                    .map(|sample| sample.server_count as f64)
+                    .sum::<f64>()
+                    / sample_count,
+                playlists: average_breakdown(
+                    samples.iter().map(|sample| &sample.playlists).collect(),
+                ),
+                regions: average_breakdown(samples.iter().map(|sample| &sample.regions).collect()),
+            }
+        })
+        .collect()
+}
+
+fn load_samples(app: &tauri::AppHandle) -> Result<Vec<StatisticsSample>, String> {
+    let store = app
+        .store(STATISTICS_STORE)
+        .map_err(|err| format!("Failed opening statistics store: {err}"))?;
+    match store.get(STATISTICS_SAMPLES_KEY) {
+        Some(samples) => serde_json::from_value(samples)
+            .map_err(|err| format!("Failed reading statistics samples: {err}")),
+        None => Ok(vec![]),
+    }
+}
+
+/// Appends the sample to the store, dropping samples past the retention period
+fn store_sample(app: &tauri::AppHandle, sample: StatisticsSample) -> Result<(), String> {
+    let mut samples = load_samples(app)?;
+    let oldest_kept = chrono::Utc::now() - STATISTICS_RETENTION;
+    samples.retain(|sample| {
+        sample
+            .parsed_timestamp()
+            .is_some_and(|timestamp| timestamp >= oldest_kept)
+    });
+    samples.push(sample);
+
+    let store = app
+        .store(STATISTICS_STORE)
+        .map_err(|err| format!("Failed opening statistics store: {err}"))?;
+    store.set(STATISTICS_SAMPLES_KEY, serde_json::json!(samples));
+    store
+        .save()
+        .map_err(|err| format!("Failed saving statistics samples: {err}"))
+}
+
+/// Fetches the server list of the master server, records a sample and returns the total player
+/// and server count
+pub async fn record_statistics(
+    app: &tauri::AppHandle,
+    master_server_url: &str,
+) -> Result<(i32, usize), String> {
+    let ns_servers = fetch_server_list(master_server_url)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    let sample = StatisticsSample::from_servers(&ns_servers, master_server_url, chrono::Utc::now());
+    let counts = (sample.player_count, ns_servers.len());
+    if let Err(err) = store_sample(app, sample) {
+        log::warn!("{err}");
+    }
+
+    Ok(counts)
+}
+
+/// Returns player and server counts of the last day or week aggregated into time slots
+/// Uses the samples of the given profile's master server, the official one if none is given
+#[tauri::command]
+pub fn get_statistics_history(
+    app: tauri::AppHandle,
+    range: StatisticsHistoryRange,
+    game_install: Option<GameInstall>,
+) -> Result<StatisticsHistory, String> {
+    let samples = load_samples(&app)?;
+    let master_server_url = get_master_server_url(game_install.as_ref());
+    Ok(StatisticsHistory {
+        range,
+        buckets: aggregate_samples(&samples, &master_server_url, range, chrono::Utc::now()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(player_count: i32, playlist: &str, region: &str) -> NorthstarServer {
+        serde_json::from_value(serde_json::json!({
+            "playerCount": player_count,
+            "playlist": playlist,
+            "region": region,
+        }))
+        .unwrap()
+    }
+
+    fn timestamp(rfc3339: &str) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::parse_from_rfc3339(rfc3339)
+            .unwrap()
+            .with_timezone(&chrono::Utc)
+    }
+
+    #[test]
+    fn test_sample_from_servers() {
+        let servers = vec![
+            server(10, "aitdm", "EU West"),
+            server(4, "ps", "EU West"),
+            server(6, "aitdm", "US East"),
+        ];
+        let sample = StatisticsSample::from_servers(
+            &servers,
+            MASTER_SERVER_URL,
+            timestamp("2024-05-01T12:00:00Z"),
+        );
+
+        assert_eq!(sample.player_count, 20);
+        assert_eq!(sample.server_count, 3);
+        assert_eq!(
+            sample.playlists["aitdm"],
+            StatisticsBreakdown {
+                player_count: 16,
+                server_count: 2
+            }
+        );
+        assert_eq!(sample.regions["EU West"].player_count, 14);
+    }
+
+    #[test]
+    fn test_aggregate_samples() {
+        let samples = vec![
+            StatisticsSample::from_servers(
+                &[server(10, "aitdm", "EU West")],
+                MASTER_SERVER_URL,
+                timestamp("2024-04-29T12:00:00Z"),
+            ),
+            StatisticsSample::from_servers(
+                &[server(10, "aitdm", "EU West")],
+                MASTER_SERVER_URL,
+                timestamp("2024-05-01T10:05:00Z"),
+            ),
+            StatisticsSample::from_servers(
+                &[server(20, "aitdm", "EU West"), server(4, "ps", "US East")],
+                MASTER_SERVER_URL,
+                timestamp("2024-05-01T10:40:00Z"),
+            ),
+            StatisticsSample::from_servers(
+                &[server(6, "ps", "US East")],
+                MASTER_SERVER_URL,
+                timestamp("2024-05-01T11:10:00Z"),
+            ),
+            // Left out as it belongs to another master server
+            StatisticsSample::from_servers(
+                &[server(50, "ps", "US East")],
+                "https://ms.example.com",
+                timestamp("2024-05-01T11:20:00Z"),
+            ),
+        ];
+
+        let buckets = aggregate_samples(
+            &samples,
+            MASTER_SERVER_URL,
+            StatisticsHistoryRange::Day,
+            timestamp("2024-05-01T12:00:00Z"),
+        );
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].start, "2024-05-01T10:00:00+00:00");
+        assert_eq!(buckets[0].sample_count, 2);
+        assert_eq!(buckets[0].average_player_count, 17.0);
+        assert_eq!(buckets[0].peak_player_count, 24);
+        assert_eq!(buckets[0].playlists["aitdm"], 15.0);
+        assert_eq!(buckets[0].playlists["ps"], 2.0);
+        assert_eq!(buckets[1].regions["US East"], 6.0);
+
+        let buckets = aggregate_samples(
+            &samples,
+            MASTER_SERVER_URL,
+            StatisticsHistoryRange::Week,
+            timestamp("2024-05-01T12:00:00Z"),
+        );
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[1].start, "2024-05-01T06:00:00+00:00");
+        assert_eq!(buckets[1].sample_count, 3);
+    }
+}
@@ -0,0 +1,317 @@
+//! Checking installed packages against Thunderstore and the package blocklist
+//!
+//! The blocklist is maintained in the FlightCore repository so packages known to break the game
+//! can be flagged without releasing a new version. `BLACKLISTED_MODS` always applies on top.
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::constants::{APP_USER_AGENT, BLACKLISTED_MODS, PACKAGE_BLOCKLIST_URL};
+use crate::mod_management::get_installed_mods_and_properties;
+use crate::thunderstore::{index, ThunderstoreMod};
+use crate::GameInstall;
+
+/// A package that should not be installed
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+#[ts(export)]
+pub struct PackageBlocklistEntry {
+    /// `author-name` of the package
+    pub package: String,
+    /// Affected versions, all versions if empty
+    #[serde(default)]
+    pub versions: Vec<String>,
+    pub reason: String,
+    /// `author-name` of a package to use instead
+    #[serde(default)]
+    pub replacement: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+#[ts(export)]
+pub enum PackageAuditIssue {
+    Blocklisted,
+    Deprecated,
+    RemovedFromThunderstore,
+}
+
+/// An installed package that should be looked at
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+#[ts(export)]
+pub struct PackageAuditFinding {
+    pub thunderstore_mod_string: String,
+    /// Mods the package installed
+    pub mod_names: Vec<String>,
+    pub issue: PackageAuditIssue,
+    pub reason: String,
+    pub replacement: Option<String>,
+}
+
+/// Entries for `BLACKLISTED_MODS` which are never installed as regular mods
+fn builtin_blocklist() -> Vec<PackageBlocklistEntry> {
+    BLACKLISTED_MODS
+        .iter()
+        .map(|package| PackageBlocklistEntry {
+            package: package.to_string(),
+            versions: vec![],
+            reason: "Not meant to be installed as a mod".to_string(),
+            replacement: None,
+        })
+        .collect()
+}
+
+/// Fetches the maintained blocklist, an unreachable list only means fewer findings
+async fn fetch_blocklist() -> Vec<PackageBlocklistEntry> {
+    // A stalled request times out like an unreachable one instead of blocking the audit
+    let client = match reqwest::Client::builder()
+        .connect_timeout(index::CONNECT_TIMEOUT)
+        .timeout(index::FETCH_TIMEOUT)
+        .build()
+    {
+        Ok(client) => client,
+        Err(err) => {
+            log::warn!("Couldn't fetch package blocklist: {err}");
+            return vec![];
+        }
+    };
+    let response = match client
+        .get(PACKAGE_BLOCKLIST_URL)
+        .header(reqwest::header::USER_AGENT, APP_USER_AGENT)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+    {
+        Ok(response) => response,
+        Err(err) => {
+            log::warn!("Couldn't fetch package blocklist: {err}");
+            return vec![];
+        }
+    };
+
+    match response.json().await {
+        Ok(blocklist) => blocklist,
+        Err(err) => {
+            log::warn!("Failed parsing package blocklist: {err}");
+            vec![]
+        }
+    }
+}
+
+/// Adds the entries for `BLACKLISTED_MODS` to the fetched blocklist
+fn merge_blocklists(mut blocklist: Vec<PackageBlocklistEntry>) -> Vec<PackageBlocklistEntry> {
+    blocklist.extend(builtin_blocklist());
+    blocklist
+}
+
+/// Finds issues of the given installed packages
+/// `installed` holds pairs of Thunderstore mod string and the names of the mods it contains
+fn audit_packages(
+    installed: &[(String, Vec<String>)],
+    packages: &[ThunderstoreMod],
+    blocklist: &[PackageBlocklistEntry],
+) -> Vec<PackageAuditFinding> {
+    let mut findings: Vec<PackageAuditFinding> = vec![];
+
+    for (thunderstore_mod_string, mod_names) in installed {
+        let Some((full_name, version)) = thunderstore_mod_string.rsplit_once('-') else {
+            continue;
+        };
+        let finding = |issue: PackageAuditIssue, reason: String, replacement: Option<String>| {
+            PackageAuditFinding {
+                thunderstore_mod_string: thunderstore_mod_string.clone(),
+                mod_names: mod_names.clone(),
+                issue,
+                reason,
+                replacement,
+            }
+        };
+
+        if let Some(entry) = blocklist.iter().find(|entry| {
+            entry.package.eq_ignore_ascii_case(full_name)
+                && (entry.versions.is_empty() || entry.versions.iter().any(|v| v == version))
+        }) {
+            findings.push(finding(
+                PackageAuditIssue::Blocklisted,
+                entry.reason.clone(),
+                entry.replacement.clone(),
+            ));
+            continue;
+        }
+
+        // A known replacement is suggested for deprecated and removed packages as well
+        let replacement = blocklist
+            .iter()
+            .find(|entry| entry.package.eq_ignore_ascii_case(full_name))
+            .and_then(|entry| entry.replacement.clone());
+
+        match packages
+            .iter()
+            .find(|package| package.full_name.eq_ignore_ascii_case(full_name))
+        {
+            None => findings.push(finding(
+                PackageAuditIssue::RemovedFromThunderstore,
+                format!("{full_name} is no longer available on Thunderstore"),
+                replacement,
+            )),
+            Some(package) if package.is_deprecated => findings.push(finding(
+                PackageAuditIssue::Deprecated,
+                format!("{full_name} was marked as deprecated by its author"),
+                replacement,
+            )),
+            Some(_) => {}
+        }
+    }
+
+    findings
+}
+
+/// Returns the installed packages that are blocklisted, deprecated or no longer on Thunderstore
+#[tauri::command]
+pub async fn audit_installed_packages(
+    game_install: GameInstall,
+) -> Result<Vec<PackageAuditFinding>, String> {
+    let mut installed: Vec<(String, Vec<String>)> = vec![];
+    for installed_mod in get_installed_mods_and_properties(game_install)? {
+        let Some(thunderstore_mod_string) = installed_mod.thunderstore_mod_string else {
+            continue;
+        };
+        match installed
+            .iter_mut()
+            .find(|(mod_string, _)| mod_string == &thunderstore_mod_string)
+        {
+            Some((_, mod_names)) => mod_names.push(installed_mod.name),
+            None => installed.push((thunderstore_mod_string, vec![installed_mod.name])),
+        }
+    }
+    if installed.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let packages = index::get_packages().await?;
+    let blocklist = merge_blocklists(fetch_blocklist().await);
+
+    Ok(audit_packages(&installed, &packages, &blocklist))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::thunderstore::test_package;
+
+    fn package(full_name: &str, is_deprecated: bool) -> ThunderstoreMod {
+        let (owner, name) = full_name.split_once('-').unwrap();
+        ThunderstoreMod {
+            is_deprecated,
+            ..test_package(owner, name, &[])
+        }
+    }
+
+    fn installed(thunderstore_mod_string: &str) -> (String, Vec<String>) {
+        (
+            thunderstore_mod_string.to_string(),
+            vec![thunderstore_mod_string.replace('-', ".")],
+        )
+    }
+
+    #[test]
+    fn test_audit_packages() {
+        let packages = vec![
+            package("Fifty-Frostbite", false),
+            package("Old-Legacy", true),
+            package("Someone-Crashy", false),
+        ];
+        let blocklist = vec![
+            PackageBlocklistEntry {
+                package: "Someone-Crashy".to_string(),
+                versions: vec!["1.0.0".to_string()],
+                reason: "Crashes on launch".to_string(),
+                replacement: None,
+            },
+            PackageBlocklistEntry {
+                package: "Old-Legacy".to_string(),
+                versions: vec!["0.0.1".to_string()],
+                reason: "Broken".to_string(),
+                replacement: Some("New-Legacy".to_string()),
+            },
+        ];
+        let installed = vec![
+            installed("Fifty-Frostbite-1.1.0"),
+            installed("Old-Legacy-2.0.0"),
+            installed("Someone-Crashy-1.0.0"),
+            installed("Someone-Crashy-1.0.1"),
+            installed("Gone-Vanished-1.0.0"),
+        ];
+
+        let findings = audit_packages(&installed, &packages, &blocklist);
+        let issues: Vec<(&str, &PackageAuditIssue)> = findings
+            .iter()
+            .map(|finding| (finding.thunderstore_mod_string.as_str(), &finding.issue))
+            .collect();
+        assert_eq!(
+            issues,
+            vec![
+                ("Old-Legacy-2.0.0", &PackageAuditIssue::Deprecated),
+                ("Someone-Crashy-1.0.0", &PackageAuditIssue::Blocklisted),
+                (
+                    "Gone-Vanished-1.0.0",
+                    &PackageAuditIssue::RemovedFromThunderstore
+                ),
+            ]
+        );
+        assert_eq!(findings[0].replacement.as_deref(), Some("New-Legacy"));
+        assert_eq!(findings[1].reason, "Crashes on launch");
+    }
+
+    #[test]
+    fn test_audit_packages_builtin_blocklist() {
+        let packages = vec![
+            package("northstar-Northstar", false),
+            package("ebkr-r2modman", false),
+            package("Someone-Crashy", false),
+        ];
+        let remote_blocklist = vec![PackageBlocklistEntry {
+            package: "Someone-Crashy".to_string(),
+            versions: vec![],
+            reason: "Crashes on launch".to_string(),
+            replacement: None,
+        }];
+        let installed = vec![
+            installed("northstar-Northstar-1.19.0"),
+            installed("ebkr-r2modman-3.1.0"),
+            installed("Someone-Crashy-1.0.0"),
+        ];
+
+        let issues = |blocklist: &[PackageBlocklistEntry]| -> Vec<(String, String)> {
+            audit_packages(&installed, &packages, blocklist)
+                .into_iter()
+                .filter(|finding| finding.issue == PackageAuditIssue::Blocklisted)
+                .map(|finding| (finding.thunderstore_mod_string, finding.reason))
+                .collect()
+        };
+        let builtin_reason = "Not meant to be installed as a mod".to_string();
+
+        assert_eq!(
+            issues(&merge_blocklists(remote_blocklist)),
+            vec![
+                (
+                    "northstar-Northstar-1.19.0".to_string(),
+                    builtin_reason.clone()
+                ),
+                ("ebkr-r2modman-3.1.0".to_string(), builtin_reason.clone()),
+                (
+                    "Someone-Crashy-1.0.0".to_string(),
+                    "Crashes on launch".to_string()
+                ),
+            ]
+        );
+        // Builtin entries still apply if the remote list couldn't be fetched
+        assert_eq!(
+            issues(&merge_blocklists(vec![])),
+            vec![
+                (
+                    "northstar-Northstar-1.19.0".to_string(),
+                    builtin_reason.clone()
+                ),
+                ("ebkr-r2modman-3.1.0".to_string(), builtin_reason),
+            ]
+        );
+    }
+}
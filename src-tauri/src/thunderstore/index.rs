@@ -0,0 +1,224 @@
+//! Shared cache of the Thunderstore package index
+//!
+//! The index is several megabytes large and needed by the mod browser, by every install for
+//! download URLs and dependencies, and by the Northstar version checks. It is fetched once and
+//! kept for `INDEX_TTL`, after which it is revalidated with a conditional request so an
+//! unchanged index is not downloaded again. Concurrent callers wait for the same fetch, which is
+//! bounded by `FETCH_TIMEOUT`. If revalidating fails the previous index keeps being used and the
+//! next attempt is only made after `RETRY_AFTER_FAILURE`.
+//!
+//! The cache is process-wide rather than Tauri state as most callers have no `AppHandle`.
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::constants::APP_USER_AGENT;
+use crate::thunderstore::ThunderstoreMod;
+
+/// Thunderstore package list of the Northstar community
+const PACKAGE_INDEX_URL: &str = "https://northstar.thunderstore.io/api/v1/package/";
+
+/// How long a fetched index is used before it is revalidated
+pub const INDEX_TTL: Duration = Duration::from_secs(5 * 60);
+
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Upper bound for fetching the whole index, callers are waiting on it
+pub const FETCH_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Wait before trying again after the index couldn't be revalidated, e.g. while offline
+const RETRY_AFTER_FAILURE: Duration = Duration::from_secs(30);
+
+struct CachedIndex {
+    packages: Arc<Vec<ThunderstoreMod>>,
+    /// Same packages in the form libthermite works with
+    mods: Arc<Vec<thermite::model::Mod>>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// When the index should be fetched or revalidated again
+    revalidate_at: Instant,
+}
+
+/// Holding the lock while fetching makes concurrent callers share a single request
+static PACKAGE_INDEX: tokio::sync::Mutex<Option<CachedIndex>> = tokio::sync::Mutex::const_new(None);
+
+/// Converts a package the same way `thermite::api::get_package_index` does
+/// Dependencies on Northstar itself are left out as they are not installed like mods
+pub(crate) fn to_thermite_mod(package: &ThunderstoreMod) -> Option<thermite::model::Mod> {
+    let latest = package.versions.first()?;
+    let versions = package
+        .versions
+        .iter()
+        .map(|version| {
+            (
+                version.version_number.clone(),
+                thermite::model::ModVersion {
+                    name: package.name.clone(),
+                    full_name: version.full_name.clone(),
+                    version: version.version_number.clone(),
+                    url: version.download_url.clone(),
+                    desc: version.description.clone(),
+                    deps: version
+                        .dependencies
+                        .iter()
+                        .filter(|dependency| !dependency.contains("northstar-Northstar"))
+                        .cloned()
+                        .collect(),
+                    installed: false,
+                    global: false,
+                    file_size: version.file_size.max(0) as u64,
+                },
+            )
+        })
+        .collect();
+
+    Some(thermite::model::Mod {
+        name: package.name.clone(),
+        latest: latest.version_number.clone(),
+        installed: false,
+        upgradable: false,
+        global: false,
+        versions,
+        author: package.owner.clone(),
+    })
+}
+
+fn header_value(response: &reqwest::Response, name: reqwest::header::HeaderName) -> Option<String> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Fetches the index, or only revalidates it if a cached one is given
+/// Returns `None` if the cached index is still current
+async fn fetch_index(cached: Option<&CachedIndex>) -> Result<Option<CachedIndex>, String> {
+    let client = reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(FETCH_TIMEOUT)
+        .build()
+        .map_err(|err| format!("Couldn't fetch from Thunderstore: {err}"))?;
+    let mut request = client
+        .get(PACKAGE_INDEX_URL)
+        .header(reqwest::header::USER_AGENT, APP_USER_AGENT);
+    if let Some(cached) = cached {
+        if let Some(etag) = &cached.etag {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &cached.last_modified {
+            request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let response = request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| format!("Couldn't fetch from Thunderstore: {err}"))?;
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+
+    let etag = header_value(&response, reqwest::header::ETAG);
+    let last_modified = header_value(&response, reqwest::header::LAST_MODIFIED);
+    let body = response
+        .bytes()
+        .await
+        .map_err(|err| format!("Couldn't fetch from Thunderstore: {err}"))?;
+    let packages: Vec<ThunderstoreMod> = serde_json::from_slice(&body)
+        .map_err(|err| format!("Failed parsing Thunderstore index: {err}"))?;
+    let mods = packages.iter().filter_map(to_thermite_mod).collect();
+
+    Ok(Some(CachedIndex {
+        packages: Arc::new(packages),
+        mods: Arc::new(mods),
+        etag,
+        last_modified,
+        revalidate_at: Instant::now() + INDEX_TTL,
+    }))
+}
+
+/// Runs `f` on the cached index, fetching or revalidating it first if it is older than `INDEX_TTL`
+/// Falls back to an outdated index if Thunderstore cannot be reached
+async fn with_index<T>(f: impl FnOnce(&CachedIndex) -> T) -> Result<T, String> {
+    let mut cache = PACKAGE_INDEX.lock().await;
+
+    let is_fresh = cache
+        .as_ref()
+        .is_some_and(|cached| Instant::now() < cached.revalidate_at);
+    if !is_fresh {
+        log::info!("Fetching Thunderstore index");
+        match fetch_index(cache.as_ref()).await {
+            Ok(Some(fetched)) => *cache = Some(fetched),
+            Ok(None) => {
+                log::info!("Thunderstore index not modified");
+                if let Some(cached) = cache.as_mut() {
+                    cached.revalidate_at = Instant::now() + INDEX_TTL;
+                }
+            }
+            Err(err) if cache.is_some() => {
+                log::warn!("{err}, using previously fetched index");
+                if let Some(cached) = cache.as_mut() {
+                    cached.revalidate_at = Instant::now() + RETRY_AFTER_FAILURE;
+                }
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    match cache.as_ref() {
+        Some(cached) => Ok(f(cached)),
+        None => Err("Thunderstore index is not available".to_string()),
+    }
+}
+
+/// Returns the Thunderstore package list
+pub async fn get_packages() -> Result<Arc<Vec<ThunderstoreMod>>, String> {
+    with_index(|cached| cached.packages.clone()).await
+}
+
+/// Returns the package index in the form libthermite works with
+/// Replaces `thermite::api::get_package_index` which downloads the whole index on every call
+pub async fn get_package_index() -> Result<Arc<Vec<thermite::model::Mod>>, String> {
+    with_index(|cached| cached.mods.clone()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::thunderstore::ThunderstoreModVersion;
+
+    #[test]
+    fn test_to_thermite_mod() {
+        let package = ThunderstoreMod {
+            name: "Frostbite".to_string(),
+            owner: "Fifty".to_string(),
+            versions: ["1.1.0", "1.0.0"]
+                .iter()
+                .map(|version| ThunderstoreModVersion {
+                    full_name: format!("Fifty-Frostbite-{version}"),
+                    version_number: version.to_string(),
+                    dependencies: vec![
+                        "northstar-Northstar-1.19.0".to_string(),
+                        "Someone-Library-2.0.0".to_string(),
+                    ],
+                    file_size: 1024,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        let thermite_mod = to_thermite_mod(&package).unwrap();
+        assert_eq!(thermite_mod.latest, "1.1.0");
+        assert_eq!(thermite_mod.author, "Fifty");
+        assert_eq!(thermite_mod.versions.len(), 2);
+        assert_eq!(
+            thermite_mod.versions["1.0.0"].deps,
+            vec!["Someone-Library-2.0.0"]
+        );
+        assert_eq!(thermite_mod.versions["1.0.0"].file_size, 1024);
+
+        assert!(to_thermite_mod(&ThunderstoreMod::default()).is_none());
+    }
+}
@@ -1,5 +1,10 @@
 //! For interacting with Thunderstore API
-use crate::constants::{APP_USER_AGENT, BLACKLISTED_MODS};
+pub mod audit;
+pub mod index;
+pub mod package_docs;
+pub mod search;
+
+use crate::constants::BLACKLISTED_MODS;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use ts_rs::TS;
@@ -40,47 +45,45 @@ pub struct ThunderstoreModVersion {
     pub file_size: i64,
 }
 
-/// Performs actual fetch from Thunderstore and returns response
-async fn fetch_thunderstore_packages() -> Result<String, reqwest::Error> {
-    log::info!("Fetching Thunderstore API");
-
-    // Fetches
-    let url = "https://northstar.thunderstore.io/api/v1/package/";
-
-    let client = reqwest::Client::new();
-    client
-        .get(url)
-        .header(reqwest::header::USER_AGENT, APP_USER_AGENT)
-        .send()
-        .await?
-        .text()
-        .await
+/// Builds a package for tests, versions are listed newest first like in the Thunderstore index
+#[cfg(test)]
+pub(crate) fn test_package(owner: &str, name: &str, versions: &[&str]) -> ThunderstoreMod {
+    ThunderstoreMod {
+        name: name.to_string(),
+        full_name: format!("{owner}-{name}"),
+        owner: owner.to_string(),
+        versions: versions
+            .iter()
+            .map(|version| ThunderstoreModVersion {
+                name: name.to_string(),
+                full_name: format!("{owner}-{name}-{version}"),
+                version_number: version.to_string(),
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    }
 }
 
-/// Queries Thunderstore packages API
-#[tauri::command]
-pub async fn query_thunderstore_packages_api() -> Result<Vec<ThunderstoreMod>, String> {
-    let res = match fetch_thunderstore_packages().await {
-        Ok(res) => res,
-        Err(err) => {
-            let warn_response = format!("Couldn't fetch from Thunderstore: {err}");
-            log::warn!("{warn_response}");
-            return Err(warn_response);
-        }
-    };
-
-    // Parse response
-    let parsed_json: Vec<ThunderstoreMod> = match serde_json::from_str(&res) {
-        Ok(res) => res,
-        Err(err) => return Err(err.to_string()),
-    };
+/// Returns the package list, leaving out packages that may not be installed
+pub async fn fetch_package_list() -> Result<Vec<ThunderstoreMod>, String> {
+    let packages = index::get_packages()
+        .await
+        .inspect_err(|err| log::warn!("{err}"))?;
 
     // Remove some mods from listing
     let to_remove_set: HashSet<&str> = BLACKLISTED_MODS.iter().copied().collect();
-    let filtered_packages = parsed_json
-        .into_iter()
+    let filtered_packages = packages
+        .iter()
         .filter(|package| !to_remove_set.contains(&package.full_name.as_ref()))
+        .cloned()
         .collect::<Vec<ThunderstoreMod>>();
 
     Ok(filtered_packages)
 }
+
+/// Queries Thunderstore packages API
+#[tauri::command]
+pub async fn query_thunderstore_packages_api() -> Result<Vec<ThunderstoreMod>, String> {
+    fetch_package_list().await
+}
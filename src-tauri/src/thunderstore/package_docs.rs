@@ -0,0 +1,331 @@
+//! README and changelog of Thunderstore packages
+//!
+//! Documents are read from the installed package under `packages/` if available and fetched
+//! from Thunderstore otherwise. The markdown is rendered to HTML here and cleaned with an
+//! allowlist, so nothing that could run code in the webview reaches the frontend.
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::LazyLock;
+use ts_rs::TS;
+
+use crate::constants::APP_USER_AGENT;
+use crate::mod_management::ParsedThunderstoreModString;
+use crate::thunderstore::index;
+use crate::GameInstall;
+
+/// Thunderstore API serving rendered package documents
+const PACKAGE_API_URL: &str = "https://northstar.thunderstore.io/api/experimental/package";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+#[ts(export)]
+pub enum PackageDocsSource {
+    Installed,
+    Thunderstore,
+}
+
+/// README and changelog of a package version
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct PackageDocs {
+    pub thunderstore_mod_string: String,
+    /// README rendered from markdown to sanitized HTML, `None` if the package has no README
+    pub readme_html: Option<String>,
+    /// Changelog rendered from markdown to sanitized HTML, `None` if the package has no changelog
+    pub changelog_html: Option<String>,
+    pub source: PackageDocsSource,
+}
+
+/// Changelog entries between the installed and the latest version
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct PackageChangesSinceInstalled {
+    pub installed_version: String,
+    pub latest_version: String,
+    /// Versions the changelog has entries for, newest first
+    pub versions: Vec<String>,
+    /// The matching changelog sections concatenated and rendered from markdown to sanitized HTML,
+    /// `None` if the package has no changelog
+    pub changelog_html: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MarkdownResponse {
+    markdown: String,
+}
+
+/// Only lets through elements, attributes and URL schemes known to be harmless
+static HTML_SANITIZER: LazyLock<ammonia::Builder<'static>> = LazyLock::new(|| {
+    let mut builder = ammonia::Builder::default();
+    // Commonly used to center banners and badges in READMEs
+    builder.add_generic_attributes(["align"]);
+    builder.url_schemes(HashSet::from(["http", "https", "mailto"]));
+    builder
+});
+
+static VERSION_HEADING: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^#{1,4}\s*\[?v?(\d+\.\d+\.\d+(?:-[0-9A-Za-z.-]+)?)\]?").unwrap());
+
+/// Renders markdown to HTML that is safe to show in the webview
+/// Raw HTML in the markdown is kept as far as it passes the allowlist
+fn render_markdown(markdown: &str) -> String {
+    let options = pulldown_cmark::Options::ENABLE_TABLES
+        | pulldown_cmark::Options::ENABLE_STRIKETHROUGH
+        | pulldown_cmark::Options::ENABLE_TASKLISTS;
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(
+        &mut html,
+        pulldown_cmark::Parser::new_ext(markdown, options),
+    );
+    HTML_SANITIZER.clean(&html).to_string()
+}
+
+/// Splits a changelog into `(version, section)` pairs, sections start with a heading naming a
+/// version such as `## v1.2.0` or `### [1.2.0] - 2024-01-01`
+fn split_changelog(changelog: &str) -> Vec<(semver::Version, String)> {
+    let mut sections: Vec<(semver::Version, String)> = vec![];
+    for line in changelog.lines() {
+        let heading_version = VERSION_HEADING
+            .captures(line.trim_start())
+            .and_then(|captures| semver::Version::parse(&captures[1]).ok());
+        match (heading_version, sections.last_mut()) {
+            (Some(version), _) => sections.push((version, format!("{line}\n"))),
+            (None, Some((_, section))) => {
+                section.push_str(line);
+                section.push('\n');
+            }
+            // Text before the first version heading
+            (None, None) => {}
+        }
+    }
+    sections
+}
+
+/// Changelog sections newer than `installed` up to and including `latest`, newest first
+fn changes_between(
+    changelog: &str,
+    installed: &semver::Version,
+    latest: &semver::Version,
+) -> Vec<(semver::Version, String)> {
+    let mut sections: Vec<(semver::Version, String)> = split_changelog(changelog)
+        .into_iter()
+        .filter(|(version, _)| version > installed && version <= latest)
+        .collect();
+    sections.sort_by(|(a, _), (b, _)| b.cmp(a));
+    sections
+}
+
+fn read_installed_document(
+    game_install: &GameInstall,
+    thunderstore_mod_string: &str,
+    file_name: &str,
+) -> Option<String> {
+    let path = std::path::Path::new(&game_install.game_path)
+        .join(&game_install.profile)
+        .join("packages")
+        .join(thunderstore_mod_string)
+        .join(file_name);
+    std::fs::read_to_string(path).ok()
+}
+
+/// Fetches the `readme` or `changelog` of a package version, `None` if the package has none
+async fn fetch_document(
+    package: &ParsedThunderstoreModString,
+    document: &str,
+) -> Result<Option<String>, String> {
+    let url = format!(
+        "{PACKAGE_API_URL}/{}/{}/{}/{document}/",
+        package.author_name, package.mod_name, package.version
+    );
+    // Bounded like index fetches so a stalled request doesn't block the docs forever
+    let client = reqwest::Client::builder()
+        .connect_timeout(index::CONNECT_TIMEOUT)
+        .timeout(index::FETCH_TIMEOUT)
+        .build()
+        .map_err(|err| format!("Couldn't fetch {document} from Thunderstore: {err}"))?;
+    let response = client
+        .get(url)
+        .header(reqwest::header::USER_AGENT, APP_USER_AGENT)
+        .send()
+        .await
+        .map_err(|err| format!("Couldn't fetch {document} from Thunderstore: {err}"))?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+
+    let response: MarkdownResponse = response
+        .error_for_status()
+        .map_err(|err| format!("Couldn't fetch {document} from Thunderstore: {err}"))?
+        .json()
+        .await
+        .map_err(|err| format!("Failed parsing {document}: {err}"))?;
+    Ok(Some(response.markdown))
+}
+
+/// Returns README and changelog of the given package version (`author-name-version`)
+/// The installed package is used if it exists in the given install
+#[tauri::command]
+pub async fn get_package_docs(
+    game_install: Option<GameInstall>,
+    thunderstore_mod_string: String,
+) -> Result<PackageDocs, String> {
+    let package: ParsedThunderstoreModString = thunderstore_mod_string
+        .parse()
+        .map_err(|_| format!("Invalid mod string {thunderstore_mod_string}"))?;
+
+    if let Some(game_install) = &game_install {
+        if let Some(readme) =
+            read_installed_document(game_install, &thunderstore_mod_string, "README.md")
+        {
+            let changelog =
+                read_installed_document(game_install, &thunderstore_mod_string, "CHANGELOG.md");
+            return Ok(PackageDocs {
+                thunderstore_mod_string,
+                readme_html: Some(render_markdown(&readme)),
+                changelog_html: changelog.as_deref().map(render_markdown),
+                source: PackageDocsSource::Installed,
+            });
+        }
+    }
+
+    let readme = fetch_document(&package, "readme").await?;
+    let changelog = fetch_document(&package, "changelog").await?;
+    Ok(PackageDocs {
+        thunderstore_mod_string,
+        readme_html: readme.as_deref().map(render_markdown),
+        changelog_html: changelog.as_deref().map(render_markdown),
+        source: PackageDocsSource::Thunderstore,
+    })
+}
+
+/// Returns the changelog entries added since the installed version (`author-name-version`)
+#[tauri::command]
+pub async fn get_package_changes_since_installed(
+    thunderstore_mod_string: String,
+) -> Result<PackageChangesSinceInstalled, String> {
+    let installed: ParsedThunderstoreModString = thunderstore_mod_string
+        .parse()
+        .map_err(|_| format!("Invalid mod string {thunderstore_mod_string}"))?;
+    let installed_version = semver::Version::parse(&installed.version)
+        .map_err(|err| format!("Invalid version {}: {err}", installed.version))?;
+
+    let full_name = format!("{}-{}", installed.author_name, installed.mod_name);
+    let packages = index::get_packages().await?;
+    let Some(latest) = packages
+        .iter()
+        .find(|package| package.full_name == full_name)
+        .and_then(|package| package.versions.first())
+    else {
+        return Err(format!("Couldn't find {full_name} on Thunderstore"));
+    };
+    let latest_version = semver::Version::parse(&latest.version_number)
+        .map_err(|err| format!("Invalid version {}: {err}", latest.version_number))?;
+
+    let mut changes = PackageChangesSinceInstalled {
+        installed_version: installed_version.to_string(),
+        latest_version: latest_version.to_string(),
+        versions: vec![],
+        changelog_html: None,
+    };
+    if latest_version <= installed_version {
+        return Ok(changes);
+    }
+
+    let latest_package: ParsedThunderstoreModString = latest
+        .full_name
+        .parse()
+        .map_err(|_| format!("Invalid mod string {}", latest.full_name))?;
+    let Some(changelog) = fetch_document(&latest_package, "changelog").await? else {
+        return Ok(changes);
+    };
+
+    let sections = changes_between(&changelog, &installed_version, &latest_version);
+    changes.versions = sections
+        .iter()
+        .map(|(version, _)| version.to_string())
+        .collect();
+    changes.changelog_html = Some(render_markdown(
+        &sections
+            .into_iter()
+            .map(|(_, section)| section)
+            .collect::<Vec<String>>()
+            .join("\n"),
+    ));
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_markdown() {
+        let markdown = r#"# Title
+<p align="center"><img src="banner.png" onerror="alert(1)"></p>
+
+Some **text** and a [link](https://northstar.tf)"#;
+        assert_eq!(
+            render_markdown(markdown),
+            r#"<h1>Title</h1>
+<p align="center"><img src="banner.png"></p>
+<p>Some <strong>text</strong> and a <a href="https://northstar.tf" rel="noopener noreferrer">link</a></p>
+"#
+        );
+
+        let hostile = [
+            r#"<script>alert("hi")</script>"#,
+            "<svg/onload=alert(1)>",
+            "[click](javascript&#58;alert(1))",
+            r#"<a href="javascript&#58;alert(1)">click</a>"#,
+            r#"<meta http-equiv="refresh" content="0;url=https://example.com">"#,
+            r#"<base href="https://example.com/">"#,
+            r#"<link rel="stylesheet" href="https://example.com/style.css">"#,
+            r#"<iframe src="https://example.com"></iframe>"#,
+        ];
+        for markdown in hostile {
+            let html = render_markdown(markdown).to_lowercase();
+            // Escaped text is fine, only actual elements and attributes are a problem
+            for needle in [
+                "<script",
+                "<svg",
+                " onload",
+                "href=\"javascript",
+                "<meta",
+                "<base",
+                "<link",
+                "<iframe",
+            ] {
+                assert!(!html.contains(needle), "{markdown} rendered to {html}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_changes_between() {
+        let changelog = "# Changelog
+
+## v1.3.0
+- Added more
+
+### [1.2.1] - 2024-02-01
+- Fixed a bug
+
+## 1.2.0
+- Initial features
+";
+        let sections = changes_between(
+            changelog,
+            &semver::Version::new(1, 2, 0),
+            &semver::Version::new(1, 3, 0),
+        );
+
+        assert_eq!(
+            sections
+                .iter()
+                .map(|(version, _)| version.to_string())
+                .collect::<Vec<String>>(),
+            vec!["1.3.0", "1.2.1"]
+        );
+        assert_eq!(sections[1].1, "### [1.2.1] - 2024-02-01\n- Fixed a bug\n\n");
+    }
+}
@@ -0,0 +1,353 @@
+//! Searching the Thunderstore package list in the backend
+//!
+//! Searches run against the shared in-memory index and only send the requested page of
+//! packages to the frontend instead of the whole list.
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::constants::BLACKLISTED_MODS;
+use crate::mod_management::get_installed_mods_and_properties;
+use crate::thunderstore::{index, ThunderstoreMod};
+use crate::util::is_update_available;
+use crate::GameInstall;
+
+/// Page size used if none is given
+const DEFAULT_PAGE_SIZE: usize = 24;
+
+/// Upper limit for the page size to keep responses small
+const MAX_PAGE_SIZE: usize = 100;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, TS)]
+#[ts(export)]
+pub enum ThunderstoreSortField {
+    Name,
+    #[default]
+    Updated,
+    Downloads,
+    Rating,
+}
+
+/// Filters, sorting and page of a package search
+/// Unset fields do not filter
+#[derive(Serialize, Deserialize, Debug, Clone, Default, TS)]
+#[ts(export)]
+#[serde(default)]
+pub struct ThunderstoreSearchQuery {
+    /// Fuzzy matched against name and owner, substring matched against the description
+    pub search: Option<String>,
+    /// Packages need to have all of these categories
+    pub categories: Vec<String>,
+    pub author: Option<String>,
+    pub include_deprecated: bool,
+    pub include_nsfw: bool,
+    /// Only packages installed in the given install
+    pub only_installed: bool,
+    /// Only installed packages with a newer version available
+    pub only_with_update: bool,
+    pub sort_by: ThunderstoreSortField,
+    pub sort_descending: bool,
+    /// Zero-based page index
+    pub page: usize,
+    pub page_size: Option<usize>,
+}
+
+/// A package as returned by a search, only containing its latest version
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct ThunderstoreSearchEntry {
+    pub package: ThunderstoreMod,
+    /// Downloads summed up over all versions
+    pub total_downloads: i64,
+    /// Installed version if the package is installed in the given install
+    pub installed_version: Option<String>,
+    pub has_update: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+pub struct ThunderstoreSearchResult {
+    pub entries: Vec<ThunderstoreSearchEntry>,
+    /// Number of packages matching the query over all pages
+    pub total: usize,
+    pub page: usize,
+    pub page_size: usize,
+}
+
+/// Checks whether all characters of `search` appear in `text` in order, ignoring case
+/// Same matching as the `fuzzy_filter` of the frontend
+fn fuzzy_match(text: &str, search: &str) -> bool {
+    let text = text.to_lowercase();
+    let mut remaining = text.chars();
+    search
+        .to_lowercase()
+        .chars()
+        .all(|c| remaining.by_ref().any(|text_char| text_char == c))
+}
+
+/// Strips off the version of a Thunderstore mod string (`author-name-version`)
+fn package_prefix(mod_string: &str) -> &str {
+    mod_string
+        .rsplit_once('-')
+        .map_or(mod_string, |(prefix, _)| prefix)
+}
+
+impl ThunderstoreSearchQuery {
+    fn page_size(&self) -> usize {
+        self.page_size
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE)
+    }
+
+    fn matches(&self, package: &ThunderstoreMod, installed_version: Option<&str>) -> bool {
+        if let Some(search) = &self.search {
+            let description = package
+                .versions
+                .first()
+                .map(|version| version.description.to_lowercase())
+                .unwrap_or_default();
+            if !fuzzy_match(&package.name, search)
+                && !fuzzy_match(&package.owner, search)
+                && !description.contains(&search.to_lowercase())
+            {
+                return false;
+            }
+        }
+        if let Some(author) = &self.author {
+            if !package.owner.eq_ignore_ascii_case(author) {
+                return false;
+            }
+        }
+        if !self
+            .categories
+            .iter()
+            .all(|category| package.categories.contains(category))
+        {
+            return false;
+        }
+
+        let has_update = match (installed_version, package.versions.first()) {
+            (Some(installed_version), Some(latest)) => {
+                is_update_available(installed_version, &latest.version_number)
+            }
+            _ => false,
+        };
+        !(BLACKLISTED_MODS.contains(&package.full_name.as_str())
+            || package.is_deprecated && !self.include_deprecated
+            || package.has_nsfw_content && !self.include_nsfw
+            || self.only_installed && installed_version.is_none()
+            || self.only_with_update && !has_update)
+    }
+
+    /// Filters, sorts and paginates the given packages
+    /// `installed` holds the Thunderstore mod strings of the installed packages
+    fn apply(
+        &self,
+        packages: &[ThunderstoreMod],
+        installed: &[String],
+    ) -> ThunderstoreSearchResult {
+        let mut entries: Vec<ThunderstoreSearchEntry> = packages
+            .iter()
+            .filter_map(|package| {
+                let latest = package.versions.first()?;
+                let installed_version = installed
+                    .iter()
+                    .find(|mod_string| package_prefix(mod_string) == package.full_name)
+                    .and_then(|mod_string| mod_string.rsplit_once('-'))
+                    .map(|(_, version)| version.to_string());
+                if !self.matches(package, installed_version.as_deref()) {
+                    return None;
+                }
+
+                Some(ThunderstoreSearchEntry {
+                    total_downloads: package
+                        .versions
+                        .iter()
+                        .map(|version| version.downloads as i64)
+                        .sum(),
+                    has_update: installed_version.as_ref().is_some_and(|version| {
+                        is_update_available(version, &latest.version_number)
+                    }),
+                    installed_version,
+                    package: ThunderstoreMod {
+                        versions: vec![latest.clone()],
+                        ..package.clone()
+                    },
+                })
+            })
+            .collect();
+
+        entries.sort_by(|a, b| {
+            let ordering = match self.sort_by {
+                ThunderstoreSortField::Name => a
+                    .package
+                    .name
+                    .to_lowercase()
+                    .cmp(&b.package.name.to_lowercase()),
+                ThunderstoreSortField::Updated => {
+                    a.package.date_updated.cmp(&b.package.date_updated)
+                }
+                ThunderstoreSortField::Downloads => a.total_downloads.cmp(&b.total_downloads),
+                ThunderstoreSortField::Rating => {
+                    a.package.rating_score.cmp(&b.package.rating_score)
+                }
+            };
+            if self.sort_descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+
+        let total = entries.len();
+        let page_size = self.page_size();
+        let entries = entries
+            .into_iter()
+            .skip(self.page.saturating_mul(page_size))
+            .take(page_size)
+            .collect();
+
+        ThunderstoreSearchResult {
+            entries,
+            total,
+            page: self.page,
+            page_size,
+        }
+    }
+}
+
+/// Searches the Thunderstore package list
+/// `game_install` is needed to tell installed packages apart and for the installed/update filters
+#[tauri::command]
+pub async fn search_thunderstore_packages(
+    query: ThunderstoreSearchQuery,
+    game_install: Option<GameInstall>,
+) -> Result<ThunderstoreSearchResult, String> {
+    let packages = index::get_packages().await?;
+
+    let installed: Vec<String> = match game_install {
+        Some(game_install) => get_installed_mods_and_properties(game_install)?
+            .into_iter()
+            .filter_map(|installed_mod| installed_mod.thunderstore_mod_string)
+            .collect(),
+        None if query.only_installed || query.only_with_update => {
+            return Err("Filtering installed packages requires a game install".to_string())
+        }
+        None => vec![],
+    };
+
+    Ok(query.apply(&packages, &installed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::thunderstore::test_package;
+
+    /// Package with the given downloads per version and categories
+    fn package(
+        owner: &str,
+        name: &str,
+        versions: &[(&str, i32)],
+        categories: &[&str],
+    ) -> ThunderstoreMod {
+        let version_numbers: Vec<&str> = versions.iter().map(|(version, _)| *version).collect();
+        let mut package = test_package(owner, name, &version_numbers);
+        package.categories = categories
+            .iter()
+            .map(|category| category.to_string())
+            .collect();
+        for (version, (_, downloads)) in package.versions.iter_mut().zip(versions) {
+            version.downloads = *downloads;
+        }
+        package
+    }
+
+    fn names(result: &ThunderstoreSearchResult) -> Vec<&str> {
+        result
+            .entries
+            .iter()
+            .map(|entry| entry.package.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_fuzzy_match() {
+        assert!(fuzzy_match("Gecko", "geo"));
+        assert!(!fuzzy_match("Gecko", "goe"));
+        assert!(fuzzy_match("anything", ""));
+    }
+
+    #[test]
+    fn test_search_packages() {
+        let mut deprecated = package("Old", "Legacy", &[("1.0.0", 500)], &["Mods"]);
+        deprecated.is_deprecated = true;
+        let packages = vec![
+            package(
+                "Fifty",
+                "Frostbite",
+                &[("1.1.0", 10), ("1.0.0", 90)],
+                &["Mods", "Maps"],
+            ),
+            package("Someone", "Parkour", &[("0.3.1", 50)], &["Mods"]),
+            deprecated,
+        ];
+        let installed = vec!["Fifty-Frostbite-1.0.0".to_string()];
+
+        let query = ThunderstoreSearchQuery {
+            sort_by: ThunderstoreSortField::Downloads,
+            sort_descending: true,
+            ..Default::default()
+        };
+        let result = query.apply(&packages, &installed);
+        assert_eq!(names(&result), vec!["Frostbite", "Parkour"]);
+        assert_eq!(result.entries[0].total_downloads, 100);
+        assert_eq!(result.entries[0].package.versions.len(), 1);
+        assert_eq!(
+            result.entries[0].installed_version.as_deref(),
+            Some("1.0.0")
+        );
+        assert!(result.entries[0].has_update);
+
+        let query = ThunderstoreSearchQuery {
+            categories: vec!["Maps".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            names(&query.apply(&packages, &installed)),
+            vec!["Frostbite"]
+        );
+
+        let query = ThunderstoreSearchQuery {
+            search: Some("pkr".to_string()),
+            include_deprecated: true,
+            ..Default::default()
+        };
+        assert_eq!(names(&query.apply(&packages, &installed)), vec!["Parkour"]);
+
+        let query = ThunderstoreSearchQuery {
+            only_with_update: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            names(&query.apply(&packages, &installed)),
+            vec!["Frostbite"]
+        );
+
+        let query = ThunderstoreSearchQuery {
+            include_deprecated: true,
+            sort_by: ThunderstoreSortField::Name,
+            page: 1,
+            page_size: Some(2),
+            ..Default::default()
+        };
+        let result = query.apply(&packages, &installed);
+        assert_eq!(result.total, 3);
+        assert_eq!(names(&result), vec!["Parkour"]);
+
+        let query = ThunderstoreSearchQuery {
+            page: usize::MAX,
+            ..Default::default()
+        };
+        assert!(query.apply(&packages, &installed).entries.is_empty());
+    }
+}
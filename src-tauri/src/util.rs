@@ -1,16 +1,13 @@
 //! This module contains various utility/helper functions that do not fit into any other module
 
-use anyhow::{Context, Result};
-use serde::{Deserialize, Serialize};
-use zip::ZipArchive;
+use anyhow::Result;
+use serde::{de::DeserializeOwned, Serialize};
+use tauri::Manager;
+use tauri_plugin_store::StoreExt;
 
-use crate::constants::{APP_USER_AGENT, MASTER_SERVER_URL, SERVER_BROWSER_ENDPOINT};
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct NorthstarServer {
-    #[serde(rename = "playerCount")]
-    pub player_count: i32,
-}
+use crate::constants::FLIGHTCORE_SETTINGS_STORE;
+use crate::northstar::LaunchedNorthstar;
+use crate::process_monitor::ProcessMonitor;
 
 /// This function's only use is to force a `panic!()`
 // This must NOT be async to ensure crashing whole application.
@@ -68,126 +65,50 @@ pub async fn close_application<R: tauri::Runtime>(app: tauri::AppHandle<R>) -> R
     Ok(())
 }
 
-/// Fetches `/client/servers` endpoint from master server
-async fn fetch_server_list() -> Result<String, anyhow::Error> {
-    let url = format!("{MASTER_SERVER_URL}{SERVER_BROWSER_ENDPOINT}");
-    let client = reqwest::Client::new();
-    let res = client
-        .get(url)
-        .header(reqwest::header::USER_AGENT, APP_USER_AGENT)
-        .send()
-        .await?
-        .text()
-        .await?;
-
-    Ok(res)
-}
-
-/// Gets server and playercount from master server API
-#[tauri::command]
-pub async fn get_server_player_count() -> Result<(i32, usize), String> {
-    let res = match fetch_server_list().await {
-        Ok(res) => res,
-        Err(err) => return Err(err.to_string()),
-    };
-
-    let ns_servers: Vec<NorthstarServer> =
-        serde_json::from_str(&res).expect("JSON was not well-formatted");
-
-    // Get server count
-    let server_count = ns_servers.len();
-
-    // Sum up player count
-    let total_player_count: i32 = ns_servers.iter().map(|server| server.player_count).sum();
-
-    log::info!("total_player_count: {}", total_player_count);
-    log::info!("server_count:       {}", server_count);
-
-    Ok((total_player_count, server_count))
-}
-
 #[tauri::command]
-pub async fn kill_northstar() -> Result<(), String> {
-    if !check_northstar_running() {
-        return Err("Northstar is not running".to_string());
-    }
-
-    let s = sysinfo::System::new_all();
-
-    for process in s.processes_by_exact_name("Titanfall2.exe".as_ref()) {
-        log::info!("Killing Process {}", process.pid());
-        process.kill();
+pub async fn kill_northstar(app: tauri::AppHandle) -> Result<(), String> {
+    // Stop the Wine/Proton process FlightCore launched the game in first
+    let mut killed_launched_process = false;
+    let launched_northstar = app.state::<LaunchedNorthstar>();
+    if let Some(mut child) = launched_northstar.0.lock().unwrap().child.take() {
+        if let Ok(None) = child.try_wait() {
+            log::info!("Killing Process {}", child.id());
+            killed_launched_process = child.kill().is_ok();
+            let _ = child.wait();
+        }
     }
 
-    for process in s.processes_by_exact_name("NorthstarLauncher.exe".as_ref()) {
-        log::info!("Killing Process {}", process.pid());
-        process.kill();
+    let killed_processes = app.state::<ProcessMonitor>().kill_northstar();
+    if killed_processes == 0 && !killed_launched_process {
+        return Err("Northstar is not running".to_string());
     }
 
     Ok(())
 }
 
-/// Copied from `papa` source code and modified
-///Extract N* zip file to target game path
-// fn extract(ctx: &Ctx, zip_file: File, target: &Path) -> Result<()> {
-pub fn extract(zip_file: std::fs::File, target: &std::path::Path) -> Result<()> {
-    let mut archive = ZipArchive::new(&zip_file).context("Unable to open zip archive")?;
-    for i in 0..archive.len() {
-        let mut f = archive.by_index(i).unwrap();
-
-        //This should work fine for N* because the dir structure *should* always be the same
-        if f.enclosed_name().unwrap().starts_with("Northstar") {
-            let out = target.join(
-                f.enclosed_name()
-                    .unwrap()
-                    .strip_prefix("Northstar")
-                    .unwrap(),
-            );
-
-            if (*f.name()).ends_with('/') {
-                log::info!("Create directory {}", f.name());
-                std::fs::create_dir_all(target.join(f.name()))
-                    .context("Unable to create directory")?;
-                continue;
-            } else if let Some(p) = out.parent() {
-                std::fs::create_dir_all(p).context("Unable to create directory")?;
-            }
-
-            let mut outfile = std::fs::OpenOptions::new()
-                .create(true)
-                .write(true)
-                .truncate(true)
-                .open(&out)?;
-
-            log::info!("Write file {}", out.display());
-
-            std::io::copy(&mut f, &mut outfile).context("Unable to write to file")?;
-        }
-    }
-
-    Ok(())
-}
+/// Reads a value from the persistent settings store
+/// Values are wrapped in `{ value: ... }` the same way the frontend stores them
+pub fn get_stored_value<T: DeserializeOwned>(
+    app: &tauri::AppHandle,
+    key: &str,
+) -> Result<Option<T>> {
+    let store = app.store(FLIGHTCORE_SETTINGS_STORE)?;
+    let Some(stored) = store.get(key) else {
+        return Ok(None);
+    };
 
-pub fn check_ea_app_or_origin_running() -> bool {
-    let s = sysinfo::System::new_all();
-    let x = s.processes_by_name("Origin.exe".as_ref()).next().is_some()
-        || s.processes_by_name("EADesktop.exe".as_ref())
-            .next()
-            .is_some();
-    x
+    match stored.get("value") {
+        Some(value) => Ok(Some(serde_json::from_value(value.clone())?)),
+        None => Ok(None),
+    }
 }
 
-/// Checks if Northstar process is running
-pub fn check_northstar_running() -> bool {
-    let s = sysinfo::System::new_all();
-    let x = s
-        .processes_by_name("NorthstarLauncher.exe".as_ref())
-        .next()
-        .is_some()
-        || s.processes_by_name("Titanfall2.exe".as_ref())
-            .next()
-            .is_some();
-    x
+/// Writes a value to the persistent settings store and saves it to disk
+pub fn set_stored_value<T: Serialize>(app: &tauri::AppHandle, key: &str, value: &T) -> Result<()> {
+    let store = app.store(FLIGHTCORE_SETTINGS_STORE)?;
+    store.set(key, serde_json::json!({ "value": value }));
+    store.save()?;
+    Ok(())
 }
 
 /// Copies a folder and all its contents to a new location
@@ -266,6 +187,18 @@ pub fn convert_release_candidate_number(version_number: String) -> String {
     panic!();
 }
 
+/// Whether `latest` is newer than the installed version
+/// Versions that aren't valid semver are only compared for equality
+pub fn is_update_available(installed: &str, latest: &str) -> bool {
+    match (
+        semver::Version::parse(installed),
+        semver::Version::parse(latest),
+    ) {
+        (Ok(installed), Ok(latest)) => latest > installed,
+        _ => installed != latest,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -323,4 +256,12 @@ mod tests {
 
         assert_eq!(output, expected_output);
     }
+
+    #[test]
+    fn test_is_update_available() {
+        assert!(is_update_available("1.9.0", "1.10.0"));
+        assert!(!is_update_available("1.10.0", "1.9.0"));
+        assert!(!is_update_available("1.10.0", "1.10.0"));
+        assert!(is_update_available("dev", "1.0.0"));
+    }
 }